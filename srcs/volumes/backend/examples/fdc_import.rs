@@ -0,0 +1,400 @@
+//! Import the generic foods of a USDA FoodData Central CSV release into the
+//! shared food catalog.
+//!
+//! Usage: `cargo run --example fdc_import -- <release_dir>`
+//!
+//! The directory holds `food.csv`, `nutrient.csv`, `food_nutrient.csv`,
+//! `food_portion.csv` and, when present, `measure_unit.csv`. Only foundation,
+//! SR legacy and survey foods are imported, branded foods come from
+//! OpenFoodFacts. Foods are upserted by FDC id so newer releases can be
+//! imported again. The database is the one of `SERVICE_DB_URL`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize};
+use sqlx::{postgres::PgPoolOptions, Postgres, Transaction};
+
+const SOURCE: &str = "fdc";
+const BATCH_SIZE: usize = 1_000;
+const KJ_PER_KCAL: f64 = 4.184;
+const NAME_MAX_LEN: usize = 256;
+/// Pure fat is the most energy dense food.
+const MAX_KCAL_100G: f64 = 900.;
+
+const GENERIC_DATA_TYPES: [&str; 3] = ["foundation_food", "sr_legacy_food", "survey_fndds_food"];
+
+// Nutrient ids, by order of preference when a food has several of them.
+/// Energy, then Energy (Atwater General and Specific Factors), then Energy in kJ.
+const ENERGY_IDS: [i64; 4] = [1008, 2047, 2048, 1062];
+/// Carbohydrate by difference, then by summation.
+const CARBS_IDS: [i64; 2] = [1005, 1050];
+const PROTEINS_IDS: [i64; 1] = [1003];
+/// Total lipid (fat), then Total fat (NLEA).
+const LIPIDS_IDS: [i64; 2] = [1004, 1085];
+/// Alcohol, ethyl.
+const ALCOHOL_IDS: [i64; 1] = [1018];
+
+/// FDC measure unit id of the portions without a proper unit.
+const UNDETERMINED_UNIT_ID: i64 = 9999;
+
+// region:    --- Release files
+
+#[derive(Debug, Deserialize)]
+struct FoodRow {
+    fdc_id: i64,
+    data_type: String,
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NutrientRow {
+    id: i64,
+    unit_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FoodNutrientRow {
+    fdc_id: i64,
+    nutrient_id: i64,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    amount: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FoodPortionRow {
+    fdc_id: i64,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    amount: Option<f64>,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    measure_unit_id: Option<i64>,
+    #[serde(default)]
+    portion_description: String,
+    #[serde(default)]
+    modifier: String,
+    #[serde(deserialize_with = "csv::invalid_option")]
+    gram_weight: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MeasureUnitRow {
+    id: i64,
+    name: String,
+}
+
+/// Stream the rows of a release file, rows that don't parse are skipped.
+fn read_rows<T: DeserializeOwned>(dir: &Path, name: &str) -> Result<impl Iterator<Item = T>> {
+    let path = dir.join(name);
+    let reader =
+        csv::Reader::from_path(&path).with_context(|| format!("Cannot open {}", path.display()))?;
+
+    Ok(reader.into_deserialize().filter_map(|row| row.ok()))
+}
+
+// endregion: --- Release files
+
+// region:    --- Units
+
+/// Map an FDC measure to the unit codes used by the food portions.
+fn unit_code(name: &str) -> Option<&'static str> {
+    let code = match name.trim().to_lowercase().as_str() {
+        "cup" | "cups" => "cup",
+        "tablespoon" | "tbsp" => "tbsp",
+        "teaspoon" | "tsp" => "tsp",
+        "fl oz" | "fluid ounce" => "fl_oz",
+        "oz" | "ounce" => "oz",
+        "lb" | "pound" => "lb",
+        "ml" | "milliliter" => "ml",
+        "liter" | "l" => "l",
+        "slice" | "slices" => "slice",
+        "piece" | "pieces" => "piece",
+        "serving" => "serving",
+        "small" | "medium" | "large" | "extra large" => "piece",
+        _ => return None,
+    };
+
+    Some(code)
+}
+
+/// SR legacy portions have an undetermined unit, it is then the first words
+/// of their modifier, e.g. "cup, chopped".
+fn portion_unit(row: &FoodPortionRow, units: &HashMap<i64, String>) -> &'static str {
+    let measure = row
+        .measure_unit_id
+        .filter(|id| *id != UNDETERMINED_UNIT_ID)
+        .and_then(|id| units.get(&id))
+        .and_then(|name| unit_code(name));
+
+    measure
+        .or_else(|| {
+            [&row.modifier, &row.portion_description]
+                .into_iter()
+                .find_map(|text| {
+                    let head = text.split([',', '(']).next()?;
+                    unit_code(head)
+                })
+        })
+        .unwrap_or("portion")
+}
+
+// endregion: --- Units
+
+#[derive(Debug, Default)]
+struct FoodNutrients {
+    /// Amounts by nutrient id.
+    amounts: HashMap<i64, f64>,
+}
+
+impl FoodNutrients {
+    fn first(&self, ids: &[i64]) -> Option<(i64, f64)> {
+        ids.iter()
+            .find_map(|id| self.amounts.get(id).map(|amount| (*id, *amount)))
+    }
+}
+
+#[derive(Debug)]
+struct ImportedFood {
+    fdc_id: i64,
+    name: String,
+    kcal_100g: f32,
+    carbs_100g: f32,
+    proteins_100g: f32,
+    lipids_100g: f32,
+    alcohol_100g: f32,
+}
+
+#[derive(Debug)]
+struct ImportedPortion {
+    unit: &'static str,
+    amount: f32,
+    description: Option<String>,
+    gram_weight: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Skip {
+    MissingNutrition,
+    ImpossibleNutrition,
+}
+
+/// FDC amounts are given per 100 g.
+fn normalize(
+    fdc_id: i64,
+    name: &str,
+    nutrients: &FoodNutrients,
+    units: &HashMap<i64, String>,
+) -> Result<ImportedFood, Skip> {
+    let kcal =
+        nutrients
+            .first(&ENERGY_IDS)
+            .map(|(id, amount)| match units.get(&id).map(String::as_str) {
+                Some("kJ") => amount / KJ_PER_KCAL,
+                _ => amount,
+            });
+    let (Some(kcal), Some((_, proteins)), Some((_, lipids))) = (
+        kcal,
+        nutrients.first(&PROTEINS_IDS),
+        nutrients.first(&LIPIDS_IDS),
+    ) else {
+        return Err(Skip::MissingNutrition);
+    };
+    // Carbohydrates are left out of foods that have none, like meats.
+    let carbs = nutrients
+        .first(&CARBS_IDS)
+        .map(|(_, amount)| amount)
+        .unwrap_or_default();
+    let alcohol = nutrients
+        .first(&ALCOHOL_IDS)
+        .map(|(_, amount)| amount)
+        .unwrap_or_default();
+
+    let macros = [carbs, proteins, lipids, alcohol];
+    if !(0. ..=MAX_KCAL_100G).contains(&kcal)
+        || macros.iter().any(|v| !(0. ..=100.).contains(v))
+        || macros.iter().sum::<f64>() > 100.
+    {
+        return Err(Skip::ImpossibleNutrition);
+    }
+
+    Ok(ImportedFood {
+        fdc_id,
+        name: name.trim().chars().take(NAME_MAX_LEN).collect(),
+        kcal_100g: kcal as f32,
+        carbs_100g: carbs as f32,
+        proteins_100g: proteins as f32,
+        lipids_100g: lipids as f32,
+        alcohol_100g: alcohol as f32,
+    })
+}
+
+async fn upsert_food(
+    tx: &mut Transaction<'_, Postgres>,
+    food: &ImportedFood,
+    portions: &[ImportedPortion],
+) -> Result<()> {
+    let (food_id,) = sqlx::query_as::<_, (i64,)>(
+        "INSERT INTO food (name, kcal_100g, carbs_100g, proteins_100g, lipids_100g, alcohol_100g,
+            source, source_ref)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (source, source_ref) DO UPDATE SET name = EXCLUDED.name,
+            kcal_100g = EXCLUDED.kcal_100g, carbs_100g = EXCLUDED.carbs_100g,
+            proteins_100g = EXCLUDED.proteins_100g, lipids_100g = EXCLUDED.lipids_100g,
+            alcohol_100g = EXCLUDED.alcohol_100g
+        RETURNING id",
+    )
+    .bind(&food.name)
+    .bind(food.kcal_100g)
+    .bind(food.carbs_100g)
+    .bind(food.proteins_100g)
+    .bind(food.lipids_100g)
+    .bind(food.alcohol_100g)
+    .bind(SOURCE)
+    .bind(food.fdc_id.to_string())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Portions are replaced as a whole, a release can drop some of them.
+    sqlx::query("DELETE FROM food_portion WHERE food_id = $1")
+        .bind(food_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for portion in portions {
+        sqlx::query(
+            "INSERT INTO food_portion (food_id, unit, amount, description, gram_weight)
+            VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(food_id)
+        .bind(portion.unit)
+        .bind(portion.amount)
+        .bind(&portion.description)
+        .bind(portion.gram_weight)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let Some(dir) = env::args().nth(1).map(PathBuf::from) else {
+        bail!("Usage: fdc_import <release_dir>");
+    };
+    let db_url = env::var("SERVICE_DB_URL").context("SERVICE_DB_URL is not set")?;
+
+    let db = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&db_url)
+        .await?;
+
+    let foods: HashMap<i64, String> = read_rows::<FoodRow>(&dir, "food.csv")?
+        .filter(|row| GENERIC_DATA_TYPES.contains(&row.data_type.as_str()))
+        .map(|row| (row.fdc_id, row.description))
+        .collect();
+    println!("{} generic foods in the release", foods.len());
+
+    let nutrient_units: HashMap<i64, String> = read_rows::<NutrientRow>(&dir, "nutrient.csv")?
+        .map(|row| (row.id, row.unit_name))
+        .collect();
+
+    let wanted: HashSet<i64> = [
+        &ENERGY_IDS[..],
+        &CARBS_IDS,
+        &PROTEINS_IDS,
+        &LIPIDS_IDS,
+        &ALCOHOL_IDS,
+    ]
+    .concat()
+    .into_iter()
+    .collect();
+    let mut nutrients: HashMap<i64, FoodNutrients> = HashMap::new();
+    for row in read_rows::<FoodNutrientRow>(&dir, "food_nutrient.csv")? {
+        if let (true, true, Some(amount)) = (
+            foods.contains_key(&row.fdc_id),
+            wanted.contains(&row.nutrient_id),
+            row.amount,
+        ) {
+            nutrients
+                .entry(row.fdc_id)
+                .or_default()
+                .amounts
+                .insert(row.nutrient_id, amount);
+        }
+    }
+
+    let measure_units: HashMap<i64, String> = if dir.join("measure_unit.csv").exists() {
+        read_rows::<MeasureUnitRow>(&dir, "measure_unit.csv")?
+            .map(|row| (row.id, row.name))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let mut portions: HashMap<i64, Vec<ImportedPortion>> = HashMap::new();
+    for row in read_rows::<FoodPortionRow>(&dir, "food_portion.csv")? {
+        let Some(gram_weight) = row.gram_weight.filter(|g| g.is_finite() && *g > 0.) else {
+            continue;
+        };
+        if !foods.contains_key(&row.fdc_id) {
+            continue;
+        }
+
+        let description = [&row.portion_description, &row.modifier]
+            .into_iter()
+            .map(|text| text.trim())
+            .find(|text| !text.is_empty())
+            .map(|text| text.chars().take(NAME_MAX_LEN).collect());
+
+        portions
+            .entry(row.fdc_id)
+            .or_default()
+            .push(ImportedPortion {
+                unit: portion_unit(&row, &measure_units),
+                amount: row
+                    .amount
+                    .filter(|a| a.is_finite() && *a > 0.)
+                    .unwrap_or(1.) as f32,
+                description,
+                gram_weight: gram_weight as f32,
+            });
+    }
+
+    let mut imported = 0usize;
+    let mut skipped: HashMap<Skip, usize> = HashMap::new();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+    let empty = FoodNutrients::default();
+    let mut foods = foods.iter().peekable();
+    while let Some((fdc_id, name)) = foods.next() {
+        let food_nutrients = nutrients.get(fdc_id).unwrap_or(&empty);
+        match normalize(*fdc_id, name, food_nutrients, &nutrient_units) {
+            Ok(food) => batch.push(food),
+            Err(skip) => *skipped.entry(skip).or_default() += 1,
+        }
+
+        if batch.len() == BATCH_SIZE || (foods.peek().is_none() && !batch.is_empty()) {
+            let mut tx = db.begin().await?;
+            for food in &batch {
+                let food_portions = portions.get(&food.fdc_id).map(Vec::as_slice);
+                upsert_food(&mut tx, food, food_portions.unwrap_or_default()).await?;
+            }
+            tx.commit().await?;
+
+            imported += batch.len();
+            batch.clear();
+            println!("Imported {imported} foods");
+        }
+    }
+
+    println!("\n Done: {imported} foods imported");
+    for (skip, count) in skipped {
+        println!("  skipped {count} ({skip:?})");
+    }
+
+    Ok(())
+}
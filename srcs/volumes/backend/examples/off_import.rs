@@ -0,0 +1,351 @@
+//! Import an OpenFoodFacts export into the shared food catalog.
+//!
+//! Usage: `cargo run --example off_import -- <export.csv|export.jsonl>`
+//!
+//! The CSV export is tab separated, the JSONL one holds a product per line.
+//! Products are upserted by barcode so newer exports can be imported again.
+//! The database is the one of `SERVICE_DB_URL`.
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::{postgres::PgPoolOptions, Postgres, Transaction};
+
+#[path = "../src/utils/gtin.rs"]
+mod gtin;
+
+const SOURCE: &str = "off";
+const BATCH_SIZE: usize = 1_000;
+const KJ_PER_KCAL: f64 = 4.184;
+const NAME_MAX_LEN: usize = 256;
+/// Pure fat is the most energy dense food.
+const MAX_KCAL_100G: f64 = 900.;
+
+/// A nutrient value, OpenFoodFacts gives it per 100 g, per serving or both.
+#[derive(Debug, Default)]
+struct Nutrient {
+    per_100g: Option<f64>,
+    per_serving: Option<f64>,
+}
+
+impl Nutrient {
+    fn per_100g(&self, serving_g: Option<f64>) -> Option<f64> {
+        self.per_100g
+            .or_else(|| Some(self.per_serving? * 100. / serving_g?))
+            .filter(|v| v.is_finite())
+    }
+}
+
+#[derive(Debug)]
+struct OffProduct {
+    code: String,
+    name: Option<String>,
+    brand: Option<String>,
+    serving_g: Option<f64>,
+    energy_kcal: Nutrient,
+    energy_kj: Nutrient,
+    carbs: Nutrient,
+    proteins: Nutrient,
+    fat: Nutrient,
+}
+
+#[derive(Debug)]
+struct ImportedFood {
+    gtin: String,
+    name: String,
+    brand: Option<String>,
+    serving_g: Option<f32>,
+    kcal_100g: f32,
+    carbs_100g: f32,
+    proteins_100g: f32,
+    lipids_100g: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Skip {
+    Unparsable,
+    InvalidCode,
+    MissingName,
+    MissingNutrition,
+    ImpossibleNutrition,
+}
+
+fn clean_text(value: Option<String>) -> Option<String> {
+    let value = value?;
+    let value = value.trim();
+
+    (!value.is_empty()).then(|| value.chars().take(NAME_MAX_LEN).collect())
+}
+
+impl OffProduct {
+    fn normalize(self) -> Result<ImportedFood, Skip> {
+        let gtin = gtin::normalize_gtin(&self.code).ok_or(Skip::InvalidCode)?;
+        let name = clean_text(self.name).ok_or(Skip::MissingName)?;
+        // Brands are a comma separated list, the first one is the main brand.
+        let brand = clean_text(
+            self.brand
+                .and_then(|b| b.split(',').next().map(str::to_string)),
+        );
+        let serving_g = self.serving_g.filter(|g| g.is_finite() && *g > 0.);
+
+        let kcal = self.energy_kcal.per_100g(serving_g).or_else(|| {
+            self.energy_kj
+                .per_100g(serving_g)
+                .map(|kj| kj / KJ_PER_KCAL)
+        });
+        let (Some(kcal), Some(carbs), Some(proteins), Some(fat)) = (
+            kcal,
+            self.carbs.per_100g(serving_g),
+            self.proteins.per_100g(serving_g),
+            self.fat.per_100g(serving_g),
+        ) else {
+            return Err(Skip::MissingNutrition);
+        };
+
+        let macros = [carbs, proteins, fat];
+        if !(0. ..=MAX_KCAL_100G).contains(&kcal)
+            || macros.iter().any(|v| !(0. ..=100.).contains(v))
+            || macros.iter().sum::<f64>() > 100.
+        {
+            return Err(Skip::ImpossibleNutrition);
+        }
+
+        Ok(ImportedFood {
+            gtin,
+            name,
+            brand,
+            serving_g: serving_g.map(|g| g as f32),
+            kcal_100g: kcal as f32,
+            carbs_100g: carbs as f32,
+            proteins_100g: proteins as f32,
+            lipids_100g: fat as f32,
+        })
+    }
+}
+
+// region:    --- CSV export
+
+#[derive(Debug, Deserialize)]
+struct CsvRow {
+    code: String,
+    product_name: Option<String>,
+    brands: Option<String>,
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    serving_quantity: Option<f64>,
+    #[serde(
+        rename = "energy-kcal_100g",
+        default,
+        deserialize_with = "csv::invalid_option"
+    )]
+    energy_kcal_100g: Option<f64>,
+    #[serde(
+        rename = "energy-kj_100g",
+        default,
+        deserialize_with = "csv::invalid_option"
+    )]
+    energy_kj_100g: Option<f64>,
+    /// Energy in kJ.
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    energy_100g: Option<f64>,
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    carbohydrates_100g: Option<f64>,
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    proteins_100g: Option<f64>,
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    fat_100g: Option<f64>,
+}
+
+impl From<CsvRow> for OffProduct {
+    fn from(row: CsvRow) -> Self {
+        let per_100g = |value| Nutrient {
+            per_100g: value,
+            per_serving: None,
+        };
+
+        OffProduct {
+            code: row.code,
+            name: row.product_name,
+            brand: row.brands,
+            serving_g: row.serving_quantity,
+            energy_kcal: per_100g(row.energy_kcal_100g),
+            energy_kj: per_100g(row.energy_kj_100g.or(row.energy_100g)),
+            carbs: per_100g(row.carbohydrates_100g),
+            proteins: per_100g(row.proteins_100g),
+            fat: per_100g(row.fat_100g),
+        }
+    }
+}
+
+fn read_csv(file: File) -> impl Iterator<Item = Option<OffProduct>> {
+    csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .flexible(true)
+        .from_reader(BufReader::new(file))
+        .into_deserialize::<CsvRow>()
+        .map(|row| row.ok().map(OffProduct::from))
+}
+
+// endregion: --- CSV export
+
+// region:    --- JSONL export
+
+#[derive(Debug, Deserialize)]
+struct JsonProduct {
+    #[serde(default)]
+    code: String,
+    product_name: Option<String>,
+    brands: Option<String>,
+    serving_quantity: Option<Value>,
+    #[serde(default)]
+    nutriments: HashMap<String, Value>,
+}
+
+/// Numbers are sometimes exported as strings.
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+impl From<JsonProduct> for OffProduct {
+    fn from(product: JsonProduct) -> Self {
+        let value = |key: String| product.nutriments.get(&key).and_then(as_number);
+        let nutrient = |name: &str| Nutrient {
+            per_100g: value(format!("{name}_100g")),
+            per_serving: value(format!("{name}_serving")),
+        };
+
+        let energy_kj = match nutrient("energy-kj") {
+            Nutrient {
+                per_100g: None,
+                per_serving: None,
+            } => nutrient("energy"),
+            kj => kj,
+        };
+
+        OffProduct {
+            energy_kcal: nutrient("energy-kcal"),
+            energy_kj,
+            carbs: nutrient("carbohydrates"),
+            proteins: nutrient("proteins"),
+            fat: nutrient("fat"),
+            serving_g: product.serving_quantity.as_ref().and_then(as_number),
+            code: product.code,
+            name: product.product_name,
+            brand: product.brands,
+        }
+    }
+}
+
+fn read_jsonl(file: File) -> impl Iterator<Item = Option<OffProduct>> {
+    BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str::<JsonProduct>(&line)
+                .ok()
+                .map(OffProduct::from)
+        })
+}
+
+// endregion: --- JSONL export
+
+async fn upsert_food(tx: &mut Transaction<'_, Postgres>, food: &ImportedFood) -> Result<()> {
+    let (food_id,) = sqlx::query_as::<_, (i64,)>(
+        "INSERT INTO food (name, kcal_100g, carbs_100g, proteins_100g, lipids_100g, source, source_ref)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (source, source_ref) DO UPDATE SET name = EXCLUDED.name,
+            kcal_100g = EXCLUDED.kcal_100g, carbs_100g = EXCLUDED.carbs_100g,
+            proteins_100g = EXCLUDED.proteins_100g, lipids_100g = EXCLUDED.lipids_100g
+        RETURNING id",
+    )
+    .bind(&food.name)
+    .bind(food.kcal_100g)
+    .bind(food.carbs_100g)
+    .bind(food.proteins_100g)
+    .bind(food.lipids_100g)
+    .bind(SOURCE)
+    .bind(&food.gtin)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO product (gtin, food_id, brand, serving_g) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (gtin) DO UPDATE SET food_id = EXCLUDED.food_id,
+            brand = EXCLUDED.brand, serving_g = EXCLUDED.serving_g",
+    )
+    .bind(&food.gtin)
+    .bind(food_id)
+    .bind(&food.brand)
+    .bind(food.serving_g)
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let Some(path) = env::args().nth(1) else {
+        bail!("Usage: off_import <export.csv|export.jsonl>");
+    };
+    let db_url = env::var("SERVICE_DB_URL").context("SERVICE_DB_URL is not set")?;
+
+    let db = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&db_url)
+        .await?;
+
+    let file = File::open(&path).with_context(|| format!("Cannot open {path}"))?;
+    let products: Box<dyn Iterator<Item = Option<OffProduct>>> =
+        if path.ends_with(".jsonl") || path.ends_with(".json") {
+            Box::new(read_jsonl(file))
+        } else {
+            Box::new(read_csv(file))
+        };
+
+    let mut imported = 0usize;
+    let mut skipped: HashMap<Skip, usize> = HashMap::new();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+    let mut products = products.peekable();
+    while let Some(product) = products.next() {
+        match product
+            .ok_or(Skip::Unparsable)
+            .and_then(OffProduct::normalize)
+        {
+            Ok(food) => batch.push(food),
+            Err(skip) => *skipped.entry(skip).or_default() += 1,
+        }
+
+        if batch.len() == BATCH_SIZE || (products.peek().is_none() && !batch.is_empty()) {
+            let mut tx = db.begin().await?;
+            for food in &batch {
+                upsert_food(&mut tx, food).await?;
+            }
+            tx.commit().await?;
+
+            imported += batch.len();
+            batch.clear();
+            println!("Imported {imported} products");
+        }
+    }
+
+    println!("\n Done: {imported} products imported");
+    for (skip, count) in skipped {
+        println!("  skipped {count} ({skip:?})");
+    }
+
+    Ok(())
+}
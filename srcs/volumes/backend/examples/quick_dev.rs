@@ -1,40 +1,50 @@
-use anyhow::Result;
 use serde_json::json;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let hc = httpc_test::new_client("http://localhost:8080")?;
 
-    hc.do_get("/hello/").await?.print().await?;
-
     let auth_request = hc.do_post(
         "/api/login/",
         json!({
-            "username": "val",
-            "password": "aaa"
+            "username": "demo1",
+            "password": "Welcome"
         }),
     );
     auth_request.await?.print().await?;
 
     let create_req = hc.do_post(
-        "/api/tickets/",
+        "/api/meals/",
         json!({
-            "title": "My first Ticket"
+            "name": "My first meal",
+            "kcal": 520,
+            "carbs": 60,
+            "proteins": 30,
+            "lipids": 17
         }),
     );
     create_req.await?.print().await?;
 
     let create_req = hc.do_post(
-        "/api/tickets/",
+        "/api/meals/",
         json!({
-            "title": "My second Ticket"
+            "name": "My second meal",
+            "kcal": 310,
+            "carbs": 20,
+            "proteins": 25,
+            "lipids": 14
         }),
     );
     create_req.await?.print().await?;
-    hc.do_get("/api/tickets/").await?.print().await?;
+    hc.do_get("/api/meals/").await?.print().await?;
+
+    hc.do_patch("/api/meals/1000", json!({ "kcal": 540 }))
+        .await?
+        .print()
+        .await?;
 
-    hc.do_delete("/api/tickets/1").await?.print().await?;
-    hc.do_delete("/api/tickets/1").await?.print().await?;
-    hc.do_get("/api/tickets/").await?.print().await?;
+    hc.do_delete("/api/meals/1000").await?.print().await?;
+    hc.do_delete("/api/meals/1000").await?.print().await?;
+    hc.do_get("/api/meals/").await?.print().await?;
     Ok(())
 }
@@ -1,3 +1,6 @@
+-- Trigram matching for the fuzzy search
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
 -- User table
 CREATE TABLE "user" (
   id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
@@ -13,7 +16,31 @@ CREATE TABLE public_user (
   owner BIGINT UNIQUE NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
   age INT NOT NULL,
   size_cm INT NOT NULL,
-  weight REAL NOT NULL
+  weight REAL NOT NULL,
+  -- Inputs of the energy expenditure, see model::energy
+  sex VARCHAR(16) NOT NULL DEFAULT 'unspecified',
+  activity_level VARCHAR(16) NOT NULL DEFAULT 'sedentary',
+  body_fat_pct REAL CHECK (body_fat_pct > 0 AND body_fat_pct < 100)
+);
+
+-- Weight history, public_user.weight mirrors the latest entry
+CREATE TABLE weight_log (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  owner BIGINT NOT NULL REFERENCES public_user(owner) ON DELETE CASCADE,
+  weight_kg REAL NOT NULL CHECK (weight_kg > 0),
+  logged_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX weight_log_owner_logged_at_idx ON weight_log (owner, logged_at);
+
+-- Weight goal of a user, the daily targets are derived from the profile
+CREATE TABLE goal (
+  owner BIGINT PRIMARY KEY REFERENCES public_user(owner) ON DELETE CASCADE,
+  target_weight_kg REAL NOT NULL CHECK (target_weight_kg > 0),
+  weekly_rate_kg REAL NOT NULL CHECK (weekly_rate_kg >= 0),
+  -- See model::goal::MacroSplit
+  macro_split JSONB NOT NULL,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
 );
 
 -- tasks table
@@ -24,5 +51,160 @@ CREATE TABLE meal (
   carbs INT NOT NULL,
   lipids INT NOT NULL,
   proteins INT NOT NULL,
-  owner BIGINT NOT NULL REFERENCES public_user(owner)
+  owner BIGINT NOT NULL REFERENCES public_user(owner),
+  consumed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  slot VARCHAR(64) NOT NULL DEFAULT 'snack',
+  -- Extended nutrients by code, see model::nutrient
+  nutrients JSONB NOT NULL DEFAULT '{}',
+  -- Volume of beverages, counted toward hydration
+  volume_ml REAL CHECK (volume_ml >= 0),
+  -- User labels, lowercased, see model::tag
+  tags TEXT[] NOT NULL DEFAULT '{}',
+  notes TEXT,
+  -- Set when moved to the trash, purged after the retention period
+  deleted_at TIMESTAMPTZ
+);
+
+CREATE INDEX meal_owner_consumed_at_idx ON meal (owner, consumed_at);
+CREATE INDEX meal_name_trgm_idx ON meal USING GIN (name gin_trgm_ops);
+CREATE INDEX meal_tags_idx ON meal USING GIN (tags);
+CREATE INDEX meal_deleted_at_idx ON meal (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Meals starred by their owner for quick re-logging
+CREATE TABLE favorite_meal (
+  owner BIGINT NOT NULL REFERENCES public_user(owner) ON DELETE CASCADE,
+  meal_id BIGINT NOT NULL REFERENCES meal(id) ON DELETE CASCADE,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (owner, meal_id)
+);
+
+-- Photos of a meal, the files live in the photo folder under owner/meal_id
+CREATE TABLE meal_photo (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  meal_id BIGINT NOT NULL REFERENCES meal(id) ON DELETE CASCADE,
+  owner BIGINT NOT NULL REFERENCES public_user(owner) ON DELETE CASCADE,
+  content_type VARCHAR(32) NOT NULL,
+  size_bytes INT NOT NULL CHECK (size_bytes > 0),
+  width INT NOT NULL CHECK (width > 0),
+  height INT NOT NULL CHECK (height > 0),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
 );
+
+CREATE INDEX meal_photo_meal_id_idx ON meal_photo (meal_id);
+
+-- Water drunk, beverages with energy are logged as meals with a volume
+CREATE TABLE water_log (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  owner BIGINT NOT NULL REFERENCES public_user(owner) ON DELETE CASCADE,
+  volume_ml REAL NOT NULL CHECK (volume_ml > 0),
+  logged_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX water_log_owner_logged_at_idx ON water_log (owner, logged_at);
+
+-- Food catalog, nutrition per 100 g (owner NULL for shared entries)
+CREATE TABLE food (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  owner BIGINT REFERENCES public_user(owner) ON DELETE CASCADE,
+  name VARCHAR(256) NOT NULL,
+  kcal_100g REAL NOT NULL CHECK (kcal_100g >= 0),
+  carbs_100g REAL NOT NULL CHECK (carbs_100g >= 0),
+  proteins_100g REAL NOT NULL CHECK (proteins_100g >= 0),
+  lipids_100g REAL NOT NULL CHECK (lipids_100g >= 0),
+  alcohol_100g REAL NOT NULL DEFAULT 0 CHECK (alcohol_100g >= 0),
+  -- Grams per millilitre, to convert volumes without a volume portion
+  density_g_ml REAL CHECK (density_g_ml > 0),
+  -- Origin of imported entries, so re-imports can upsert them
+  source VARCHAR(32),
+  source_ref VARCHAR(64),
+  UNIQUE (source, source_ref)
+);
+
+CREATE INDEX food_name_trgm_idx ON food USING GIN (name gin_trgm_ops);
+
+-- Household measures of a food, e.g. 1 cup weighing 158 g
+CREATE TABLE food_portion (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  food_id BIGINT NOT NULL REFERENCES food(id) ON DELETE CASCADE,
+  unit VARCHAR(64) NOT NULL,
+  amount REAL NOT NULL CHECK (amount > 0),
+  description VARCHAR(256),
+  gram_weight REAL NOT NULL CHECK (gram_weight > 0)
+);
+
+CREATE INDEX food_portion_food_id_idx ON food_portion (food_id);
+
+-- Meal composition, one row per food eaten
+CREATE TABLE meal_entry (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  meal_id BIGINT NOT NULL REFERENCES meal(id) ON DELETE CASCADE,
+  food_id BIGINT NOT NULL REFERENCES food(id),
+  quantity_g REAL NOT NULL CHECK (quantity_g > 0)
+);
+
+CREATE INDEX meal_entry_food_id_idx ON meal_entry (food_id);
+
+-- Packaged products, keyed by GTIN-14
+CREATE TABLE product (
+  gtin CHAR(14) PRIMARY KEY,
+  food_id BIGINT NOT NULL REFERENCES food(id) ON DELETE CASCADE,
+  brand VARCHAR(256),
+  serving_g REAL CHECK (serving_g > 0)
+);
+
+CREATE INDEX product_food_id_idx ON product (food_id);
+
+-- Recipes, yield given in servings and/or cooked weight
+CREATE TABLE recipe (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  owner BIGINT NOT NULL REFERENCES public_user(owner) ON DELETE CASCADE,
+  name VARCHAR(128) NOT NULL,
+  servings REAL CHECK (servings > 0),
+  cooked_weight_g REAL CHECK (cooked_weight_g > 0)
+);
+
+CREATE TABLE recipe_ingredient (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  recipe_id BIGINT NOT NULL REFERENCES recipe(id) ON DELETE CASCADE,
+  food_id BIGINT NOT NULL REFERENCES food(id),
+  quantity_g REAL NOT NULL CHECK (quantity_g > 0)
+);
+
+-- Groups of users sharing meals and recipes, e.g. a family
+CREATE TABLE household (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  name VARCHAR(128) NOT NULL,
+  owner BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- The owner of a household is one of its members
+CREATE TABLE household_member (
+  household_id BIGINT NOT NULL REFERENCES household(id) ON DELETE CASCADE,
+  member BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+  joined_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (household_id, member)
+);
+
+CREATE INDEX household_member_member_idx ON household_member (member);
+
+-- Meal or recipe shared by its owner with a user or with a household
+CREATE TABLE share_grant (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  owner BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+  meal_id BIGINT REFERENCES meal(id) ON DELETE CASCADE,
+  recipe_id BIGINT REFERENCES recipe(id) ON DELETE CASCADE,
+  grantee BIGINT REFERENCES "user"(id) ON DELETE CASCADE,
+  household_id BIGINT REFERENCES household(id) ON DELETE CASCADE,
+  -- 'read' or 'log', logging implies reading
+  permission VARCHAR(16) NOT NULL CHECK (permission IN ('read', 'log')),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CHECK ((meal_id IS NULL) <> (recipe_id IS NULL)),
+  CHECK ((grantee IS NULL) <> (household_id IS NULL))
+);
+
+-- A single grant per resource and grantee, granting again replaces the permission
+CREATE UNIQUE INDEX share_grant_target_idx ON share_grant
+  (COALESCE(meal_id, 0), COALESCE(recipe_id, 0), COALESCE(grantee, 0), COALESCE(household_id, 0));
+CREATE INDEX share_grant_grantee_idx ON share_grant (grantee);
+CREATE INDEX share_grant_household_id_idx ON share_grant (household_id);
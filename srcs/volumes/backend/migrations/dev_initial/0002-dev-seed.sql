@@ -1,4 +1,31 @@
 -- User demo1
 INSERT INTO "user" (username) VALUES ( 'demo1' );
 
-INSERT INTO public_user (owner, age, size_cm, weight) VALUES (1000, 22, 176, 86.4);
+INSERT INTO public_user (owner, age, size_cm, weight, sex, activity_level) VALUES (1000, 22, 176, 86.4, 'male', 'moderate');
+
+INSERT INTO weight_log (owner, weight_kg, logged_at) VALUES
+  (1000, 88.1, now() - interval '28 days'),
+  (1000, 87.3, now() - interval '14 days'),
+  (1000, 86.4, now() - interval '1 day');
+
+-- Shared foods
+INSERT INTO food (name, kcal_100g, carbs_100g, proteins_100g, lipids_100g, alcohol_100g, density_g_ml) VALUES
+  ('Rice, white, cooked', 130, 28.2, 2.7, 0.3, 0, NULL),
+  ('Chicken breast, cooked', 165, 0, 31, 3.6, 0, NULL),
+  ('Olive oil', 884, 0, 0, 100, 0, 0.91),
+  ('Broccoli, raw', 34, 6.6, 2.8, 0.4, 0, NULL),
+  ('Beer, regular', 43, 3.6, 0.5, 0, 3.9, 1.01);
+
+-- Shared food portions
+INSERT INTO food_portion (food_id, unit, amount, description, gram_weight)
+SELECT food.id, portion.unit, portion.amount, portion.description, portion.gram_weight
+FROM (VALUES
+  ('Rice, white, cooked', 'cup', 1, NULL, 158),
+  ('Chicken breast, cooked', 'piece', 1, '1 breast', 172),
+  ('Broccoli, raw', 'cup', 1, 'chopped', 91)
+) AS portion (food_name, unit, amount, description, gram_weight)
+JOIN food ON food.name = portion.food_name AND food.owner IS NULL;
+
+-- Shared products
+INSERT INTO product (gtin, food_id, brand, serving_g) VALUES
+  ('04006381333931', 1002, 'Demo Oils', 15);
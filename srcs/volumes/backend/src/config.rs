@@ -1,4 +1,4 @@
-use std::{env, str::FromStr, sync::OnceLock};
+use std::{env, path::Path, str::FromStr, sync::OnceLock};
 
 use crate::{Error, Result};
 
@@ -7,6 +7,7 @@ use crate::{Error, Result};
 pub struct Config {
     // WEB
     pub WEB_FOLDER: String,
+    pub PHOTO_FOLDER: String,
 
     // DB :
     pub DB_URL: String,
@@ -15,6 +16,15 @@ pub struct Config {
     pub PWD_KEY: Vec<u8>,
     pub TOKEN_KEY: Vec<u8>,
     pub TOKEN_DURATION: f64,
+
+    // ALCOHOL GUIDELINE :
+    pub STANDARD_DRINK_G: f64,
+    pub DRINKS_DAY_LIMIT: f64,
+    pub DRINKS_WEEK_LIMIT: f64,
+
+    // TRASH :
+    pub MEAL_TRASH_RETENTION_DAYS: i32,
+    pub TRASH_PURGE_INTERVAL_SECS: u64,
 }
 
 pub fn config() -> &'static Config {
@@ -27,12 +37,29 @@ pub fn config() -> &'static Config {
 
 impl Config {
     pub fn load_from_env() -> Result<Self> {
+        let web_folder = get_env("SERVICE_WEB_FOLDER")?;
+        // Next to the web folder by default, photos must not be served as static files.
+        let photo_folder = Path::new(&web_folder)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join("photos");
+
         Ok(Self {
-            WEB_FOLDER: get_env("SERVICE_WEB_FOLDER")?,
+            PHOTO_FOLDER: get_env_parse_or(
+                "SERVICE_PHOTO_FOLDER",
+                photo_folder.to_string_lossy().into_owned(),
+            )?,
+            WEB_FOLDER: web_folder,
             DB_URL: get_env("SERVICE_DB_URL")?,
             PWD_KEY: get_env_b64_as_u8("SERVICE_PWD_KEY")?,
             TOKEN_KEY: get_env_b64_as_u8("SERVICE_TOKEN_KEY")?,
             TOKEN_DURATION: get_env_parse("SERVICE_TOKEN_DURATION_SECS")?,
+            // US guideline by default, a standard drink is 10 g in most of Europe.
+            STANDARD_DRINK_G: get_env_parse_or("SERVICE_STANDARD_DRINK_G", 14.)?,
+            DRINKS_DAY_LIMIT: get_env_parse_or("SERVICE_DRINKS_DAY_LIMIT", 2.)?,
+            DRINKS_WEEK_LIMIT: get_env_parse_or("SERVICE_DRINKS_WEEK_LIMIT", 14.)?,
+            MEAL_TRASH_RETENTION_DAYS: get_env_parse_or("SERVICE_MEAL_TRASH_RETENTION_DAYS", 30)?,
+            TRASH_PURGE_INTERVAL_SECS: get_env_parse_or("SERVICE_TRASH_PURGE_INTERVAL_SECS", 3600)?,
         })
     }
 }
@@ -50,3 +77,10 @@ fn get_env_parse<T: FromStr>(name: &'static str) -> Result<T> {
 
     val.parse().map_err(|_| Error::ConfigWrongFormat(name))
 }
+
+fn get_env_parse_or<T: FromStr>(name: &'static str, default: T) -> Result<T> {
+    match env::var(name) {
+        Ok(val) => val.parse().map_err(|_| Error::ConfigWrongFormat(name)),
+        Err(_) => Ok(default),
+    }
+}
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use time::Duration as TimeDuration;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{error, info};
+
+use crate::config::config;
+use crate::ctx::Ctx;
+use crate::model::{meal::MealBmc, ModelManager};
+use crate::utils::time_utils::now_utc;
+
+/// Purge the meals kept in the trash past the retention period, once at
+/// startup then every `TRASH_PURGE_INTERVAL_SECS`.
+pub fn spawn_trash_purge(mm: ModelManager) {
+    let config = config();
+    let period = Duration::from_secs(config.TRASH_PURGE_INTERVAL_SECS.max(1));
+    let retention = TimeDuration::days(config.MEAL_TRASH_RETENTION_DAYS.into());
+
+    tokio::spawn(async move {
+        let mut ticker = interval(period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            match MealBmc::purge_trash(&Ctx::root_ctx(), &mm, now_utc() - retention).await {
+                Ok(0) => {}
+                Ok(count) => info!("{:<12} - Purged {count} meals from the trash", "JOB"),
+                Err(err) => error!("{:<12} - Trash purge failed: {err}", "JOB"),
+            }
+        }
+    });
+}
@@ -1,17 +1,11 @@
 use model::ModelManager;
 use tokio::net::TcpListener;
 
-use axum::{
-    middleware,
-    response::Html,
-    routing::get,
-    Router,
-};
+use axum::{middleware, Router};
 
 use tower_cookies::CookieManagerLayer;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
-use web::mw_auth::mw_require_auth;
 
 pub use self::error::{Error, Result};
 
@@ -19,6 +13,7 @@ mod config;
 mod crypt;
 mod ctx;
 mod error;
+mod jobs;
 mod log;
 mod model;
 mod utils;
@@ -40,13 +35,27 @@ async fn main() -> Result<()> {
 
     let mm = ModelManager::new().await?;
 
-    //let api_routes = web::routes_tickets::routes(mm.clone())
-    //    .route_layer(middleware::from_fn(web::mw_auth::mw_require_auth));
+    jobs::spawn_trash_purge(mm.clone());
+
+    let api_routes = Router::new()
+        .merge(web::routes_user::routes(mm.clone()))
+        .merge(web::routes_meals::routes(mm.clone()))
+        .merge(web::routes_photos::routes(mm.clone()))
+        .merge(web::routes_foods::routes(mm.clone()))
+        .merge(web::routes_recipes::routes(mm.clone()))
+        .merge(web::routes_quick_add::routes(mm.clone()))
+        .merge(web::routes_diary::routes(mm.clone()))
+        .merge(web::routes_hydration::routes(mm.clone()))
+        .merge(web::routes_goals::routes(mm.clone()))
+        .merge(web::routes_reports::routes(mm.clone()))
+        .merge(web::routes_search::routes(mm.clone()))
+        .merge(web::routes_households::routes(mm.clone()))
+        .merge(web::routes_shares::routes(mm.clone()))
+        .merge(web::routes_weights::routes(mm.clone()));
 
     let routes_all = Router::new()
         .merge(web::routes_login::routes(mm.clone()))
-        .nest("/api", web::routes_user::routes(mm.clone()))
-        //.nest("/api", api_routes)
+        .nest("/api", api_routes)
         .layer(middleware::map_response(
             web::mw_res_map::main_response_mapper,
         ))
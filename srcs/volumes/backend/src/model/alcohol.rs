@@ -0,0 +1,181 @@
+use serde::Serialize;
+use time::{Date, Duration};
+
+use crate::config::config;
+use crate::ctx::Ctx;
+use crate::utils::time_utils::day_range_utc;
+
+use crate::model::{Error, Result};
+
+use super::ModelManager;
+
+/// Days of the rolling week the weekly limit applies to, the date included.
+const GUIDELINE_WEEK_DAYS: i64 = 7;
+
+/// Size of a standard drink and the limits it is counted against,
+/// set by the service configuration.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct DrinkGuideline {
+    pub standard_drink_g: f64,
+    pub day_limit: f64,
+    pub week_limit: f64,
+}
+
+impl DrinkGuideline {
+    pub fn from_config() -> Self {
+        let config = config();
+
+        DrinkGuideline {
+            standard_drink_g: config.STANDARD_DRINK_G,
+            day_limit: config.DRINKS_DAY_LIMIT,
+            week_limit: config.DRINKS_WEEK_LIMIT,
+        }
+    }
+
+    pub fn standard_drinks(&self, alcohol_g: f64) -> f64 {
+        if self.standard_drink_g > 0. {
+            alcohol_g / self.standard_drink_g
+        } else {
+            0.
+        }
+    }
+}
+
+/// Alcohol of a UTC day and of the 7 days ending on it.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlcoholSummary {
+    pub date: Date,
+    pub alcohol_g: f64,
+    pub standard_drinks: f64,
+    pub over_day_limit: bool,
+    pub week_alcohol_g: f64,
+    pub week_standard_drinks: f64,
+    pub over_week_limit: bool,
+    pub guideline: DrinkGuideline,
+}
+
+pub struct AlcoholBmc {}
+
+impl AlcoholBmc {
+    pub async fn summary(ctx: &Ctx, mm: &ModelManager, date: Date) -> Result<AlcoholSummary> {
+        let db = mm.db();
+
+        let week_from = date
+            .checked_sub(Duration::days(GUIDELINE_WEEK_DAYS - 1))
+            .ok_or(Error::InvalidDateRange {
+                from: date,
+                to: date,
+            })?;
+        let (week_start, end) = day_range_utc(week_from, date).ok_or(Error::InvalidDateRange {
+            from: week_from,
+            to: date,
+        })?;
+        let day_start = end - Duration::DAY;
+
+        let (alcohol_g, week_alcohol_g) = sqlx::query_as::<_, (f64, f64)>(
+            "SELECT
+                COALESCE(SUM((nutrients->>'alcohol')::FLOAT8) FILTER (WHERE consumed_at >= $3), 0),
+                COALESCE(SUM((nutrients->>'alcohol')::FLOAT8), 0)
+            FROM meal
+            WHERE owner = $1 AND deleted_at IS NULL AND consumed_at >= $2 AND consumed_at < $4",
+        )
+        .bind(ctx.user_id())
+        .bind(week_start)
+        .bind(day_start)
+        .bind(end)
+        .fetch_one(db)
+        .await?;
+
+        let guideline = DrinkGuideline::from_config();
+        let standard_drinks = guideline.standard_drinks(alcohol_g);
+        let week_standard_drinks = guideline.standard_drinks(week_alcohol_g);
+
+        Ok(AlcoholSummary {
+            date,
+            alcohol_g,
+            standard_drinks,
+            over_day_limit: standard_drinks > guideline.day_limit,
+            week_alcohol_g,
+            week_standard_drinks,
+            over_week_limit: week_standard_drinks > guideline.week_limit,
+            guideline,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        _dev_utils::dev_init_tests,
+        model::{
+            food::FoodBmc,
+            meal::{MealBmc, MealForCreate},
+            meal_entry::MealEntryForCreate,
+            unit::{Quantity, Unit},
+        },
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_summary_counts_standard_drinks() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_day = Date::from_calendar_date(2021, time::Month::April, 9)?;
+        let beer_id = FoodBmc::list(&ctx, &mm)
+            .await?
+            .into_iter()
+            .find(|f| f.name == "Beer, regular")
+            .expect("Seed should have a beer")
+            .id;
+        let fx_beer = |date: Date, amount: f32| -> Result<MealForCreate> {
+            Ok(MealForCreate {
+                name: "test_summary_counts_standard_drinks".to_string(),
+                consumed_at: Some(date.with_hms(20, 0, 0)?.assume_utc()),
+                entries: vec![MealEntryForCreate {
+                    food_id: beer_id,
+                    quantity: Quantity {
+                        amount: Some(amount),
+                        unit: Some(Unit::L),
+                        ..Default::default()
+                    },
+                }],
+                ..Default::default()
+            })
+        };
+
+        let ids = [
+            MealBmc::create(&ctx, &mm, fx_beer(fx_day, 1.)?).await?,
+            MealBmc::create(&ctx, &mm, fx_beer(fx_day - Duration::days(3), 2.)?).await?,
+            // Out of the week.
+            MealBmc::create(&ctx, &mm, fx_beer(fx_day - Duration::days(7), 5.)?).await?,
+        ];
+
+        let meal = MealBmc::get(&ctx, &mm, ids[0]).await?;
+        // 1010 g of beer, the 39.4 g of alcohol account for 276 of the 434 kcal.
+        assert_eq!(meal.kcal, 434);
+        assert_eq!((meal.nutrients.alcohol_g() * 10.).round(), 394.);
+
+        let summary = AlcoholBmc::summary(&ctx, &mm, fx_day).await?;
+
+        assert_eq!((summary.standard_drinks * 100.).round(), 281.);
+        assert!(summary.over_day_limit);
+        assert_eq!((summary.week_alcohol_g * 10.).round(), 1182.);
+        assert_eq!((summary.week_standard_drinks * 100.).round(), 844.);
+        assert!(!summary.over_week_limit);
+
+        for date in [Date::MIN, Date::MAX] {
+            let res = AlcoholBmc::summary(&ctx, &mm, date).await;
+            assert!(matches!(res, Err(Error::InvalidDateRange { .. })));
+        }
+
+        for id in ids {
+            MealBmc::delete(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+}
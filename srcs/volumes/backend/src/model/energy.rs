@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+
+use super::user::{public_user::PublicUser, FullUser};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sex {
+    Female,
+    Male,
+    /// The sex-specific formulas can't be used.
+    #[default]
+    Unspecified,
+}
+
+impl Sex {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sex::Female => "female",
+            Sex::Male => "male",
+            Sex::Unspecified => "unspecified",
+        }
+    }
+}
+
+impl From<String> for Sex {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "female" => Sex::Female,
+            "male" => Sex::Male,
+            _ => Sex::Unspecified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityLevel {
+    /// Desk job, little to no exercise.
+    #[default]
+    Sedentary,
+    /// Exercise 1 to 3 days a week.
+    Light,
+    /// Exercise 3 to 5 days a week.
+    Moderate,
+    /// Exercise 6 to 7 days a week.
+    Active,
+    /// Hard daily exercise or a physical job.
+    VeryActive,
+}
+
+impl ActivityLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityLevel::Sedentary => "sedentary",
+            ActivityLevel::Light => "light",
+            ActivityLevel::Moderate => "moderate",
+            ActivityLevel::Active => "active",
+            ActivityLevel::VeryActive => "very_active",
+        }
+    }
+
+    /// Multiplier from the BMR to the TDEE.
+    pub fn factor(&self) -> f64 {
+        match self {
+            ActivityLevel::Sedentary => 1.2,
+            ActivityLevel::Light => 1.375,
+            ActivityLevel::Moderate => 1.55,
+            ActivityLevel::Active => 1.725,
+            ActivityLevel::VeryActive => 1.9,
+        }
+    }
+}
+
+impl From<String> for ActivityLevel {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "light" => ActivityLevel::Light,
+            "moderate" => ActivityLevel::Moderate,
+            "active" => ActivityLevel::Active,
+            "very_active" => ActivityLevel::VeryActive,
+            _ => ActivityLevel::Sedentary,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BmrFormula {
+    MifflinStJeor,
+    /// Revised by Roza and Shizgal (1984).
+    HarrisBenedict,
+    /// From the lean body mass, needs the body fat.
+    #[serde(rename = "katch_mcardle")]
+    KatchMcArdle,
+}
+
+impl BmrFormula {
+    /// Basal metabolic rate in kcal per day, None when the formula lacks
+    /// one of its inputs.
+    pub fn bmr(&self, body: &BodyMetrics) -> Option<f64> {
+        let weight = f64::from(body.weight_kg);
+        let height = f64::from(body.size_cm);
+        let age = f64::from(body.age);
+
+        match (self, body.sex) {
+            (BmrFormula::MifflinStJeor, Sex::Male) => {
+                Some(10. * weight + 6.25 * height - 5. * age + 5.)
+            }
+            (BmrFormula::MifflinStJeor, Sex::Female) => {
+                Some(10. * weight + 6.25 * height - 5. * age - 161.)
+            }
+            (BmrFormula::HarrisBenedict, Sex::Male) => {
+                Some(88.362 + 13.397 * weight + 4.799 * height - 5.677 * age)
+            }
+            (BmrFormula::HarrisBenedict, Sex::Female) => {
+                Some(447.593 + 9.247 * weight + 3.098 * height - 4.330 * age)
+            }
+            (BmrFormula::KatchMcArdle, _) => body.body_fat_pct.map(|body_fat_pct| {
+                let lean_mass = weight * (1. - f64::from(body_fat_pct) / 100.);
+                370. + 21.6 * lean_mass
+            }),
+            (_, Sex::Unspecified) => None,
+        }
+    }
+}
+
+/// Profile fields the energy expenditure is estimated from.
+#[derive(Debug, Clone)]
+pub struct BodyMetrics {
+    pub sex: Sex,
+    pub age: i32,
+    pub size_cm: i32,
+    pub weight_kg: f32,
+    pub body_fat_pct: Option<f32>,
+    pub activity_level: ActivityLevel,
+}
+
+impl From<&PublicUser> for BodyMetrics {
+    fn from(public_user: &PublicUser) -> Self {
+        BodyMetrics {
+            sex: public_user.sex,
+            age: public_user.age,
+            size_cm: public_user.size_cm,
+            weight_kg: public_user.weight,
+            body_fat_pct: public_user.body_fat_pct,
+            activity_level: public_user.activity_level,
+        }
+    }
+}
+
+impl From<&FullUser> for BodyMetrics {
+    fn from(full_user: &FullUser) -> Self {
+        BodyMetrics {
+            sex: full_user.sex,
+            age: full_user.age,
+            size_cm: full_user.size_cm,
+            weight_kg: full_user.weight,
+            body_fat_pct: full_user.body_fat_pct,
+            activity_level: full_user.activity_level,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnergyEstimate {
+    pub formula: BmrFormula,
+    pub bmr_kcal: f64,
+    pub tdee_kcal: f64,
+}
+
+/// Daily energy expenditure, the basis of every kcal target.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnergyProfile {
+    pub activity_level: ActivityLevel,
+    pub activity_factor: f64,
+    /// Katch-McArdle when the body fat is known, Mifflin-St Jeor otherwise.
+    pub formula: BmrFormula,
+    pub bmr_kcal: f64,
+    pub tdee_kcal: f64,
+    /// Every formula the profile has the inputs for.
+    pub estimates: Vec<EnergyEstimate>,
+}
+
+impl EnergyProfile {
+    /// None when no formula applies, i.e. the sex is unspecified and the
+    /// body fat unknown.
+    pub fn new(body: &BodyMetrics) -> Option<EnergyProfile> {
+        let activity_factor = body.activity_level.factor();
+
+        let estimates: Vec<EnergyEstimate> = [
+            BmrFormula::KatchMcArdle,
+            BmrFormula::MifflinStJeor,
+            BmrFormula::HarrisBenedict,
+        ]
+        .into_iter()
+        .filter_map(|formula| {
+            let bmr = formula.bmr(body)?;
+            Some(EnergyEstimate {
+                formula,
+                bmr_kcal: bmr.round(),
+                tdee_kcal: (bmr * activity_factor).round(),
+            })
+        })
+        .collect();
+
+        let preferred = estimates.first()?;
+
+        Some(EnergyProfile {
+            activity_level: body.activity_level,
+            activity_factor,
+            formula: preferred.formula,
+            bmr_kcal: preferred.bmr_kcal,
+            tdee_kcal: preferred.tdee_kcal,
+            estimates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActivityLevel, BmrFormula, BodyMetrics, EnergyProfile, Sex};
+
+    fn fixture_body() -> BodyMetrics {
+        BodyMetrics {
+            sex: Sex::Male,
+            age: 30,
+            size_cm: 180,
+            weight_kg: 80.,
+            body_fat_pct: None,
+            activity_level: ActivityLevel::Moderate,
+        }
+    }
+
+    #[test]
+    fn test_bmr_formulas() {
+        let body = fixture_body();
+        let female = BodyMetrics {
+            sex: Sex::Female,
+            ..fixture_body()
+        };
+        let lean = BodyMetrics {
+            body_fat_pct: Some(20.),
+            ..fixture_body()
+        };
+
+        assert_eq!(BmrFormula::MifflinStJeor.bmr(&body), Some(1780.));
+        assert_eq!(BmrFormula::MifflinStJeor.bmr(&female), Some(1614.));
+        let harris_benedict = BmrFormula::HarrisBenedict.bmr(&body).unwrap();
+        assert!((harris_benedict - 1853.632).abs() < 1e-6);
+        assert_eq!(BmrFormula::KatchMcArdle.bmr(&body), None);
+        let katch_mcardle = BmrFormula::KatchMcArdle.bmr(&lean).unwrap();
+        assert!((katch_mcardle - 1752.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_energy_profile_prefers_lean_mass() {
+        let profile = EnergyProfile::new(&fixture_body()).unwrap();
+        let lean = EnergyProfile::new(&BodyMetrics {
+            body_fat_pct: Some(20.),
+            ..fixture_body()
+        })
+        .unwrap();
+        let unknown = EnergyProfile::new(&BodyMetrics {
+            sex: Sex::Unspecified,
+            ..fixture_body()
+        });
+
+        assert_eq!(profile.formula, BmrFormula::MifflinStJeor);
+        assert_eq!(profile.tdee_kcal, 2759.);
+        assert_eq!(profile.estimates.len(), 2);
+        assert_eq!(lean.formula, BmrFormula::KatchMcArdle);
+        assert_eq!(lean.bmr_kcal, 1752.);
+        assert_eq!(lean.estimates.len(), 3);
+        assert!(unknown.is_none());
+    }
+}
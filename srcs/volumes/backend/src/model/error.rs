@@ -1,9 +1,10 @@
 use serde::Serialize;
 use serde_with::{serde_as, DisplayFromStr};
+use time::Date;
 
 use crate::crypt;
 
-use super::store;
+use super::{store, validation::FieldError};
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -14,8 +15,16 @@ pub enum Error {
     Crypt(crypt::Error),
 
     Sqlx(#[serde_as(as = "DisplayFromStr")] sqlx::Error),
+    Io(#[serde_as(as = "DisplayFromStr")] std::io::Error),
 
     ItemNotFound { entity: &'static str, id: i64 },
+    ItemInUse { entity: &'static str, id: i64 },
+    /// Item visible to the user without the permission the action needs.
+    AccessDenied { entity: &'static str, id: i64 },
+    InvalidDateRange { from: Date, to: Date },
+    /// Stored enum value the model doesn't know.
+    UnknownValue { kind: &'static str, value: String },
+    Validation { entity: &'static str, errors: Vec<FieldError> },
     PublicUserNotFound { owner_id: i64 },
 }
 
@@ -27,6 +36,18 @@ impl std::fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// Turn a foreign key violation into `ItemInUse`, other errors are kept as is.
+    pub fn from_fk_violation(err: sqlx::Error, entity: &'static str, id: i64) -> Self {
+        match &err {
+            sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("23503") => {
+                Error::ItemInUse { entity, id }
+            }
+            _ => Error::Sqlx(err),
+        }
+    }
+}
+
 // Froms
 
 impl From<store::Error> for Error {
@@ -46,3 +67,9 @@ impl From<sqlx::Error> for Error {
         Self::Sqlx(value)
     }
 }
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
@@ -0,0 +1,66 @@
+use crate::ctx::Ctx;
+
+use crate::model::{Error, Result};
+
+use super::{
+    meal::{Meal, MealBmc, MEAL_COLUMNS},
+    ModelManager,
+};
+
+pub struct FavoriteBmc {}
+
+impl FavoriteBmc {
+    /// Star a meal, starring it twice is a no-op.
+    pub async fn add(ctx: &Ctx, mm: &ModelManager, meal_id: i64) -> Result<()> {
+        MealBmc::get(ctx, mm, meal_id).await?;
+
+        let db = mm.db();
+
+        sqlx::query(
+            "INSERT INTO favorite_meal (owner, meal_id) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(ctx.user_id())
+        .bind(meal_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<Meal>> {
+        let db = mm.db();
+
+        let meals = sqlx::query_as(&format!(
+            "SELECT {MEAL_COLUMNS} FROM meal
+            WHERE owner = $1 AND deleted_at IS NULL
+                AND id IN (SELECT meal_id FROM favorite_meal WHERE owner = $1)
+            ORDER BY name, id"
+        ))
+        .bind(ctx.user_id())
+        .fetch_all(db)
+        .await?;
+
+        Ok(meals)
+    }
+
+    pub async fn remove(ctx: &Ctx, mm: &ModelManager, meal_id: i64) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query("DELETE FROM favorite_meal WHERE owner = $1 AND meal_id = $2")
+            .bind(ctx.user_id())
+            .bind(meal_id)
+            .execute(db)
+            .await?
+            .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "favorite",
+                id: meal_id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use crate::ctx::Ctx;
+use crate::utils::serde_utils::nullable;
+
+use crate::model::{Error, Result};
+
+use super::{
+    validation::{FieldErrorKind, FieldErrors},
+    ModelManager,
+};
+
+/// Catalog entry, nutrition values are given per 100 g.
+/// Foods without owner are shared with every user.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Food {
+    pub id: i64,
+    pub owner: Option<i64>,
+    pub name: String,
+    pub kcal_100g: f32,
+    pub carbs_100g: f32,
+    pub proteins_100g: f32,
+    pub lipids_100g: f32,
+    /// Ethanol, counted in the energy at 7 kcal/g.
+    pub alcohol_100g: f32,
+    /// Grams per millilitre, needed to log the food in volume units
+    /// when it has no volume portion.
+    pub density_g_ml: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct FoodForCreate {
+    pub name: String,
+    pub kcal_100g: f32,
+    pub carbs_100g: f32,
+    pub proteins_100g: f32,
+    pub lipids_100g: f32,
+    #[serde(default)]
+    pub alcohol_100g: f32,
+    #[serde(default)]
+    pub density_g_ml: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct FoodForUpdate {
+    pub name: Option<String>,
+    pub kcal_100g: Option<f32>,
+    pub carbs_100g: Option<f32>,
+    pub proteins_100g: Option<f32>,
+    pub lipids_100g: Option<f32>,
+    pub alcohol_100g: Option<f32>,
+    /// `null` clears the density.
+    #[serde(default, deserialize_with = "nullable")]
+    pub density_g_ml: Option<Option<f32>>,
+}
+
+const FOOD_NAME_MAX_LEN: usize = 256;
+/// Pure fat is the most energy dense food.
+const FOOD_MAX_KCAL_100G: f64 = 900.;
+const FOOD_MIN_DENSITY_G_ML: f64 = 0.01;
+const FOOD_MAX_DENSITY_G_ML: f64 = 5.;
+
+fn check_density(errors: &mut FieldErrors, density_g_ml: Option<f32>) {
+    if let Some(density_g_ml) = density_g_ml {
+        errors.check_range(
+            "density_g_ml",
+            density_g_ml.into(),
+            FOOD_MIN_DENSITY_G_ML,
+            FOOD_MAX_DENSITY_G_ML,
+        );
+    }
+}
+
+fn check_nutrition(
+    errors: &mut FieldErrors,
+    kcal_100g: Option<f32>,
+    macros_100g: [(&'static str, Option<f32>); 4],
+) {
+    if let Some(kcal_100g) = kcal_100g {
+        errors.check_range("kcal_100g", kcal_100g.into(), 0., FOOD_MAX_KCAL_100G);
+    }
+
+    for (field, value) in macros_100g {
+        if let Some(value) = value {
+            errors.check_range(field, value.into(), 0., 100.);
+        }
+    }
+}
+
+/// Macros and alcohol can't weigh more than the 100 g they are given for.
+fn check_macros_weight(errors: &mut FieldErrors, macros_100g: [f32; 4]) {
+    let total_g: f64 = macros_100g.into_iter().map(f64::from).sum();
+    if total_g > 100. {
+        errors.push(
+            "carbs_100g",
+            FieldErrorKind::MacrosOverWeight {
+                total_g,
+                max_g: 100.,
+            },
+        );
+    }
+}
+
+impl FoodForCreate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        errors.check_text("name", &self.name, FOOD_NAME_MAX_LEN);
+        check_nutrition(
+            &mut errors,
+            Some(self.kcal_100g),
+            [
+                ("carbs_100g", Some(self.carbs_100g)),
+                ("proteins_100g", Some(self.proteins_100g)),
+                ("lipids_100g", Some(self.lipids_100g)),
+                ("alcohol_100g", Some(self.alcohol_100g)),
+            ],
+        );
+        check_density(&mut errors, self.density_g_ml);
+        check_macros_weight(
+            &mut errors,
+            [
+                self.carbs_100g,
+                self.proteins_100g,
+                self.lipids_100g,
+                self.alcohol_100g,
+            ],
+        );
+
+        errors.into_result("food")
+    }
+}
+
+impl FoodForUpdate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        if let Some(name) = &self.name {
+            errors.check_text("name", name, FOOD_NAME_MAX_LEN);
+        }
+        check_nutrition(
+            &mut errors,
+            self.kcal_100g,
+            [
+                ("carbs_100g", self.carbs_100g),
+                ("proteins_100g", self.proteins_100g),
+                ("lipids_100g", self.lipids_100g),
+                ("alcohol_100g", self.alcohol_100g),
+            ],
+        );
+        check_density(&mut errors, self.density_g_ml.flatten());
+
+        errors.into_result("food")
+    }
+}
+
+pub struct FoodBmc {}
+
+impl FoodBmc {
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, food_c: FoodForCreate) -> Result<i64> {
+        food_c.validate()?;
+
+        let db = mm.db();
+
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO food (owner, name, kcal_100g, carbs_100g, proteins_100g, lipids_100g, alcohol_100g,
+                density_g_ml)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+        )
+        .bind(ctx.user_id())
+        .bind(food_c.name)
+        .bind(food_c.kcal_100g)
+        .bind(food_c.carbs_100g)
+        .bind(food_c.proteins_100g)
+        .bind(food_c.lipids_100g)
+        .bind(food_c.alcohol_100g)
+        .bind(food_c.density_g_ml)
+        .fetch_one(db)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Get a food visible by the ctx user, either shared or owned.
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Food> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, Food>(
+            "SELECT id, owner, name, kcal_100g, carbs_100g, proteins_100g, lipids_100g, alcohol_100g,
+                density_g_ml
+            FROM food WHERE id = $1 AND (owner IS NULL OR owner = $2)",
+        )
+        .bind(id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound { entity: "food", id })
+    }
+
+    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<Food>> {
+        let db = mm.db();
+
+        let foods = sqlx::query_as(
+            "SELECT id, owner, name, kcal_100g, carbs_100g, proteins_100g, lipids_100g, alcohol_100g,
+                density_g_ml
+            FROM food WHERE owner IS NULL OR owner = $1 ORDER BY name, id",
+        )
+        .bind(ctx.user_id())
+        .fetch_all(db)
+        .await?;
+
+        Ok(foods)
+    }
+
+    /// Only owned foods can be updated, shared ones are read only.
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        food_u: FoodForUpdate,
+    ) -> Result<()> {
+        food_u.validate()?;
+
+        let food_to_update = FoodBmc::get(ctx, mm, id).await?;
+
+        let carbs_100g = food_u.carbs_100g.unwrap_or(food_to_update.carbs_100g);
+        let proteins_100g = food_u.proteins_100g.unwrap_or(food_to_update.proteins_100g);
+        let lipids_100g = food_u.lipids_100g.unwrap_or(food_to_update.lipids_100g);
+        let alcohol_100g = food_u.alcohol_100g.unwrap_or(food_to_update.alcohol_100g);
+
+        let mut errors = FieldErrors::default();
+        check_macros_weight(
+            &mut errors,
+            [carbs_100g, proteins_100g, lipids_100g, alcohol_100g],
+        );
+        errors.into_result("food")?;
+
+        let db = mm.db();
+
+        let count = sqlx::query(
+            "UPDATE food SET name = $1, kcal_100g = $2, carbs_100g = $3, proteins_100g = $4, lipids_100g = $5,
+                alcohol_100g = $6, density_g_ml = $7
+            WHERE id = $8 AND owner = $9",
+        )
+        .bind(food_u.name.unwrap_or(food_to_update.name))
+        .bind(food_u.kcal_100g.unwrap_or(food_to_update.kcal_100g))
+        .bind(carbs_100g)
+        .bind(proteins_100g)
+        .bind(lipids_100g)
+        .bind(alcohol_100g)
+        .bind(food_u.density_g_ml.unwrap_or(food_to_update.density_g_ml))
+        .bind(id)
+        .bind(ctx.user_id())
+        .execute(db)
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound { entity: "food", id })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query("DELETE FROM food WHERE id = $1 AND owner = $2")
+            .bind(id)
+            .bind(ctx.user_id())
+            .execute(db)
+            .await
+            .map_err(|e| Error::from_fk_violation(e, "food", id))?
+            .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound { entity: "food", id })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::_dev_utils::dev_init_tests;
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    fn fixture_food(name: &str) -> FoodForCreate {
+        FoodForCreate {
+            name: name.to_string(),
+            kcal_100g: 389.,
+            carbs_100g: 66.,
+            proteins_100g: 17.,
+            lipids_100g: 7.,
+            alcohol_100g: 0.,
+            density_g_ml: None,
+        }
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_ok() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+
+        let id = FoodBmc::create(&ctx, &mm, fixture_food("test_create_ok oats")).await?;
+
+        let food = FoodBmc::get(&ctx, &mm, id).await?;
+
+        assert_eq!(food.name, "test_create_ok oats");
+        assert_eq!(food.owner, Some(ctx.user_id()));
+        assert_eq!(food.kcal_100g, 389.);
+
+        FoodBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_list_includes_shared() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+
+        let id = FoodBmc::create(&ctx, &mm, fixture_food("test_list_includes_shared")).await?;
+
+        let foods = FoodBmc::list(&ctx, &mm).await?;
+
+        assert!(foods.iter().any(|f| f.id == id));
+        assert!(foods.iter().any(|f| f.owner.is_none()));
+
+        FoodBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_update_shared_not_found() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+
+        let shared = FoodBmc::list(&ctx, &mm)
+            .await?
+            .into_iter()
+            .find(|f| f.owner.is_none())
+            .expect("Seed should have shared foods");
+
+        let res = FoodBmc::update(
+            &ctx,
+            &mm,
+            shared.id,
+            FoodForUpdate {
+                name: Some("hijacked".to_string()),
+                kcal_100g: None,
+                carbs_100g: None,
+                proteins_100g: None,
+                lipids_100g: None,
+                alcohol_100g: None,
+                density_g_ml: None,
+            },
+        )
+        .await;
+
+        assert!(
+            matches!(res, Err(Error::ItemNotFound { entity: "food", .. })),
+            "Shared food should not be updatable"
+        );
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_update_checks_merged_macros() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+
+        let id =
+            FoodBmc::create(&ctx, &mm, fixture_food("test_update_checks_merged_macros")).await?;
+
+        // 66 g of carbs are kept, 66 + 40 + 7 is over 100 g.
+        let res = FoodBmc::update(
+            &ctx,
+            &mm,
+            id,
+            FoodForUpdate {
+                name: None,
+                kcal_100g: None,
+                carbs_100g: None,
+                proteins_100g: Some(40.),
+                lipids_100g: None,
+                alcohol_100g: None,
+                density_g_ml: None,
+            },
+        )
+        .await;
+
+        assert!(
+            matches!(res, Err(Error::Validation { entity: "food", .. })),
+            "Merged macros over 100 g should fail, was {res:?}"
+        );
+        assert_eq!(FoodBmc::get(&ctx, &mm, id).await?.proteins_100g, 17.);
+
+        FoodBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_update_clears_density() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let id = FoodBmc::create(
+            &ctx,
+            &mm,
+            FoodForCreate {
+                density_g_ml: Some(1.03),
+                ..fixture_food("test_update_clears_density")
+            },
+        )
+        .await?;
+
+        let food_u: FoodForUpdate = serde_json::from_str(r#"{"kcal_100g": 380}"#)?;
+        FoodBmc::update(&ctx, &mm, id, food_u).await?;
+        let kept = FoodBmc::get(&ctx, &mm, id).await?;
+
+        let food_u: FoodForUpdate = serde_json::from_str(r#"{"density_g_ml": null}"#)?;
+        FoodBmc::update(&ctx, &mm, id, food_u).await?;
+        let cleared = FoodBmc::get(&ctx, &mm, id).await?;
+
+        assert_eq!(kept.density_g_ml, Some(1.03));
+        assert_eq!(cleared.density_g_ml, None);
+        assert_eq!(cleared.kcal_100g, 380.);
+
+        FoodBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+}
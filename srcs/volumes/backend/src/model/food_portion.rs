@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use crate::ctx::Ctx;
+
+use crate::model::{Error, Result};
+
+use super::{food::FoodBmc, unit::Unit, validation::FieldErrors, ModelManager};
+
+/// Household measure of a food, e.g. 0.5 cup weighing 44 g. The unit is
+/// a volume unit code or the name of the portion, like "slice".
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct FoodPortion {
+    pub id: i64,
+    pub food_id: i64,
+    pub unit: String,
+    pub amount: f32,
+    pub description: Option<String>,
+    pub gram_weight: f32,
+}
+
+#[derive(Deserialize)]
+pub struct FoodPortionForCreate {
+    pub unit: Unit,
+    /// Defaults to 1.
+    pub amount: Option<f32>,
+    pub description: Option<String>,
+    pub gram_weight: f32,
+}
+
+const PORTION_UNIT_MAX_LEN: usize = 64;
+const PORTION_DESCRIPTION_MAX_LEN: usize = 256;
+const PORTION_MAX_AMOUNT: f64 = 1_000.;
+const PORTION_MAX_WEIGHT_G: f64 = 10_000.;
+
+impl FoodPortion {
+    /// Weight of a single unit of the portion.
+    pub fn unit_grams(&self) -> f64 {
+        f64::from(self.gram_weight) / f64::from(self.amount)
+    }
+}
+
+impl FoodPortionForCreate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        errors.check_text("unit", self.unit.as_str(), PORTION_UNIT_MAX_LEN);
+        if let Some(amount) = self.amount {
+            errors.check_range("amount", amount.into(), 0.001, PORTION_MAX_AMOUNT);
+        }
+        if let Some(description) = &self.description {
+            errors.check_text("description", description, PORTION_DESCRIPTION_MAX_LEN);
+        }
+        errors.check_range(
+            "gram_weight",
+            self.gram_weight.into(),
+            0.1,
+            PORTION_MAX_WEIGHT_G,
+        );
+
+        errors.into_result("food_portion")
+    }
+}
+
+pub struct FoodPortionBmc {}
+
+impl FoodPortionBmc {
+    /// Portions can only be added to owned foods.
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        food_id: i64,
+        portion_c: FoodPortionForCreate,
+    ) -> Result<i64> {
+        portion_c.validate()?;
+
+        let db = mm.db();
+
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO food_portion (food_id, unit, amount, description, gram_weight)
+            SELECT id, $1, $2, $3, $4 FROM food WHERE id = $5 AND owner = $6
+            RETURNING id",
+        )
+        .bind(portion_c.unit.as_str())
+        .bind(portion_c.amount.unwrap_or(1.))
+        .bind(portion_c.description)
+        .bind(portion_c.gram_weight)
+        .bind(food_id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "food",
+            id: food_id,
+        })?;
+
+        Ok(id)
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, food_id: i64, id: i64) -> Result<FoodPortion> {
+        FoodBmc::get(ctx, mm, food_id).await?;
+
+        let db = mm.db();
+
+        sqlx::query_as::<_, FoodPortion>(
+            "SELECT id, food_id, unit, amount, description, gram_weight
+            FROM food_portion WHERE id = $1 AND food_id = $2",
+        )
+        .bind(id)
+        .bind(food_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "food_portion",
+            id,
+        })
+    }
+
+    pub async fn list(ctx: &Ctx, mm: &ModelManager, food_id: i64) -> Result<Vec<FoodPortion>> {
+        FoodBmc::get(ctx, mm, food_id).await?;
+
+        let db = mm.db();
+
+        let portions = sqlx::query_as(
+            "SELECT id, food_id, unit, amount, description, gram_weight
+            FROM food_portion WHERE food_id = $1 ORDER BY id",
+        )
+        .bind(food_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(portions)
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, food_id: i64, id: i64) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query(
+            "DELETE FROM food_portion USING food
+            WHERE food_portion.id = $1 AND food_portion.food_id = $2
+                AND food.id = food_portion.food_id AND food.owner = $3",
+        )
+        .bind(id)
+        .bind(food_id)
+        .bind(ctx.user_id())
+        .execute(db)
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "food_portion",
+                id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
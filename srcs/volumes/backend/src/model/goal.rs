@@ -0,0 +1,578 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::Json};
+use time::{Date, OffsetDateTime};
+
+use crate::ctx::Ctx;
+use crate::utils::time_utils::now_utc;
+
+use crate::model::{Error, Result};
+
+use super::{
+    energy::{BodyMetrics, EnergyProfile},
+    meal::{MealBmc, NutritionTotals},
+    user::public_user::{PublicUser, PublicUserBmc},
+    validation::{FieldErrorKind, FieldErrors},
+    weight::{check_weight, WeightBmc},
+    ModelManager,
+};
+
+/// Energy stored in a kilogram of body weight.
+const KCAL_PER_KG: f64 = 7_700.;
+const GOAL_WEEKLY_RATE_MAX_KG: f64 = 1.;
+/// Distance to the target weight under which the goal is maintenance.
+const GOAL_WEIGHT_TOLERANCE_KG: f32 = 0.5;
+const MACRO_PROTEINS_MAX_G_PER_KG: f64 = 4.;
+const MACRO_LIPIDS_MAX_G_PER_KG: f64 = 3.;
+
+const GOAL_COLUMNS: &str = "owner, target_weight_kg, weekly_rate_kg, macro_split, updated_at";
+
+/// How the daily kcal target is shared between the macros.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum MacroSplit {
+    /// Percent of the kcal target, adding up to 100.
+    Percent {
+        carbs: f32,
+        proteins: f32,
+        lipids: f32,
+    },
+    /// Grams per kg of body weight, the carbs fill the remaining kcal.
+    GPerKg { proteins: f32, lipids: f32 },
+}
+
+impl MacroSplit {
+    fn check(&self, errors: &mut FieldErrors) {
+        match *self {
+            MacroSplit::Percent {
+                carbs,
+                proteins,
+                lipids,
+            } => {
+                errors.check_range("carbs", carbs.into(), 0., 100.);
+                errors.check_range("proteins", proteins.into(), 0., 100.);
+                errors.check_range("lipids", lipids.into(), 0., 100.);
+
+                let total = f64::from(carbs + proteins + lipids);
+                if (total - 100.).abs() > 0.5 {
+                    errors.push(
+                        "macro_split",
+                        FieldErrorKind::WrongTotal {
+                            total,
+                            expected: 100.,
+                        },
+                    );
+                }
+            }
+            MacroSplit::GPerKg { proteins, lipids } => {
+                errors.check_range("proteins", proteins.into(), 0., MACRO_PROTEINS_MAX_G_PER_KG);
+                errors.check_range("lipids", lipids.into(), 0., MACRO_LIPIDS_MAX_G_PER_KG);
+            }
+        }
+    }
+}
+
+/// Database form of `MacroSplit`, a JSONB object.
+type MacroSplitJson = Json<MacroSplit>;
+
+impl From<MacroSplitJson> for MacroSplit {
+    fn from(value: MacroSplitJson) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Goal {
+    pub owner: i64,
+    pub target_weight_kg: f32,
+    /// Weight to lose or gain each week, toward the target weight.
+    pub weekly_rate_kg: f32,
+    #[sqlx(try_from = "MacroSplitJson")]
+    pub macro_split: MacroSplit,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// Setting a goal replaces the previous one.
+#[derive(Deserialize)]
+pub struct GoalForSet {
+    pub target_weight_kg: f32,
+    pub weekly_rate_kg: f32,
+    pub macro_split: MacroSplit,
+}
+
+impl GoalForSet {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+        check_weight(&mut errors, "target_weight_kg", self.target_weight_kg);
+        errors.check_range(
+            "weekly_rate_kg",
+            self.weekly_rate_kg.into(),
+            0.,
+            GOAL_WEEKLY_RATE_MAX_KG,
+        );
+        self.macro_split.check(&mut errors);
+        errors.into_result("goal")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GoalDirection {
+    Lose,
+    Maintain,
+    Gain,
+}
+
+/// Daily kcal and macros in grams.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MacroTargets {
+    pub kcal: f64,
+    pub carbs: f64,
+    pub proteins: f64,
+    pub lipids: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalTargets {
+    pub direction: GoalDirection,
+    pub weight_kg: f32,
+    pub tdee_kcal: f64,
+    /// Kcal added to the TDEE, negative for a deficit. The kcal target
+    /// never goes under the BMR, which can shrink a deficit.
+    pub kcal_adjustment: f64,
+    pub daily: MacroTargets,
+    /// Kcal the proteins and lipids given per kg go over the kcal target
+    /// by, the carbs are then 0.
+    pub macro_overflow_kcal: Option<f64>,
+    /// At the rate the kcal adjustment gives, None when maintaining.
+    pub weeks_to_target: Option<f64>,
+}
+
+impl GoalTargets {
+    pub fn new(goal: &Goal, body: &BodyMetrics, energy: &EnergyProfile) -> GoalTargets {
+        let weight_kg = body.weight_kg;
+        let to_target = goal.target_weight_kg - weight_kg;
+
+        let direction = if to_target.abs() <= GOAL_WEIGHT_TOLERANCE_KG || goal.weekly_rate_kg == 0.
+        {
+            GoalDirection::Maintain
+        } else if to_target < 0. {
+            GoalDirection::Lose
+        } else {
+            GoalDirection::Gain
+        };
+
+        let daily_change = f64::from(goal.weekly_rate_kg) * KCAL_PER_KG / 7.;
+        let kcal = match direction {
+            GoalDirection::Lose => (energy.tdee_kcal - daily_change).max(energy.bmr_kcal),
+            GoalDirection::Maintain => energy.tdee_kcal,
+            GoalDirection::Gain => energy.tdee_kcal + daily_change,
+        };
+
+        let kcal = kcal.round();
+        let (carbs, proteins, lipids) = match goal.macro_split {
+            MacroSplit::Percent {
+                carbs,
+                proteins,
+                lipids,
+            } => (
+                kcal * f64::from(carbs) / 100. / 4.,
+                kcal * f64::from(proteins) / 100. / 4.,
+                kcal * f64::from(lipids) / 100. / 9.,
+            ),
+            MacroSplit::GPerKg { proteins, lipids } => {
+                let proteins = f64::from(proteins * weight_kg);
+                let lipids = f64::from(lipids * weight_kg);
+                let carbs = (kcal - proteins * 4. - lipids * 9.).max(0.) / 4.;
+                (carbs, proteins, lipids)
+            }
+        };
+        let macros_kcal = carbs * 4. + proteins * 4. + lipids * 9.;
+        let macro_overflow_kcal = (macros_kcal > kcal).then(|| (macros_kcal - kcal).round());
+
+        // The BMR floor can make the deficit, and so the rate, smaller.
+        let kcal_adjustment = kcal - energy.tdee_kcal;
+        let weekly_rate_kg = kcal_adjustment.abs() * 7. / KCAL_PER_KG;
+        let weeks_to_target = match direction {
+            GoalDirection::Maintain => None,
+            _ if weekly_rate_kg == 0. => None,
+            _ => Some((f64::from(to_target.abs()) / weekly_rate_kg * 10.).round() / 10.),
+        };
+
+        GoalTargets {
+            direction,
+            weight_kg,
+            tdee_kcal: energy.tdee_kcal,
+            kcal_adjustment,
+            daily: MacroTargets {
+                kcal,
+                carbs: carbs.round(),
+                proteins: proteins.round(),
+                lipids: lipids.round(),
+            },
+            macro_overflow_kcal,
+            weeks_to_target,
+        }
+    }
+}
+
+/// Goal with the targets of the current profile, None when the energy
+/// expenditure can't be estimated.
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalWithTargets {
+    #[serde(flatten)]
+    pub goal: Goal,
+    pub targets: Option<GoalTargets>,
+}
+
+/// Intake of a UTC day against the daily targets.
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalDayProgress {
+    pub date: Date,
+    pub intake: NutritionTotals,
+    pub targets: Option<MacroTargets>,
+    /// Targets minus intake, negative once exceeded.
+    pub remaining: Option<MacroTargets>,
+}
+
+/// None when the energy expenditure can't be estimated.
+fn goal_targets(goal: &Goal, public_user: &PublicUser, weight_kg: f32) -> Option<GoalTargets> {
+    let body = BodyMetrics {
+        weight_kg,
+        ..BodyMetrics::from(public_user)
+    };
+    EnergyProfile::new(&body).map(|energy| GoalTargets::new(goal, &body, &energy))
+}
+
+pub struct GoalBmc {}
+
+impl GoalBmc {
+    /// A split given per kg must fit in the kcal target of the current
+    /// profile, a later weight change is reported by the targets.
+    pub async fn set(ctx: &Ctx, mm: &ModelManager, goal_c: GoalForSet) -> Result<()> {
+        goal_c.validate()?;
+
+        let public_user = PublicUserBmc::first_by_owner(ctx, mm).await?;
+        let goal = Goal {
+            owner: ctx.user_id(),
+            target_weight_kg: goal_c.target_weight_kg,
+            weekly_rate_kg: goal_c.weekly_rate_kg,
+            macro_split: goal_c.macro_split.clone(),
+            updated_at: now_utc(),
+        };
+        if let Some(targets) = goal_targets(&goal, &public_user, public_user.weight) {
+            if let Some(overflow) = targets.macro_overflow_kcal {
+                let mut errors = FieldErrors::default();
+                errors.push(
+                    "macro_split",
+                    FieldErrorKind::MacrosOverKcal {
+                        total_kcal: targets.daily.kcal + overflow,
+                        max_kcal: targets.daily.kcal,
+                    },
+                );
+                errors.into_result("goal")?;
+            }
+        }
+
+        let db = mm.db();
+
+        sqlx::query(
+            "INSERT INTO goal (owner, target_weight_kg, weekly_rate_kg, macro_split)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (owner) DO UPDATE SET
+                target_weight_kg = EXCLUDED.target_weight_kg,
+                weekly_rate_kg = EXCLUDED.weekly_rate_kg,
+                macro_split = EXCLUDED.macro_split,
+                updated_at = now()",
+        )
+        .bind(ctx.user_id())
+        .bind(goal_c.target_weight_kg)
+        .bind(goal_c.weekly_rate_kg)
+        .bind(Json(&goal_c.macro_split))
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager) -> Result<Goal> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, Goal>(&format!("SELECT {GOAL_COLUMNS} FROM goal WHERE owner = $1"))
+            .bind(ctx.user_id())
+            .fetch_optional(db)
+            .await?
+            .ok_or(Error::ItemNotFound {
+                entity: "goal",
+                id: ctx.user_id(),
+            })
+    }
+
+    /// Targets are derived on every read, so they follow the profile and
+    /// the latest logged weight.
+    pub async fn get_with_targets(ctx: &Ctx, mm: &ModelManager) -> Result<GoalWithTargets> {
+        let goal = GoalBmc::get(ctx, mm).await?;
+        let public_user = PublicUserBmc::first_by_owner(ctx, mm).await?;
+
+        let targets = goal_targets(&goal, &public_user, public_user.weight);
+
+        Ok(GoalWithTargets { goal, targets })
+    }
+
+    /// Targets of the day come from the weight in effect on that day.
+    pub async fn day_progress(ctx: &Ctx, mm: &ModelManager, date: Date) -> Result<GoalDayProgress> {
+        let goal = GoalBmc::get(ctx, mm).await?;
+        let public_user = PublicUserBmc::first_by_owner(ctx, mm).await?;
+        let weight_kg = WeightBmc::weight_on(ctx, mm, date)
+            .await?
+            .unwrap_or(public_user.weight);
+        let intake = MealBmc::daily_summary(ctx, mm, date, None).await?.totals;
+
+        let targets = goal_targets(&goal, &public_user, weight_kg).map(|targets| targets.daily);
+        let remaining = targets.as_ref().map(|targets| MacroTargets {
+            kcal: targets.kcal - intake.kcal as f64,
+            carbs: targets.carbs - intake.carbs as f64,
+            proteins: targets.proteins - intake.proteins as f64,
+            lipids: targets.lipids - intake.lipids as f64,
+        });
+
+        Ok(GoalDayProgress {
+            date,
+            intake,
+            targets,
+            remaining,
+        })
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query("DELETE FROM goal WHERE owner = $1")
+            .bind(ctx.user_id())
+            .execute(db)
+            .await?
+            .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "goal",
+                id: ctx.user_id(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serial_test::serial;
+    use time::{Date, Month};
+
+    use crate::{
+        _dev_utils::dev_init_tests,
+        ctx::Ctx,
+        model::{
+            energy::{ActivityLevel, BodyMetrics, EnergyProfile, Sex},
+            meal::{MealBmc, MealForCreate},
+            user::{user::UserBmc, FullUserBmc, FullUserForCreate},
+            weight::{WeightBmc, WeightLogForCreate},
+            Error,
+        },
+        utils::time_utils::now_utc,
+    };
+
+    use super::{Goal, GoalBmc, GoalDirection, GoalForSet, GoalTargets, MacroSplit, MacroTargets};
+
+    #[serial]
+    #[tokio::test]
+    async fn test_goal_targets_follow_weight() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let user_id = FullUserBmc::create_new_user(
+            &Ctx::root_ctx(),
+            &mm,
+            &FullUserForCreate {
+                username: "test_goal_targets".to_string(),
+                password_clear: "Welcome".to_string(),
+                age: 30,
+                size_cm: 180,
+                weight: 80.,
+                sex: Sex::Male,
+                activity_level: ActivityLevel::Moderate,
+                ..Default::default()
+            },
+        )
+        .await?;
+        let ctx = Ctx::new(user_id)?;
+        let fx_day = Date::from_calendar_date(2024, Month::March, 4)?;
+
+        let res = GoalBmc::set(
+            &ctx,
+            &mm,
+            GoalForSet {
+                target_weight_kg: 75.,
+                weekly_rate_kg: 0.5,
+                macro_split: MacroSplit::Percent {
+                    carbs: 50.,
+                    proteins: 30.,
+                    lipids: 30.,
+                },
+            },
+        )
+        .await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+
+        // 320 g of proteins and 240 g of lipids are 3440 kcal.
+        let res = GoalBmc::set(
+            &ctx,
+            &mm,
+            GoalForSet {
+                target_weight_kg: 75.,
+                weekly_rate_kg: 0.5,
+                macro_split: MacroSplit::GPerKg {
+                    proteins: 4.,
+                    lipids: 3.,
+                },
+            },
+        )
+        .await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+
+        GoalBmc::set(
+            &ctx,
+            &mm,
+            GoalForSet {
+                target_weight_kg: 75.,
+                weekly_rate_kg: 0.5,
+                macro_split: MacroSplit::GPerKg {
+                    proteins: 2.,
+                    lipids: 1.,
+                },
+            },
+        )
+        .await?;
+        let cutting = GoalBmc::get_with_targets(&ctx, &mm).await?.targets.unwrap();
+        // TDEE 1780 * 1.55 = 2759, minus 550 for 0.5 kg a week.
+        assert_eq!(cutting.direction, GoalDirection::Lose);
+        assert_eq!(
+            cutting.daily,
+            MacroTargets {
+                kcal: 2209.,
+                carbs: 212.,
+                proteins: 160.,
+                lipids: 80.,
+            }
+        );
+        assert_eq!(cutting.weeks_to_target, Some(10.));
+
+        let meal_id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_goal_targets".to_string(),
+                carbs: 100,
+                proteins: 50,
+                lipids: 20,
+                consumed_at: Some(fx_day.with_hms(12, 0, 0)?.assume_utc()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        let progress = GoalBmc::day_progress(&ctx, &mm, fx_day).await?;
+        assert_eq!(progress.intake.kcal, 780);
+        let remaining = progress.remaining.unwrap();
+        assert_eq!(remaining.kcal, 1429.);
+        assert_eq!(remaining.proteins, 110.);
+
+        WeightBmc::create(
+            &ctx,
+            &mm,
+            WeightLogForCreate {
+                weight_kg: 75.2,
+                logged_at: None,
+            },
+        )
+        .await?;
+        let reached = GoalBmc::get_with_targets(&ctx, &mm).await?.targets.unwrap();
+        // BMR 1732 at 75.2 kg.
+        assert_eq!(reached.direction, GoalDirection::Maintain);
+        assert_eq!(reached.daily.kcal, 2685.);
+        assert_eq!(reached.weeks_to_target, None);
+
+        // Days before the history keep its first weight, 80 kg.
+        let progress = GoalBmc::day_progress(&ctx, &mm, fx_day).await?;
+        assert_eq!(progress.targets.unwrap().kcal, 2209.);
+
+        WeightBmc::create(
+            &ctx,
+            &mm,
+            WeightLogForCreate {
+                weight_kg: 78.,
+                logged_at: Some(fx_day.with_hms(7, 0, 0)?.assume_utc()),
+            },
+        )
+        .await?;
+        // BMR 1760 at 78 kg, TDEE 2728 minus 550.
+        let progress = GoalBmc::day_progress(&ctx, &mm, fx_day).await?;
+        assert_eq!(progress.targets.unwrap().kcal, 2178.);
+
+        GoalBmc::delete(&ctx, &mm).await?;
+        let res = GoalBmc::get(&ctx, &mm).await;
+        assert!(matches!(res, Err(Error::ItemNotFound { .. })));
+
+        MealBmc::delete(&ctx, &mm, meal_id).await?;
+        MealBmc::purge(&ctx, &mm, meal_id).await?;
+        UserBmc::delete(&Ctx::root_ctx(), &mm, user_id).await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_targets_clamped_to_bmr() -> Result<()> {
+        let body = BodyMetrics {
+            sex: Sex::Male,
+            age: 30,
+            size_cm: 180,
+            weight_kg: 80.,
+            body_fat_pct: None,
+            activity_level: ActivityLevel::Sedentary,
+        };
+        let energy = EnergyProfile::new(&body).unwrap();
+        let goal = |macro_split| Goal {
+            owner: 0,
+            target_weight_kg: 70.,
+            weekly_rate_kg: 1.,
+            macro_split,
+            updated_at: now_utc(),
+        };
+
+        let balanced = GoalTargets::new(
+            &goal(MacroSplit::GPerKg {
+                proteins: 2.,
+                lipids: 1.,
+            }),
+            &body,
+            &energy,
+        );
+        let overflowing = GoalTargets::new(
+            &goal(MacroSplit::GPerKg {
+                proteins: 4.,
+                lipids: 3.,
+            }),
+            &body,
+            &energy,
+        );
+
+        // TDEE 2136 minus 1100 is under the BMR of 1780, the deficit of 356
+        // kcal is 0.32 kg a week.
+        assert_eq!(balanced.daily.kcal, 1780.);
+        assert_eq!(balanced.kcal_adjustment, -356.);
+        assert_eq!(balanced.weeks_to_target, Some(30.9));
+        assert_eq!(balanced.macro_overflow_kcal, None);
+        assert_eq!(overflowing.daily.carbs, 0.);
+        assert_eq!(overflowing.macro_overflow_kcal, Some(1660.));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use time::OffsetDateTime;
+
+use crate::ctx::Ctx;
+
+use crate::model::{Error, Result};
+
+use super::{
+    share::user_id_by_username,
+    validation::{FieldErrorKind, FieldErrors},
+    ModelManager,
+};
+
+const HOUSEHOLD_NAME_MAX_LEN: usize = 128;
+const HOUSEHOLD_MAX_MEMBERS: usize = 16;
+
+const HOUSEHOLD_COLUMNS: &str = "h.id, h.name, h.owner, h.created_at";
+
+/// Group of users, e.g. a family, meals and recipes can be shared with at
+/// once. Only its owner manages the members.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Household {
+    pub id: i64,
+    pub name: String,
+    pub owner: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct HouseholdMember {
+    pub user_id: i64,
+    pub username: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub joined_at: OffsetDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct HouseholdForCreate {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct HouseholdMemberForAdd {
+    pub username: String,
+}
+
+impl HouseholdForCreate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+        errors.check_text("name", &self.name, HOUSEHOLD_NAME_MAX_LEN);
+        errors.into_result("household")
+    }
+}
+
+pub struct HouseholdBmc {}
+
+impl HouseholdBmc {
+    /// The user creating the household owns it and is its first member.
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        household_c: HouseholdForCreate,
+    ) -> Result<i64> {
+        household_c.validate()?;
+
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO household (name, owner) VALUES ($1, $2) RETURNING id",
+        )
+        .bind(household_c.name.trim())
+        .bind(ctx.user_id())
+        .fetch_one(&mut transaction_manager)
+        .await?;
+
+        sqlx::query("INSERT INTO household_member (household_id, member) VALUES ($1, $2)")
+            .bind(id)
+            .bind(ctx.user_id())
+            .execute(&mut transaction_manager)
+            .await?;
+
+        transaction_manager.commit().await?;
+        Ok(id)
+    }
+
+    /// Only visible to its members.
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Household> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, Household>(&format!(
+            "SELECT {HOUSEHOLD_COLUMNS} FROM household h
+            JOIN household_member m ON m.household_id = h.id
+            WHERE h.id = $1 AND m.member = $2"
+        ))
+        .bind(id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "household",
+            id,
+        })
+    }
+
+    /// Household the user owns, `AccessDenied` for the other members.
+    async fn get_owned(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Household> {
+        let household = HouseholdBmc::get(ctx, mm, id).await?;
+
+        if household.owner == ctx.user_id() {
+            Ok(household)
+        } else {
+            Err(Error::AccessDenied {
+                entity: "household",
+                id,
+            })
+        }
+    }
+
+    /// Households the user is a member of.
+    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<Household>> {
+        let db = mm.db();
+
+        let households = sqlx::query_as(&format!(
+            "SELECT {HOUSEHOLD_COLUMNS} FROM household h
+            JOIN household_member m ON m.household_id = h.id
+            WHERE m.member = $1
+            ORDER BY h.name, h.id"
+        ))
+        .bind(ctx.user_id())
+        .fetch_all(db)
+        .await?;
+
+        Ok(households)
+    }
+
+    pub async fn list_members(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> Result<Vec<HouseholdMember>> {
+        HouseholdBmc::get(ctx, mm, id).await?;
+
+        let db = mm.db();
+
+        let members = sqlx::query_as(
+            "SELECT u.id AS user_id, u.username, m.joined_at
+            FROM household_member m JOIN \"user\" u ON u.id = m.member
+            WHERE m.household_id = $1
+            ORDER BY m.joined_at, u.id",
+        )
+        .bind(id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(members)
+    }
+
+    /// Add an account to the household, returns its user id. Adding a
+    /// member twice is a no-op.
+    pub async fn add_member(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        member_c: HouseholdMemberForAdd,
+    ) -> Result<i64> {
+        HouseholdBmc::get_owned(ctx, mm, id).await?;
+        let user_id = user_id_by_username(ctx, mm, &member_c.username, "household_member").await?;
+
+        let db = mm.db();
+
+        let (member_count,) = sqlx::query_as::<_, (i64,)>(
+            "SELECT COUNT(*) FROM household_member WHERE household_id = $1 AND member <> $2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(db)
+        .await?;
+
+        if member_count as usize >= HOUSEHOLD_MAX_MEMBERS {
+            let mut errors = FieldErrors::default();
+            errors.push(
+                "username",
+                FieldErrorKind::TooMany {
+                    max: HOUSEHOLD_MAX_MEMBERS,
+                },
+            );
+            errors.into_result("household_member")?;
+        }
+
+        sqlx::query(
+            "INSERT INTO household_member (household_id, member) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(db)
+        .await?;
+
+        Ok(user_id)
+    }
+
+    /// Members leave on their own, the owner removes anyone but themself.
+    /// The grants the member made to the household are revoked with it.
+    pub async fn remove_member(ctx: &Ctx, mm: &ModelManager, id: i64, user_id: i64) -> Result<()> {
+        let household = HouseholdBmc::get(ctx, mm, id).await?;
+
+        let is_owner = household.owner == ctx.user_id();
+        if user_id == household.owner || (!is_owner && user_id != ctx.user_id()) {
+            return Err(Error::AccessDenied {
+                entity: "household_member",
+                id: user_id,
+            });
+        }
+
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let count =
+            sqlx::query("DELETE FROM household_member WHERE household_id = $1 AND member = $2")
+                .bind(id)
+                .bind(user_id)
+                .execute(&mut transaction_manager)
+                .await?
+                .rows_affected();
+
+        if count == 0 {
+            return Err(Error::ItemNotFound {
+                entity: "household_member",
+                id: user_id,
+            });
+        }
+
+        sqlx::query("DELETE FROM share_grant WHERE household_id = $1 AND owner = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&mut transaction_manager)
+            .await?;
+
+        transaction_manager.commit().await?;
+        Ok(())
+    }
+
+    /// Deleting a household revokes every grant made to it.
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        HouseholdBmc::get_owned(ctx, mm, id).await?;
+
+        let db = mm.db();
+
+        sqlx::query("DELETE FROM household WHERE id = $1 AND owner = $2")
+            .bind(id)
+            .bind(ctx.user_id())
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+}
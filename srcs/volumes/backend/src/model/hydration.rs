@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use time::{Date, OffsetDateTime};
+
+use crate::ctx::Ctx;
+use crate::utils::time_utils::{day_range_utc, now_utc};
+
+use crate::model::{Error, Result};
+
+use super::{
+    unit::Unit,
+    user::public_user::PublicUserBmc,
+    validation::{FieldErrorKind, FieldErrors},
+    ModelManager,
+};
+
+/// Common guideline for the daily water intake, food moisture aside.
+pub const HYDRATION_ML_PER_KG: f64 = 35.;
+
+const WATER_LOG_MAX_VOLUME_ML: f64 = 5_000.;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct WaterLog {
+    pub id: i64,
+    pub owner: i64,
+    pub volume_ml: f32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub logged_at: OffsetDateTime,
+}
+
+/// The volume is given in millilitres or as an amount of a volume unit.
+#[derive(Deserialize, Default)]
+pub struct WaterLogForCreate {
+    pub volume_ml: Option<f32>,
+    /// Amount of `unit`, defaults to 1.
+    pub amount: Option<f32>,
+    pub unit: Option<Unit>,
+    /// Defaults to now.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub logged_at: Option<OffsetDateTime>,
+}
+
+impl WaterLogForCreate {
+    /// Validated volume in millilitres.
+    pub fn volume_ml(&self) -> Result<f32> {
+        let mut errors = FieldErrors::default();
+
+        let volume_ml = match (self.volume_ml, &self.unit) {
+            (Some(volume_ml), None) => f64::from(volume_ml),
+            (None, Some(unit)) => match unit.millilitres() {
+                Some(millilitres) => f64::from(self.amount.unwrap_or(1.)) * millilitres,
+                None => {
+                    errors.push(
+                        "unit",
+                        FieldErrorKind::UnconvertibleUnit {
+                            unit: unit.as_str().to_string(),
+                        },
+                    );
+                    0.
+                }
+            },
+            (Some(_), Some(_)) => {
+                errors.push("volume_ml", FieldErrorKind::Conflicting { with: "unit" });
+                0.
+            }
+            (None, None) => {
+                errors.push("volume_ml", FieldErrorKind::Missing);
+                0.
+            }
+        };
+        if errors.is_empty() {
+            errors.check_range("volume_ml", volume_ml, 1., WATER_LOG_MAX_VOLUME_ML);
+        }
+
+        errors.into_result("water_log")?;
+        Ok(volume_ml as f32)
+    }
+}
+
+/// Water of a UTC day, from the water log and the beverages logged as meals.
+#[derive(Debug, Clone, Serialize)]
+pub struct HydrationSummary {
+    pub date: Date,
+    pub water_ml: f64,
+    pub beverages_ml: f64,
+    pub total_ml: f64,
+    /// Derived from the body weight, None without a public profile.
+    pub target_ml: Option<f64>,
+    pub remaining_ml: Option<f64>,
+}
+
+pub struct HydrationBmc {}
+
+impl HydrationBmc {
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, water_c: WaterLogForCreate) -> Result<i64> {
+        let volume_ml = water_c.volume_ml()?;
+
+        let db = mm.db();
+
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO water_log (owner, volume_ml, logged_at) VALUES ($1, $2, $3) RETURNING id",
+        )
+        .bind(ctx.user_id())
+        .bind(volume_ml)
+        .bind(water_c.logged_at.unwrap_or_else(now_utc))
+        .fetch_one(db)
+        .await?;
+
+        Ok(id)
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<WaterLog> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, WaterLog>(
+            "SELECT id, owner, volume_ml, logged_at FROM water_log WHERE id = $1 AND owner = $2",
+        )
+        .bind(id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "water_log",
+            id,
+        })
+    }
+
+    /// Water logged on the given UTC day.
+    pub async fn list_by_date(ctx: &Ctx, mm: &ModelManager, date: Date) -> Result<Vec<WaterLog>> {
+        let db = mm.db();
+
+        let (start, end) = day_range_utc(date, date).ok_or(Error::InvalidDateRange {
+            from: date,
+            to: date,
+        })?;
+
+        let logs = sqlx::query_as(
+            "SELECT id, owner, volume_ml, logged_at FROM water_log
+            WHERE owner = $1 AND logged_at >= $2 AND logged_at < $3
+            ORDER BY logged_at, id",
+        )
+        .bind(ctx.user_id())
+        .bind(start)
+        .bind(end)
+        .fetch_all(db)
+        .await?;
+
+        Ok(logs)
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query("DELETE FROM water_log WHERE id = $1 AND owner = $2")
+            .bind(id)
+            .bind(ctx.user_id())
+            .execute(db)
+            .await?
+            .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "water_log",
+                id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn daily_summary(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        date: Date,
+    ) -> Result<HydrationSummary> {
+        let db = mm.db();
+
+        let (start, end) = day_range_utc(date, date).ok_or(Error::InvalidDateRange {
+            from: date,
+            to: date,
+        })?;
+
+        let (water_ml, beverages_ml) = sqlx::query_as::<_, (f64, f64)>(
+            "SELECT
+                (SELECT COALESCE(SUM(volume_ml), 0)::FLOAT8 FROM water_log
+                    WHERE owner = $1 AND logged_at >= $2 AND logged_at < $3),
+                (SELECT COALESCE(SUM(volume_ml), 0)::FLOAT8 FROM meal
+                    WHERE owner = $1 AND deleted_at IS NULL
+                        AND consumed_at >= $2 AND consumed_at < $3)",
+        )
+        .bind(ctx.user_id())
+        .bind(start)
+        .bind(end)
+        .fetch_one(db)
+        .await?;
+
+        let weight = match PublicUserBmc::first_by_owner(ctx, mm).await {
+            Ok(public_user) => Some(public_user.weight),
+            Err(Error::PublicUserNotFound { .. }) => None,
+            Err(err) => return Err(err),
+        };
+
+        let total_ml = water_ml + beverages_ml;
+        let target_ml = weight.map(|weight| (f64::from(weight) * HYDRATION_ML_PER_KG).round());
+
+        Ok(HydrationSummary {
+            date,
+            water_ml,
+            beverages_ml,
+            total_ml,
+            target_ml,
+            remaining_ml: target_ml.map(|target_ml| (target_ml - total_ml).max(0.)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        _dev_utils::dev_init_tests,
+        model::meal::{MealBmc, MealForCreate},
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_daily_summary_counts_beverages() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_day = Date::from_calendar_date(2021, time::Month::March, 4)?;
+        let fx_moment = fx_day.with_hms(9, 0, 0)?.assume_utc();
+
+        let water_id = HydrationBmc::create(
+            &ctx,
+            &mm,
+            WaterLogForCreate {
+                amount: Some(2.),
+                unit: Some(Unit::Cup),
+                logged_at: Some(fx_moment),
+                ..Default::default()
+            },
+        )
+        .await?;
+        let meal_id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_daily_summary_counts_beverages".to_string(),
+                kcal: Some(110),
+                carbs: 26,
+                consumed_at: Some(fx_moment),
+                volume_ml: Some(250.),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let summary = HydrationBmc::daily_summary(&ctx, &mm, fx_day).await?;
+
+        assert_eq!(summary.water_ml.round(), 473.);
+        assert_eq!(summary.beverages_ml, 250.);
+        // Seed weight of 86.4 kg.
+        assert_eq!(summary.target_ml, Some(3024.));
+        assert_eq!(summary.remaining_ml.map(f64::round), Some(2301.));
+
+        HydrationBmc::delete(&ctx, &mm, water_id).await?;
+        MealBmc::delete(&ctx, &mm, meal_id).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+use time::OffsetDateTime;
+
+use crate::utils::{
+    b64::{b64u_decode, b64u_encode},
+    time_utils::parse_time,
+};
+
+use crate::model::Result;
+
+use super::validation::{FieldErrorKind, FieldErrors};
+
+pub const LIST_DEFAULT_LIMIT: i64 = 50;
+pub const LIST_MAX_LIMIT: i64 = 500;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Type of a sort column, cursor values are parsed back to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortType {
+    BigInt,
+    Int,
+    Text,
+    /// RFC 3339, see `format_time`.
+    Timestamp,
+}
+
+/// Column a list can be sorted by. Ties are broken by id so every sort
+/// gives a total order usable for keyset pagination.
+pub trait SortField: Copy {
+    fn column(&self) -> &'static str;
+    fn sort_type(&self) -> SortType;
+}
+
+/// Conditions narrowing a list, pushed after a WHERE clause.
+pub trait ListFilter {
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>);
+
+    fn check(&self, _errors: &mut FieldErrors) {}
+}
+
+/// Page size, sort and filters of a list. The cursor is the `next_cursor`
+/// of the previous page, None for the first one.
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions<S, F> {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub sort: S,
+    pub order: SortOrder,
+    pub filter: F,
+}
+
+/// Position of the last item of a page, opaque to clients.
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    column: String,
+    value: String,
+    id: i64,
+}
+
+/// Cursor value parsed to the type of its column.
+#[derive(Debug, Clone, PartialEq)]
+enum SortValue {
+    BigInt(i64),
+    Int(i32),
+    Text(String),
+    Timestamp(OffsetDateTime),
+}
+
+impl SortType {
+    fn parse(&self, value: String) -> Option<SortValue> {
+        match self {
+            SortType::BigInt => value.parse().ok().map(SortValue::BigInt),
+            SortType::Int => value.parse().ok().map(SortValue::Int),
+            SortType::Text => Some(SortValue::Text(value)),
+            SortType::Timestamp => parse_time(&value).ok().map(SortValue::Timestamp),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// None on the last page.
+    pub next_cursor: Option<String>,
+}
+
+impl<S: SortField, F: ListFilter> ListOptions<S, F> {
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(LIST_DEFAULT_LIMIT)
+            .clamp(1, LIST_MAX_LIMIT)
+    }
+
+    /// The sort value and id of the cursor. A tampered value is rejected
+    /// here rather than failing the cast in the query.
+    fn cursor(&self, errors: &mut FieldErrors) -> Option<(SortValue, i64)> {
+        let cursor = b64u_decode(self.cursor.as_deref()?)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Cursor>(&json).ok())
+            // A cursor is only valid for the sort it was made with.
+            .filter(|cursor| cursor.column == self.sort.column())
+            .and_then(|cursor| Some((self.sort.sort_type().parse(cursor.value)?, cursor.id)));
+
+        if cursor.is_none() {
+            errors.push("cursor", FieldErrorKind::InvalidCursor);
+        }
+        cursor
+    }
+
+    /// Pushes the filters, the keyset condition, the sort and the limit
+    /// after a query ending with a WHERE clause condition.
+    pub fn push_page(
+        &self,
+        query: &mut QueryBuilder<'_, Postgres>,
+        entity: &'static str,
+    ) -> Result<()> {
+        let mut errors = FieldErrors::default();
+        let cursor = self.cursor(&mut errors);
+        self.filter.check(&mut errors);
+        errors.into_result(entity)?;
+
+        self.filter.push_conditions(query);
+
+        let column = self.sort.column();
+        let (comparison, direction) = match self.order {
+            SortOrder::Asc => (">", "ASC"),
+            SortOrder::Desc => ("<", "DESC"),
+        };
+
+        if let Some((value, id)) = cursor {
+            query.push(format!(" AND ({column}, id) {comparison} ("));
+            match value {
+                SortValue::BigInt(value) => query.push_bind(value),
+                SortValue::Int(value) => query.push_bind(value),
+                SortValue::Text(value) => query.push_bind(value),
+                SortValue::Timestamp(value) => query.push_bind(value),
+            };
+            query.push(", ").push_bind(id).push(")");
+        }
+
+        // One more item tells whether there is a next page.
+        query
+            .push(format!(
+                " ORDER BY {column} {direction}, id {direction} LIMIT "
+            ))
+            .push_bind(self.limit() + 1);
+
+        Ok(())
+    }
+
+    /// Trims the extra item fetched by `push_page` and makes the cursor of
+    /// the next page from the last item, given its sort value and id.
+    pub fn page<T>(&self, mut items: Vec<T>, key: impl Fn(&T, S) -> (String, i64)) -> Page<T> {
+        let limit = self.limit() as usize;
+        let next_cursor = (items.len() > limit).then(|| {
+            items.truncate(limit);
+            let (value, id) = key(&items[limit - 1], self.sort);
+            let cursor = Cursor {
+                column: self.sort.column().to_string(),
+                value,
+                id,
+            };
+            b64u_encode(&serde_json::to_string(&cursor).unwrap_or_default())
+        });
+
+        Page { items, next_cursor }
+    }
+}
+
+/// Pattern matching values containing `text`, for ILIKE.
+pub fn contains_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("%{escaped}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[derive(Debug, Clone, Copy, Default)]
+    struct FixtureSort;
+
+    impl SortField for FixtureSort {
+        fn column(&self) -> &'static str {
+            "kcal"
+        }
+
+        fn sort_type(&self) -> SortType {
+            SortType::Int
+        }
+    }
+
+    struct FixtureFilter;
+
+    impl ListFilter for FixtureFilter {
+        fn push_conditions(&self, _query: &mut QueryBuilder<'_, Postgres>) {}
+    }
+
+    fn fx_options(value: &str) -> ListOptions<FixtureSort, FixtureFilter> {
+        let cursor = Cursor {
+            column: "kcal".to_string(),
+            value: value.to_string(),
+            id: 7,
+        };
+        ListOptions {
+            limit: None,
+            cursor: Some(b64u_encode(&serde_json::to_string(&cursor).unwrap())),
+            sort: FixtureSort,
+            order: SortOrder::Asc,
+            filter: FixtureFilter,
+        }
+    }
+
+    #[test]
+    fn test_cursor_value_is_typed() -> Result<()> {
+        let mut errors = FieldErrors::default();
+        let cursor = fx_options("420").cursor(&mut errors);
+        assert_eq!(cursor, Some((SortValue::Int(420), 7)));
+        assert!(errors.is_empty());
+
+        let mut errors = FieldErrors::default();
+        assert!(fx_options("4e2x").cursor(&mut errors).is_none());
+        assert!(!errors.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_contains_pattern_escapes_wildcards() -> Result<()> {
+        assert_eq!(contains_pattern("50%_off"), "%50\\%\\_off%");
+
+        Ok(())
+    }
+}
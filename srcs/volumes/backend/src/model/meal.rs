@@ -1,11 +1,70 @@
+use std::num::NonZeroU32;
+
 use serde::{Deserialize, Serialize};
-use sqlx::prelude::FromRow;
+use sqlx::{prelude::FromRow, types::Json, Postgres, QueryBuilder, Transaction};
+use time::{Date, OffsetDateTime};
+use tracing::error;
 
+use crate::config::config;
 use crate::ctx::Ctx;
+use crate::utils::time_utils::{day_range_utc, format_time, now_utc};
 
 use crate::model::{Error, Result};
 
-use super::ModelManager;
+use super::{
+    alcohol::{AlcoholBmc, AlcoholSummary},
+    list_options::{contains_pattern, ListFilter, ListOptions, Page, SortField, SortType},
+    meal_entry::{MealEntry, MealEntryBmc, MealEntryForCreate},
+    nutrient::{Nutrients, NutrientsJson},
+    photo::MealPhotoBmc,
+    share::{without_foreign_foods, ShareBmc, SharePermission, ShareResource},
+    tag::{check_tags, normalize_tag, normalize_tags},
+    validation::{FieldErrorKind, FieldErrors},
+    ModelManager,
+};
+
+/// Moment of the day a meal belongs to, any other name is kept as a
+/// user defined slot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum MealSlot {
+    Breakfast,
+    Lunch,
+    Dinner,
+    #[default]
+    Snack,
+    Custom(String),
+}
+
+impl MealSlot {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MealSlot::Breakfast => "breakfast",
+            MealSlot::Lunch => "lunch",
+            MealSlot::Dinner => "dinner",
+            MealSlot::Snack => "snack",
+            MealSlot::Custom(name) => name,
+        }
+    }
+}
+
+impl From<String> for MealSlot {
+    fn from(value: String) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "breakfast" => MealSlot::Breakfast,
+            "lunch" => MealSlot::Lunch,
+            "dinner" => MealSlot::Dinner,
+            "snack" => MealSlot::Snack,
+            _ => MealSlot::Custom(value.trim().to_string()),
+        }
+    }
+}
+
+impl From<MealSlot> for String {
+    fn from(value: MealSlot) -> Self {
+        value.as_str().to_string()
+    }
+}
 
 #[derive(Debug, Clone, FromRow, Serialize)]
 pub struct Meal {
@@ -16,53 +75,666 @@ pub struct Meal {
     pub carbs: i32,
     pub proteins: i32,
     pub lipids: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub consumed_at: OffsetDateTime,
+    #[sqlx(try_from = "String")]
+    pub slot: MealSlot,
+    #[sqlx(try_from = "NutrientsJson")]
+    pub nutrients: Nutrients,
+    /// Volume of a beverage, counted toward hydration.
+    pub volume_ml: Option<f32>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
 }
 
-#[derive(Deserialize)]
+pub(in crate::model) const MEAL_COLUMNS: &str =
+    "id, owner, name, kcal, carbs, lipids, proteins, consumed_at, slot, nutrients, volume_ml, tags, notes";
+
+/// Meal in the trash, deleted for good at `purge_at`.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct TrashedMeal {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub meal: Meal,
+    #[serde(with = "time::serde::rfc3339")]
+    pub deleted_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub purge_at: OffsetDateTime,
+}
+
+/// Columns of `TrashedMeal` besides the meal ones, `$2` being the retention in days.
+const TRASH_COLUMNS: &str = "deleted_at, deleted_at + make_interval(days => $2) AS purge_at";
+
+/// When entries are given, kcal and macros are computed from the foods
+/// and the declared values are ignored. Without entries, kcal are derived
+/// from the macros when omitted.
+#[derive(Deserialize, Default)]
 pub struct MealForCreate {
     pub name: String,
-    pub kcal: i32,
+    #[serde(default)]
+    pub kcal: Option<i32>,
+    #[serde(default)]
     pub carbs: i32,
+    #[serde(default)]
     pub proteins: i32,
+    #[serde(default)]
     pub lipids: i32,
+    #[serde(default)]
+    pub entries: Vec<MealEntryForCreate>,
+    /// Defaults to now.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub consumed_at: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub slot: MealSlot,
+    #[serde(default)]
+    pub nutrients: Nutrients,
+    #[serde(default)]
+    pub volume_ml: Option<f32>,
+    /// Free-form labels like "eating out", normalized to lowercase.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct MealForUpdate {
     pub name: Option<String>,
     pub kcal: Option<i32>,
     pub carbs: Option<i32>,
     pub lipids: Option<i32>,
     pub proteins: Option<i32>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub consumed_at: Option<OffsetDateTime>,
+    pub slot: Option<MealSlot>,
+    /// Replaces the whole set of extended nutrients.
+    pub nutrients: Option<Nutrients>,
+    pub volume_ml: Option<f32>,
+    /// Replaces the whole set of tags.
+    pub tags: Option<Vec<String>>,
+    /// An empty text clears the notes.
+    pub notes: Option<String>,
+}
+
+/// Options when logging an existing meal again.
+#[derive(Deserialize, Default)]
+pub struct MealRelog {
+    /// Defaults to now.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub consumed_at: Option<OffsetDateTime>,
+    /// Defaults to the slot of the meal.
+    pub slot: Option<MealSlot>,
+    /// Portion of the original meal, defaults to 1.
+    pub multiplier: Option<f64>,
+}
+
+/// Copy of the meals of a day, or of one of its slots, to another day.
+/// Copies keep the time of day of their source.
+#[derive(Deserialize)]
+pub struct MealCopy {
+    pub to: Date,
+    pub slot: Option<MealSlot>,
+    /// Portion of the original meals, defaults to 1.
+    pub multiplier: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MealSort {
+    #[default]
+    Id,
+    Name,
+    Kcal,
+    ConsumedAt,
+}
+
+/// Every bound is optional, dates are UTC days included in the range.
+#[derive(Debug, Clone, Default)]
+pub struct MealFilter {
+    pub name_contains: Option<String>,
+    pub kcal_min: Option<i32>,
+    pub kcal_max: Option<i32>,
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+    pub tag: Option<String>,
+}
+
+pub type MealListOptions = ListOptions<MealSort, MealFilter>;
+
+impl Meal {
+    /// Payload logging this meal again with its portions scaled by `factor`,
+    /// entries are scaled instead of the totals when the meal has some.
+    pub fn to_create(&self, entries: &[MealEntry], factor: f64) -> MealForCreate {
+        let scale = |value: i32| (f64::from(value) * factor).round() as i32;
+
+        MealForCreate {
+            name: self.name.clone(),
+            kcal: Some(scale(self.kcal)),
+            carbs: scale(self.carbs),
+            proteins: scale(self.proteins),
+            lipids: scale(self.lipids),
+            entries: entries
+                .iter()
+                .filter_map(|e| MealEntryForCreate::scaled(e.food_id, e.quantity_g, factor))
+                .collect(),
+            consumed_at: None,
+            slot: self.slot.clone(),
+            nutrients: self.nutrients.scaled(factor),
+            volume_ml: self.volume_ml.map(|volume_ml| volume_ml * factor as f32),
+            tags: self.tags.clone(),
+            notes: self.notes.clone(),
+        }
+    }
+}
+
+/// Atwater general factors, in kcal per gram.
+pub const KCAL_PER_G_CARBS: i64 = 4;
+pub const KCAL_PER_G_PROTEINS: i64 = 4;
+pub const KCAL_PER_G_LIPIDS: i64 = 9;
+pub const KCAL_PER_G_ALCOHOL: f64 = 7.;
+
+const MEAL_NAME_MAX_LEN: usize = 128;
+const MEAL_SLOT_MAX_LEN: usize = 64;
+const MEAL_MAX_KCAL: f64 = 20_000.;
+const MEAL_MAX_MACRO_G: f64 = 2_000.;
+const MEAL_MAX_VOLUME_ML: f64 = 10_000.;
+const MEAL_NOTES_MAX_LEN: usize = 2_000;
+const MEAL_MIN_MULTIPLIER: f64 = 0.05;
+const MEAL_MAX_MULTIPLIER: f64 = 20.;
+
+/// Declared kcal may differ from the computed ones by the largest of both.
+const KCAL_MISMATCH_MIN: i64 = 20;
+const KCAL_MISMATCH_RATIO: f64 = 0.15;
+
+/// Alcohol is kept with the extended nutrients, in grams.
+pub fn kcal_from_macros(carbs: i32, proteins: i32, lipids: i32, alcohol_g: f64) -> i32 {
+    let kcal = i64::from(carbs) * KCAL_PER_G_CARBS
+        + i64::from(proteins) * KCAL_PER_G_PROTEINS
+        + i64::from(lipids) * KCAL_PER_G_LIPIDS
+        + (alcohol_g * KCAL_PER_G_ALCOHOL).round() as i64;
+
+    kcal.clamp(0, i32::MAX.into()) as i32
+}
+
+/// Flag declared kcal contradicting the macros, skipped when no macro is known.
+fn check_kcal_consistency(
+    errors: &mut FieldErrors,
+    kcal: i32,
+    carbs: i32,
+    proteins: i32,
+    lipids: i32,
+    alcohol_g: f64,
+) {
+    if carbs == 0 && proteins == 0 && lipids == 0 && alcohol_g == 0. {
+        return;
+    }
+
+    let computed = kcal_from_macros(carbs, proteins, lipids, alcohol_g);
+    let tolerance = KCAL_MISMATCH_MIN.max((f64::from(computed) * KCAL_MISMATCH_RATIO) as i64);
+
+    if (i64::from(kcal) - i64::from(computed)).abs() > tolerance {
+        errors.push(
+            "kcal",
+            FieldErrorKind::KcalMismatch {
+                declared: kcal,
+                computed,
+            },
+        );
+    }
+}
+
+fn check_slot(errors: &mut FieldErrors, slot: &MealSlot) {
+    if let MealSlot::Custom(name) = slot {
+        errors.check_text("slot", name, MEAL_SLOT_MAX_LEN);
+    }
+}
+
+fn check_volume(errors: &mut FieldErrors, volume_ml: Option<f32>) {
+    if let Some(volume_ml) = volume_ml {
+        errors.check_range("volume_ml", volume_ml.into(), 0., MEAL_MAX_VOLUME_ML);
+    }
+}
+
+fn check_notes(errors: &mut FieldErrors, notes: Option<&str>) {
+    if notes.is_some_and(|notes| notes.chars().count() > MEAL_NOTES_MAX_LEN) {
+        errors.push(
+            "notes",
+            FieldErrorKind::TooLong {
+                max: MEAL_NOTES_MAX_LEN,
+            },
+        );
+    }
+}
+
+/// Blank notes are stored as no notes.
+fn clean_notes(notes: String) -> Option<String> {
+    let notes = notes.trim();
+    (!notes.is_empty()).then(|| notes.to_string())
+}
+
+fn check_multiplier(errors: &mut FieldErrors, multiplier: Option<f64>) {
+    if let Some(multiplier) = multiplier {
+        errors.check_range(
+            "multiplier",
+            multiplier,
+            MEAL_MIN_MULTIPLIER,
+            MEAL_MAX_MULTIPLIER,
+        );
+    }
+}
+
+fn check_macros(errors: &mut FieldErrors, carbs: Option<i32>, proteins: Option<i32>, lipids: Option<i32>) {
+    let macros = [("carbs", carbs), ("proteins", proteins), ("lipids", lipids)];
+
+    for (field, value) in macros {
+        if let Some(value) = value {
+            errors.check_range(field, value.into(), 0., MEAL_MAX_MACRO_G);
+        }
+    }
+}
+
+impl MealForCreate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        errors.check_text("name", &self.name, MEAL_NAME_MAX_LEN);
+        check_slot(&mut errors, &self.slot);
+        self.nutrients.check(&mut errors);
+        check_volume(&mut errors, self.volume_ml);
+        check_tags(&mut errors, &normalize_tags(&self.tags));
+        check_notes(&mut errors, self.notes.as_deref());
+
+        if self.entries.is_empty() {
+            if let Some(kcal) = self.kcal {
+                errors.check_range("kcal", kcal.into(), 0., MEAL_MAX_KCAL);
+            }
+            check_macros(&mut errors, Some(self.carbs), Some(self.proteins), Some(self.lipids));
+
+            if let (Some(kcal), true) = (self.kcal, errors.is_empty()) {
+                check_kcal_consistency(
+                    &mut errors,
+                    kcal,
+                    self.carbs,
+                    self.proteins,
+                    self.lipids,
+                    self.nutrients.alcohol_g(),
+                );
+            }
+        }
+
+        for entry_c in &self.entries {
+            entry_c.check(&mut errors, "entries.quantity_g");
+        }
+
+        errors.into_result("meal")
+    }
+
+    /// Declared kcal, or the ones computed from the macros.
+    pub fn kcal_or_computed(&self) -> i32 {
+        self.kcal.unwrap_or_else(|| {
+            kcal_from_macros(
+                self.carbs,
+                self.proteins,
+                self.lipids,
+                self.nutrients.alcohol_g(),
+            )
+        })
+    }
+}
+
+impl MealForUpdate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        if let Some(name) = &self.name {
+            errors.check_text("name", name, MEAL_NAME_MAX_LEN);
+        }
+        if let Some(slot) = &self.slot {
+            check_slot(&mut errors, slot);
+        }
+        if let Some(nutrients) = &self.nutrients {
+            nutrients.check(&mut errors);
+        }
+        check_volume(&mut errors, self.volume_ml);
+        if let Some(tags) = &self.tags {
+            check_tags(&mut errors, &normalize_tags(tags));
+        }
+        check_notes(&mut errors, self.notes.as_deref());
+        if let Some(kcal) = self.kcal {
+            errors.check_range("kcal", kcal.into(), 0., MEAL_MAX_KCAL);
+        }
+        check_macros(&mut errors, self.carbs, self.proteins, self.lipids);
+
+        errors.into_result("meal")
+    }
+
+    fn has_macros(&self) -> bool {
+        self.carbs.is_some() || self.proteins.is_some() || self.lipids.is_some()
+    }
+}
+
+impl MealRelog {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        if let Some(slot) = &self.slot {
+            check_slot(&mut errors, slot);
+        }
+        check_multiplier(&mut errors, self.multiplier);
+
+        errors.into_result("meal_relog")
+    }
+}
+
+impl MealCopy {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        if let Some(slot) = &self.slot {
+            check_slot(&mut errors, slot);
+        }
+        check_multiplier(&mut errors, self.multiplier);
+
+        errors.into_result("meal_copy")
+    }
+}
+
+impl SortField for MealSort {
+    fn column(&self) -> &'static str {
+        match self {
+            MealSort::Id => "id",
+            MealSort::Name => "name",
+            MealSort::Kcal => "kcal",
+            MealSort::ConsumedAt => "consumed_at",
+        }
+    }
+
+    fn sort_type(&self) -> SortType {
+        match self {
+            MealSort::Id => SortType::BigInt,
+            MealSort::Name => SortType::Text,
+            MealSort::Kcal => SortType::Int,
+            MealSort::ConsumedAt => SortType::Timestamp,
+        }
+    }
+}
+
+impl MealSort {
+    fn cursor_value(&self, meal: &Meal) -> String {
+        match self {
+            MealSort::Id => meal.id.to_string(),
+            MealSort::Name => meal.name.clone(),
+            MealSort::Kcal => meal.kcal.to_string(),
+            MealSort::ConsumedAt => format_time(meal.consumed_at),
+        }
+    }
+}
+
+impl ListFilter for MealFilter {
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(name) = &self.name_contains {
+            query
+                .push(" AND name ILIKE ")
+                .push_bind(contains_pattern(name.trim()));
+        }
+        if let Some(kcal_min) = self.kcal_min {
+            query.push(" AND kcal >= ").push_bind(kcal_min);
+        }
+        if let Some(kcal_max) = self.kcal_max {
+            query.push(" AND kcal <= ").push_bind(kcal_max);
+        }
+        if let Some(from) = self.from {
+            query
+                .push(" AND consumed_at >= ")
+                .push_bind(from.midnight().assume_utc());
+        }
+        if let Some(to) = self.to.and_then(|to| to.next_day()) {
+            query
+                .push(" AND consumed_at < ")
+                .push_bind(to.midnight().assume_utc());
+        }
+        if let Some(tag) = &self.tag {
+            query
+                .push(" AND tags @> ARRAY[")
+                .push_bind(normalize_tag(tag))
+                .push("]::TEXT[]");
+        }
+    }
+
+    fn check(&self, errors: &mut FieldErrors) {
+        if let (Some(min), Some(max)) = (self.kcal_min, self.kcal_max) {
+            if min > max {
+                errors.push(
+                    "kcal_min",
+                    FieldErrorKind::ReversedRange { upper: "kcal_max" },
+                );
+            }
+        }
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                errors.push("from", FieldErrorKind::ReversedRange { upper: "to" });
+            }
+        }
+        if let Some(tag) = &self.tag {
+            check_tags(errors, &[normalize_tag(tag)]);
+        }
+    }
+}
+
+/// Sums of kcal and macros over a set of meals.
+#[derive(Debug, Clone, Default, FromRow, Serialize)]
+pub struct NutritionTotals {
+    pub kcal: i64,
+    pub carbs: i64,
+    pub proteins: i64,
+    pub lipids: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlotSummary {
+    pub slot: MealSlot,
+    pub meal_count: i64,
+    #[serde(flatten)]
+    pub totals: NutritionTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KcalTargetComparison {
+    pub target: u32,
+    /// Consumed kcal minus target, negative while under target.
+    pub delta: i64,
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailySummary {
+    pub date: Date,
+    pub meal_count: i64,
+    pub totals: NutritionTotals,
+    /// Extended nutrients summed over the meals declaring them.
+    pub nutrients: Nutrients,
+    pub slots: Vec<SlotSummary>,
+    pub kcal_target: Option<KcalTargetComparison>,
+    pub alcohol: AlcoholSummary,
+}
+
+/// Row of the daily summary query, the grand total row has no slot.
+#[derive(FromRow)]
+struct SummaryRow {
+    slot: Option<String>,
+    meal_count: i64,
+    #[sqlx(flatten)]
+    totals: NutritionTotals,
 }
 
 pub struct MealBmc {}
 
 impl MealBmc {
     pub async fn create(ctx: &Ctx, mm: &ModelManager, meal_c: MealForCreate) -> Result<i64> {
-        let db = mm.db();
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let id = MealBmc::insert(ctx, &mut transaction_manager, meal_c).await?;
+
+        transaction_manager.commit().await?;
+        Ok(id)
+    }
+
+    pub(in crate::model) async fn insert(
+        ctx: &Ctx,
+        transaction_manager: &mut Transaction<'_, Postgres>,
+        meal_c: MealForCreate,
+    ) -> Result<i64> {
+        meal_c.validate()?;
 
         let (id, ) = sqlx::query_as::<_, (i64,)>(
-            "INSERT INTO meal (name, kcal, carbs, lipids, proteins, owner) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id"
+            "INSERT INTO meal (name, kcal, carbs, lipids, proteins, owner, consumed_at, slot, nutrients, volume_ml,
+                tags, notes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) RETURNING id"
             )
-            .bind(meal_c.name)
-            .bind(meal_c.kcal)
+            .bind(&meal_c.name)
+            .bind(meal_c.kcal_or_computed())
             .bind(meal_c.carbs)
             .bind(meal_c.lipids)
             .bind(meal_c.proteins)
             .bind(ctx.user_id())
-            .fetch_one(db)
+            .bind(meal_c.consumed_at.unwrap_or_else(now_utc))
+            .bind(meal_c.slot.as_str())
+            .bind(Json(&meal_c.nutrients))
+            .bind(meal_c.volume_ml)
+            .bind(normalize_tags(&meal_c.tags))
+            .bind(meal_c.notes.clone().and_then(clean_notes))
+            .fetch_one(&mut *transaction_manager)
             .await?;
+
+        if !meal_c.entries.is_empty() {
+            for entry_c in meal_c.entries {
+                MealEntryBmc::insert(ctx, transaction_manager, id, entry_c).await?;
+            }
+            MealBmc::refresh_totals(ctx, transaction_manager, id).await?;
+        }
+
         Ok(id)
     }
 
+    /// Log a meal again, with its entries when it has some. A meal shared
+    /// with the `log` permission is logged into the diary of the user,
+    /// without the notes and tags of its owner.
+    pub async fn relog(ctx: &Ctx, mm: &ModelManager, id: i64, relog: MealRelog) -> Result<i64> {
+        relog.validate()?;
+
+        let meal = MealBmc::get_shared(ctx, mm, id, SharePermission::Log).await?;
+        let entries = MealEntryBmc::list(ctx, mm, id).await?;
+        let mut meal_c = meal.to_create(&entries, relog.multiplier.unwrap_or(1.));
+        if meal.owner != ctx.user_id() {
+            meal_c.notes = None;
+            meal_c.tags = Vec::new();
+        }
+
+        let meal_c = MealForCreate {
+            consumed_at: relog.consumed_at,
+            slot: relog.slot.unwrap_or_else(|| meal.slot.clone()),
+            ..without_foreign_foods(ctx, mm, meal_c).await?
+        };
+
+        MealBmc::create(ctx, mm, meal_c).await
+    }
+
+    /// Copy the meals of a UTC day to another one in a single transaction,
+    /// returns the ids of the copies. The source meals and their entries are
+    /// read in the transaction and locked until the copies are inserted.
+    pub async fn copy_day(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        from: Date,
+        copy: MealCopy,
+    ) -> Result<Vec<i64>> {
+        copy.validate()?;
+
+        let (start, end) =
+            day_range_utc(from, from).ok_or(Error::InvalidDateRange { from, to: from })?;
+        let factor = copy.multiplier.unwrap_or(1.);
+
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let meals: Vec<Meal> = sqlx::query_as(&format!(
+            "SELECT {MEAL_COLUMNS} FROM meal
+            WHERE owner = $1 AND deleted_at IS NULL AND consumed_at >= $2 AND consumed_at < $3
+            ORDER BY consumed_at, id
+            FOR SHARE"
+        ))
+        .bind(ctx.user_id())
+        .bind(start)
+        .bind(end)
+        .fetch_all(&mut transaction_manager)
+        .await?;
+
+        let meals: Vec<Meal> = meals
+            .into_iter()
+            .filter(|meal| !copy.slot.as_ref().is_some_and(|slot| *slot != meal.slot))
+            .collect();
+        let meal_ids: Vec<i64> = meals.iter().map(|meal| meal.id).collect();
+        let entries = MealEntryBmc::list_for_copy(&mut transaction_manager, &meal_ids).await?;
+
+        let mut ids = Vec::with_capacity(meals.len());
+        for meal in meals {
+            let meal_entries: Vec<MealEntry> = entries
+                .iter()
+                .filter(|entry| entry.meal_id == meal.id)
+                .cloned()
+                .collect();
+            let meal_c = MealForCreate {
+                consumed_at: Some(copy.to.with_time(meal.consumed_at.time()).assume_utc()),
+                ..meal.to_create(&meal_entries, factor)
+            };
+            ids.push(MealBmc::insert(ctx, &mut transaction_manager, meal_c).await?);
+        }
+
+        transaction_manager.commit().await?;
+        Ok(ids)
+    }
+
+    /// Recompute kcal, macros and alcohol of a meal from its entries.
+    pub(in crate::model) async fn refresh_totals(
+        ctx: &Ctx,
+        transaction_manager: &mut Transaction<'_, Postgres>,
+        id: i64,
+    ) -> Result<()> {
+        let count = sqlx::query(
+            "UPDATE meal SET kcal = totals.kcal, carbs = totals.carbs,
+                lipids = totals.lipids, proteins = totals.proteins,
+                nutrients = CASE WHEN totals.alcohol > 0
+                    THEN meal.nutrients || jsonb_build_object('alcohol', totals.alcohol)
+                    ELSE meal.nutrients - 'alcohol' END
+            FROM (
+                SELECT COALESCE(ROUND(SUM(f.kcal_100g * e.quantity_g / 100)), 0)::INT AS kcal,
+                    COALESCE(ROUND(SUM(f.carbs_100g * e.quantity_g / 100)), 0)::INT AS carbs,
+                    COALESCE(ROUND(SUM(f.lipids_100g * e.quantity_g / 100)), 0)::INT AS lipids,
+                    COALESCE(ROUND(SUM(f.proteins_100g * e.quantity_g / 100)), 0)::INT AS proteins,
+                    COALESCE(SUM(f.alcohol_100g * e.quantity_g / 100), 0)::FLOAT8 AS alcohol
+                FROM meal_entry e JOIN food f ON f.id = e.food_id
+                WHERE e.meal_id = $1
+            ) AS totals
+            WHERE meal.id = $1 AND meal.owner = $2 AND meal.deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(ctx.user_id())
+        .execute(&mut *transaction_manager)
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound { entity: "meal", id })
+        } else {
+            Ok(())
+        }
+    }
+
     pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Meal> {
         let db = mm.db();
 
-        sqlx::query_as::<_, Meal>(
-            "SELECT id, name, kcal, carbs, lipids, proteins, owner
-            FROM meal WHERE id = $1 and owner = $2",
-        )
+        sqlx::query_as::<_, Meal>(&format!(
+            "SELECT {MEAL_COLUMNS} FROM meal WHERE id = $1 and owner = $2 AND deleted_at IS NULL"
+        ))
         .bind(id)
         .bind(ctx.user_id())
         .fetch_optional(db)
@@ -70,39 +742,267 @@ impl MealBmc {
         .ok_or(Error::ItemNotFound { entity: "meal", id })
     }
 
-    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<Meal>> {
+    /// Meal owned by the user, or shared with them with at least `permission`.
+    pub async fn get_shared(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        permission: SharePermission,
+    ) -> Result<Meal> {
         let db = mm.db();
 
-        let meals = sqlx::query_as(
-            "SELECT id, name, kcal, carbs, lipids, proteins, owner
-            FROM meal WHERE owner = $1 ORDER BY id",
-        )
+        let meal = sqlx::query_as::<_, Meal>(&format!(
+            "SELECT {MEAL_COLUMNS} FROM meal WHERE id = $1 AND deleted_at IS NULL"
+        ))
+        .bind(id)
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound { entity: "meal", id })?;
+
+        if meal.owner != ctx.user_id() {
+            ShareBmc::require(ctx, mm, ShareResource::Meal, id, permission).await?;
+        }
+
+        Ok(meal)
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        options: MealListOptions,
+    ) -> Result<Page<Meal>> {
+        let db = mm.db();
+
+        let mut query = QueryBuilder::new(format!(
+            "SELECT {MEAL_COLUMNS} FROM meal WHERE deleted_at IS NULL AND owner = "
+        ));
+        query.push_bind(ctx.user_id());
+        options.push_page(&mut query, "meal_list")?;
+
+        let meals = query.build_query_as().fetch_all(db).await?;
+
+        Ok(options.page(meals, |meal: &Meal, sort| {
+            (sort.cursor_value(meal), meal.id)
+        }))
+    }
+
+    /// Meals consumed on the given UTC day.
+    pub async fn list_by_date(ctx: &Ctx, mm: &ModelManager, date: Date) -> Result<Vec<Meal>> {
+        MealBmc::list_by_range(ctx, mm, date, date).await
+    }
+
+    /// Meals consumed between two UTC days, both included.
+    pub async fn list_by_range(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        from: Date,
+        to: Date,
+    ) -> Result<Vec<Meal>> {
+        let db = mm.db();
+
+        let (start, end) = day_range_utc(from, to).ok_or(Error::InvalidDateRange { from, to })?;
+
+        let meals = sqlx::query_as(&format!(
+            "SELECT {MEAL_COLUMNS} FROM meal
+            WHERE owner = $1 AND deleted_at IS NULL AND consumed_at >= $2 AND consumed_at < $3
+            ORDER BY consumed_at, id"
+        ))
         .bind(ctx.user_id())
+        .bind(start)
+        .bind(end)
         .fetch_all(db)
         .await?;
 
         Ok(meals)
     }
 
+    /// Kcal and macros of a UTC day, in total and per slot, optionally
+    /// compared with a kcal target, and the standard drinks of the day.
+    pub async fn daily_summary(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        date: Date,
+        kcal_target: Option<NonZeroU32>,
+    ) -> Result<DailySummary> {
+        let db = mm.db();
+
+        let (start, end) = day_range_utc(date, date).ok_or(Error::InvalidDateRange {
+            from: date,
+            to: date,
+        })?;
+
+        let rows: Vec<SummaryRow> = sqlx::query_as(
+            "SELECT slot, COUNT(id) AS meal_count,
+                COALESCE(SUM(kcal), 0)::BIGINT AS kcal,
+                COALESCE(SUM(carbs), 0)::BIGINT AS carbs,
+                COALESCE(SUM(proteins), 0)::BIGINT AS proteins,
+                COALESCE(SUM(lipids), 0)::BIGINT AS lipids
+            FROM meal
+            WHERE owner = $1 AND deleted_at IS NULL AND consumed_at >= $2 AND consumed_at < $3
+            GROUP BY GROUPING SETS ((slot), ())
+            ORDER BY GROUPING(slot), MIN(consumed_at)",
+        )
+        .bind(ctx.user_id())
+        .bind(start)
+        .bind(end)
+        .fetch_all(db)
+        .await?;
+
+        let nutrients: Vec<(String, f64)> = sqlx::query_as(
+            "SELECT n.key, SUM(n.value::FLOAT8)
+            FROM meal, jsonb_each_text(meal.nutrients) AS n
+            WHERE owner = $1 AND deleted_at IS NULL AND consumed_at >= $2 AND consumed_at < $3
+            GROUP BY n.key",
+        )
+        .bind(ctx.user_id())
+        .bind(start)
+        .bind(end)
+        .fetch_all(db)
+        .await?;
+
+        let mut meal_count = 0;
+        let mut totals = NutritionTotals::default();
+        let mut slots = Vec::new();
+
+        for row in rows {
+            match row.slot {
+                Some(slot) => slots.push(SlotSummary {
+                    slot: MealSlot::from(slot),
+                    meal_count: row.meal_count,
+                    totals: row.totals,
+                }),
+                None => {
+                    meal_count = row.meal_count;
+                    totals = row.totals;
+                }
+            }
+        }
+
+        let kcal_target = kcal_target.map(|target| {
+            let target = target.get();
+            KcalTargetComparison {
+                target,
+                delta: totals.kcal - i64::from(target),
+                percent: totals.kcal as f64 * 100. / f64::from(target),
+            }
+        });
+
+        Ok(DailySummary {
+            date,
+            meal_count,
+            totals,
+            nutrients: Nutrients(nutrients.into_iter().collect()),
+            slots,
+            kcal_target,
+            alcohol: AlcoholBmc::summary(ctx, mm, date).await?,
+        })
+    }
+
     pub async fn update(
         ctx: &Ctx,
         mm: &ModelManager,
         id: i64,
         meal_u: MealForUpdate,
     ) -> Result<()> {
+        meal_u.validate()?;
+
         let meal_to_update = MealBmc::get(ctx, mm, id).await?;
 
-        let db = mm.db();
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let (has_entries,) = sqlx::query_as::<_, (bool,)>(
+            "SELECT EXISTS (SELECT 1 FROM meal_entry WHERE meal_id = $1)",
+        )
+        .bind(id)
+        .fetch_one(&mut transaction_manager)
+        .await?;
+
+        // The totals of a meal with entries are derived from them.
+        if has_entries {
+            let mut errors = FieldErrors::default();
+            let totals = [
+                ("kcal", meal_u.kcal),
+                ("carbs", meal_u.carbs),
+                ("proteins", meal_u.proteins),
+                ("lipids", meal_u.lipids),
+            ];
+            for (field, value) in totals {
+                if value.is_some() {
+                    errors.push(field, FieldErrorKind::Conflicting { with: "entries" });
+                }
+            }
+            errors.into_result("meal")?;
+        }
+
+        let carbs = meal_u.carbs.unwrap_or(meal_to_update.carbs);
+        let lipids = meal_u.lipids.unwrap_or(meal_to_update.lipids);
+        let proteins = meal_u.proteins.unwrap_or(meal_to_update.proteins);
+        let previous_alcohol_g = meal_to_update.nutrients.alcohol_g();
+        let nutrients = meal_u.nutrients.clone().unwrap_or(meal_to_update.nutrients);
+        let alcohol_g = nutrients.alcohol_g();
+
+        let kcal = match meal_u.kcal {
+            Some(kcal) => {
+                let mut errors = FieldErrors::default();
+                check_kcal_consistency(&mut errors, kcal, carbs, proteins, lipids, alcohol_g);
+                errors.into_result("meal")?;
+                kcal
+            }
+            // Alcohol carries energy, changing it changes the kcal too.
+            None if meal_u.has_macros() || alcohol_g != previous_alcohol_g => {
+                kcal_from_macros(carbs, proteins, lipids, alcohol_g)
+            }
+            None => meal_to_update.kcal,
+        };
 
         let count = sqlx::query(
-            "UPDATE meal SET name = $1, kcal = $2, carbs = $3, lipids = $4, proteins = $5
-            WHERE id = $6 AND owner = $7",
+            "UPDATE meal SET name = $1, kcal = $2, carbs = $3, lipids = $4, proteins = $5,
+                consumed_at = $6, slot = $7, nutrients = $8, volume_ml = $9, tags = $10, notes = $11
+            WHERE id = $12 AND owner = $13 AND deleted_at IS NULL",
         )
         .bind(meal_u.name.unwrap_or(meal_to_update.name))
-        .bind(meal_u.kcal.unwrap_or(meal_to_update.kcal))
-        .bind(meal_u.carbs.unwrap_or(meal_to_update.carbs))
-        .bind(meal_u.lipids.unwrap_or(meal_to_update.lipids))
-        .bind(meal_u.proteins.unwrap_or(meal_to_update.proteins))
+        .bind(kcal)
+        .bind(carbs)
+        .bind(lipids)
+        .bind(proteins)
+        .bind(meal_u.consumed_at.unwrap_or(meal_to_update.consumed_at))
+        .bind(String::from(meal_u.slot.unwrap_or(meal_to_update.slot)))
+        .bind(Json(nutrients))
+        .bind(meal_u.volume_ml.or(meal_to_update.volume_ml))
+        .bind(
+            meal_u
+                .tags
+                .map_or(meal_to_update.tags, |tags| normalize_tags(&tags)),
+        )
+        .bind(meal_u.notes.map_or(meal_to_update.notes, clean_notes))
+        .bind(id)
+        .bind(ctx.user_id())
+        .execute(&mut transaction_manager)
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            return Err(Error::ItemNotFound { entity: "meal", id });
+        }
+
+        // Replaced nutrients must not drop the alcohol of the entries.
+        if has_entries {
+            MealBmc::refresh_totals(ctx, &mut transaction_manager, id).await?;
+        }
+
+        transaction_manager.commit().await?;
+        Ok(())
+    }
+
+    /// Move the meal to the trash, it is kept with its entries and photos
+    /// until restored or purged.
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query(
+            "UPDATE meal SET deleted_at = now()
+            WHERE id = $1 AND owner = $2 AND deleted_at IS NULL",
+        )
         .bind(id)
         .bind(ctx.user_id())
         .execute(db)
@@ -116,22 +1016,119 @@ impl MealBmc {
         }
     }
 
-    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+    // -- Trash
+
+    pub async fn get_trashed(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<TrashedMeal> {
         let db = mm.db();
 
-        let count = sqlx::query("DELETE FROM meal WHERE id = $1 AND owner = $2")
-            .bind(id)
-            .bind(ctx.user_id())
-            .execute(db)
-            .await?
-            .rows_affected();
+        sqlx::query_as::<_, TrashedMeal>(&format!(
+            "SELECT {MEAL_COLUMNS}, {TRASH_COLUMNS} FROM meal
+            WHERE owner = $1 AND deleted_at IS NOT NULL AND id = $3"
+        ))
+        .bind(ctx.user_id())
+        .bind(config().MEAL_TRASH_RETENTION_DAYS)
+        .bind(id)
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "trashed_meal",
+            id,
+        })
+    }
+
+    /// Meals in the trash, latest deleted first.
+    pub async fn list_trash(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<TrashedMeal>> {
+        let db = mm.db();
+
+        let meals = sqlx::query_as(&format!(
+            "SELECT {MEAL_COLUMNS}, {TRASH_COLUMNS} FROM meal
+            WHERE owner = $1 AND deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC, id DESC"
+        ))
+        .bind(ctx.user_id())
+        .bind(config().MEAL_TRASH_RETENTION_DAYS)
+        .fetch_all(db)
+        .await?;
+
+        Ok(meals)
+    }
+
+    pub async fn restore(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query(
+            "UPDATE meal SET deleted_at = NULL
+            WHERE id = $1 AND owner = $2 AND deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .bind(ctx.user_id())
+        .execute(db)
+        .await?
+        .rows_affected();
 
         if count == 0 {
-            Err(Error::ItemNotFound { entity: "meal", id })
+            Err(Error::ItemNotFound {
+                entity: "trashed_meal",
+                id,
+            })
         } else {
             Ok(())
         }
     }
+
+    /// Delete a meal of the trash for good, with its entries and photos.
+    pub async fn purge(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let db = mm.db();
+
+        let count =
+            sqlx::query("DELETE FROM meal WHERE id = $1 AND owner = $2 AND deleted_at IS NOT NULL")
+                .bind(id)
+                .bind(ctx.user_id())
+                .execute(db)
+                .await?
+                .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "trashed_meal",
+                id,
+            })
+        } else {
+            MealPhotoBmc::remove_meal_files(ctx.user_id(), id).await
+        }
+    }
+
+    /// Delete for good the meals moved to the trash before `deleted_before`,
+    /// of every user for the root context and of the user otherwise. Returns
+    /// how many were purged, a failure to remove photo files is only logged.
+    pub async fn purge_trash(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        deleted_before: OffsetDateTime,
+    ) -> Result<usize> {
+        let db = mm.db();
+
+        let owner = (ctx.user_id() != Ctx::root_ctx().user_id()).then(|| ctx.user_id());
+        let purged: Vec<(i64, i64)> = sqlx::query_as(
+            "DELETE FROM meal WHERE deleted_at < $1 AND ($2::BIGINT IS NULL OR owner = $2)
+            RETURNING id, owner",
+        )
+        .bind(deleted_before)
+        .bind(owner)
+        .fetch_all(db)
+        .await?;
+
+        for (id, owner) in &purged {
+            if let Err(err) = MealPhotoBmc::remove_meal_files(*owner, *id).await {
+                error!(
+                    "{:<12} - Removing the photos of meal {id} failed: {err}",
+                    "MODEL"
+                );
+            }
+        }
+
+        Ok(purged.len())
+    }
 }
 
 #[cfg(test)]
@@ -139,7 +1136,14 @@ mod tests {
     #![allow(unused)]
     use std::{thread::sleep, time::Duration};
 
-    use crate::{_dev_utils::dev_init_tests, ctx};
+    use crate::{
+        _dev_utils::dev_init_tests,
+        ctx,
+        model::{
+            list_options::SortOrder,
+            user::{user::UserBmc, FullUserBmc, FullUserForCreate},
+        },
+    };
 
     use super::*;
     use anyhow::{Ok, Result};
@@ -153,17 +1157,18 @@ mod tests {
         let ctx = Ctx::demo1_ctx();
 
         let fixture_name = "test_create_ok title";
-        let fixture_kcal = 155;
+        let fixture_kcal = 480;
         let fixture_carbs = 15;
         let fixture_lipids = 28;
         let fixture_proteins = 42;
 
         let meal_c = MealForCreate {
             name: fixture_name.to_string(),
-            kcal: fixture_kcal,
+            kcal: Some(fixture_kcal),
             carbs: fixture_carbs,
             lipids: fixture_lipids,
             proteins: fixture_proteins,
+            ..Default::default()
         };
 
         let id = MealBmc::create(&ctx, &mm, meal_c).await?;
@@ -242,41 +1247,45 @@ mod tests {
         let mm = dev_init_tests().await;
         let ctx = Ctx::demo1_ctx();
 
-        let fixture_kcal = 155;
+        let fixture_kcal = 480;
         let fixture_carbs = 15;
         let fixture_lipids = 28;
         let fixture_proteins = 42;
 
         let meal_c_first = MealForCreate {
             name: "test_list_ok title - Task1".to_string(),
-            kcal: fixture_kcal,
+            kcal: Some(fixture_kcal),
             carbs: fixture_carbs,
             lipids: fixture_lipids,
             proteins: fixture_proteins,
+            ..Default::default()
         };
 
         let meal_c_second = MealForCreate {
             name: "test_list_ok title - Task2".to_string(),
-            kcal: fixture_kcal,
+            kcal: Some(fixture_kcal),
             carbs: fixture_carbs,
             lipids: fixture_lipids,
             proteins: fixture_proteins,
+            ..Default::default()
         };
 
         let meal_c_third = MealForCreate {
             name: "test_list_ok title - Task3".to_string(),
-            kcal: fixture_kcal,
+            kcal: Some(fixture_kcal),
             carbs: fixture_carbs,
             lipids: fixture_lipids,
             proteins: fixture_proteins,
+            ..Default::default()
         };
 
         let id = MealBmc::create(&ctx, &mm, meal_c_first).await?;
         let id = MealBmc::create(&ctx, &mm, meal_c_second).await?;
         let id = MealBmc::create(&ctx, &mm, meal_c_third).await?;
 
-        let tasks = MealBmc::list(&ctx, &mm).await?;
+        let tasks = MealBmc::list(&ctx, &mm, MealListOptions::default()).await?;
         let filtered_tasks: Vec<Meal> = tasks
+            .items
             .into_iter()
             .filter(|t| t.name.starts_with("test_list_ok title"))
             .collect();
@@ -285,4 +1294,450 @@ mod tests {
 
         Ok(())
     }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_list_pages_sorted_filtered() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_kcals = [300, 100, 500, 200, 900];
+
+        let mut ids = Vec::new();
+        for kcal in fx_kcals {
+            let meal_c = MealForCreate {
+                name: format!("test_list_pages_sorted_filtered {kcal}"),
+                kcal: Some(kcal),
+                ..Default::default()
+            };
+            ids.push(MealBmc::create(&ctx, &mm, meal_c).await?);
+        }
+
+        let mut options = MealListOptions {
+            limit: Some(2),
+            sort: MealSort::Kcal,
+            order: SortOrder::Desc,
+            filter: MealFilter {
+                name_contains: Some("list_pages_sorted".to_string()),
+                kcal_max: Some(800),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut kcals = Vec::new();
+        loop {
+            let page = MealBmc::list(&ctx, &mm, options.clone()).await?;
+            kcals.extend(page.items.iter().map(|meal| meal.kcal));
+            match page.next_cursor {
+                Some(cursor) => options.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(kcals, [500, 300, 200, 100]);
+
+        // A cursor only works with the sort it was made for.
+        options.sort = MealSort::Name;
+        let res = MealBmc::list(&ctx, &mm, options).await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+
+        for id in ids {
+            MealBmc::delete(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_tags_and_notes() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_tags = [
+            vec!["Eating out ", "cheat", "eating out"],
+            vec!["post-workout"],
+        ];
+
+        let mut ids = Vec::new();
+        for tags in fx_tags {
+            let meal_c = MealForCreate {
+                name: "test_tags_and_notes".to_string(),
+                kcal: Some(700),
+                tags: tags.into_iter().map(String::from).collect(),
+                notes: Some("Birthday at the ramen place".to_string()),
+                ..Default::default()
+            };
+            ids.push(MealBmc::create(&ctx, &mm, meal_c).await?);
+        }
+
+        let meal = MealBmc::get(&ctx, &mm, ids[0]).await?;
+        assert_eq!(meal.tags, ["cheat", "eating out"]);
+        assert_eq!(meal.notes.as_deref(), Some("Birthday at the ramen place"));
+
+        let options = MealListOptions {
+            filter: MealFilter {
+                name_contains: Some("test_tags_and_notes".to_string()),
+                tag: Some("EATING OUT".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let page = MealBmc::list(&ctx, &mm, options).await?;
+        let listed: Vec<i64> = page.items.iter().map(|meal| meal.id).collect();
+        assert_eq!(listed, [ids[0]]);
+
+        let meal_u = MealForUpdate {
+            name: None,
+            kcal: None,
+            carbs: None,
+            lipids: None,
+            proteins: None,
+            consumed_at: None,
+            slot: None,
+            nutrients: None,
+            volume_ml: None,
+            tags: Some(vec!["x".repeat(33)]),
+            notes: Some("  ".to_string()),
+        };
+        let res = MealBmc::update(&ctx, &mm, ids[1], meal_u).await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+
+        let meal_u = MealForUpdate {
+            name: None,
+            kcal: None,
+            carbs: None,
+            lipids: None,
+            proteins: None,
+            consumed_at: None,
+            slot: None,
+            nutrients: None,
+            volume_ml: None,
+            tags: None,
+            notes: Some("  ".to_string()),
+        };
+        MealBmc::update(&ctx, &mm, ids[1], meal_u).await?;
+        let meal = MealBmc::get(&ctx, &mm, ids[1]).await?;
+        assert_eq!(meal.tags, ["post-workout"]);
+        assert_eq!(meal.notes, None);
+
+        for id in ids {
+            MealBmc::delete(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_list_by_date_ok() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_day = Date::from_calendar_date(2024, time::Month::March, 10)?;
+        let fx_moments = [
+            (fx_day.with_hms(8, 0, 0)?, "breakfast"),
+            (fx_day.with_hms(23, 30, 0)?, "late dinner"),
+            (fx_day.next_day().unwrap().with_hms(0, 10, 0)?, "snack"),
+        ];
+
+        let mut ids = Vec::new();
+        for (moment, slot) in fx_moments {
+            let meal_c = MealForCreate {
+                name: "test_list_by_date_ok".to_string(),
+                kcal: Some(100),
+                consumed_at: Some(moment.assume_utc()),
+                slot: MealSlot::from(slot.to_string()),
+                ..Default::default()
+            };
+            ids.push(MealBmc::create(&ctx, &mm, meal_c).await?);
+        }
+
+        let day = MealBmc::list_by_date(&ctx, &mm, fx_day).await?;
+        let range = MealBmc::list_by_range(&ctx, &mm, fx_day, fx_day.next_day().unwrap()).await?;
+
+        assert_eq!(day.len(), 2);
+        assert_eq!(day[0].slot, MealSlot::Breakfast);
+        assert_eq!(day[1].slot, MealSlot::Custom("late dinner".to_string()));
+        assert_eq!(range.len(), 3);
+
+        for id in ids {
+            MealBmc::delete(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_list_by_range_reversed() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_from = Date::from_calendar_date(2024, time::Month::March, 10)?;
+        let fx_to = Date::from_calendar_date(2024, time::Month::March, 9)?;
+
+        let res = MealBmc::list_by_range(&ctx, &mm, fx_from, fx_to).await;
+
+        assert!(
+            matches!(res, Err(Error::InvalidDateRange { .. })),
+            "Reversed range should be rejected"
+        );
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_daily_summary_ok() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_day = Date::from_calendar_date(2023, time::Month::July, 14)?;
+        let fx_meals = [
+            (fx_day.with_hms(7, 0, 0)?, MealSlot::Breakfast, 75),
+            (fx_day.with_hms(12, 0, 0)?, MealSlot::Lunch, 150),
+            (fx_day.with_hms(12, 30, 0)?, MealSlot::Lunch, 40),
+        ];
+
+        let mut ids = Vec::new();
+        for (moment, slot, carbs) in fx_meals {
+            let meal_c = MealForCreate {
+                name: "test_daily_summary_ok".to_string(),
+                carbs,
+                consumed_at: Some(moment.assume_utc()),
+                slot,
+                nutrients: Nutrients([("sodium".to_string(), 400.)].into()),
+                ..Default::default()
+            };
+            ids.push(MealBmc::create(&ctx, &mm, meal_c).await?);
+        }
+
+        let summary = MealBmc::daily_summary(&ctx, &mm, fx_day, NonZeroU32::new(2120)).await?;
+        assert_eq!(summary.nutrients.0.get("sodium"), Some(&1200.));
+
+        assert_eq!(summary.meal_count, 3);
+        assert_eq!(summary.totals.kcal, 1060);
+        assert_eq!(summary.totals.carbs, 265);
+        assert_eq!(summary.slots.len(), 2);
+        assert_eq!(summary.slots[0].slot, MealSlot::Breakfast);
+        assert_eq!(summary.slots[1].meal_count, 2);
+        assert_eq!(summary.slots[1].totals.kcal, 760);
+
+        let target = summary.kcal_target.expect("Should compare with target");
+        assert_eq!(target.delta, -1060);
+        assert_eq!(target.percent, 50.);
+
+        for id in ids {
+            MealBmc::delete(&ctx, &mm, id).await?;
+        }
+
+        let empty = MealBmc::daily_summary(&ctx, &mm, fx_day, None).await?;
+        assert_eq!(empty.meal_count, 0);
+        assert!(empty.slots.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_rejects_impossible_values() -> Result<()> {
+        let meal_c = MealForCreate {
+            name: " ".to_string(),
+            kcal: Some(-20),
+            carbs: 10,
+            ..Default::default()
+        };
+
+        let res = meal_c.validate();
+
+        let Err(Error::Validation { errors, .. }) = res else {
+            panic!("Should have failed validation, was {res:?}");
+        };
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, ["name", "kcal"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_kcal_mismatch() -> Result<()> {
+        let fx_meal = |kcal| MealForCreate {
+            name: "test_validate_kcal_mismatch".to_string(),
+            kcal: Some(kcal),
+            carbs: 50,
+            proteins: 30,
+            lipids: 20,
+            ..Default::default()
+        };
+
+        fx_meal(530).validate()?;
+
+        let res = fx_meal(155).validate();
+        assert!(
+            matches!(
+                &res,
+                Err(Error::Validation { errors, .. }) if matches!(
+                    errors[0].kind,
+                    FieldErrorKind::KcalMismatch { declared: 155, computed: 500 }
+                )
+            ),
+            "Should flag the mismatch, was {res:?}"
+        );
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_kcal_derived_from_macros() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+
+        let meal_c = MealForCreate {
+            name: "test_kcal_derived_from_macros".to_string(),
+            carbs: 50,
+            proteins: 30,
+            lipids: 20,
+            ..Default::default()
+        };
+        let id = MealBmc::create(&ctx, &mm, meal_c).await?;
+        assert_eq!(MealBmc::get(&ctx, &mm, id).await?.kcal, 500);
+
+        let meal_u = MealForUpdate {
+            name: None,
+            kcal: None,
+            carbs: None,
+            lipids: Some(10),
+            proteins: None,
+            consumed_at: None,
+            slot: None,
+            nutrients: None,
+            volume_ml: None,
+            tags: None,
+            notes: None,
+        };
+        MealBmc::update(&ctx, &mm, id, meal_u).await?;
+        assert_eq!(MealBmc::get(&ctx, &mm, id).await?.kcal, 410);
+
+        MealBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_copy_day_slot_scaled() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_from = Date::from_calendar_date(2023, time::Month::March, 6)?;
+        let fx_to = Date::from_calendar_date(2023, time::Month::March, 7)?;
+        let fx_meals = [
+            (fx_from.with_hms(8, 0, 0)?, MealSlot::Breakfast),
+            (fx_from.with_hms(13, 15, 0)?, MealSlot::Lunch),
+            (fx_from.with_hms(13, 45, 0)?, MealSlot::Lunch),
+        ];
+
+        let mut ids = Vec::new();
+        for (moment, slot) in fx_meals {
+            let meal_c = MealForCreate {
+                name: "test_copy_day_slot_scaled".to_string(),
+                kcal: Some(500),
+                consumed_at: Some(moment.assume_utc()),
+                slot,
+                ..Default::default()
+            };
+            ids.push(MealBmc::create(&ctx, &mm, meal_c).await?);
+        }
+
+        let copy = MealCopy {
+            to: fx_to,
+            slot: Some(MealSlot::Lunch),
+            multiplier: Some(2.),
+        };
+        let copy_ids = MealBmc::copy_day(&ctx, &mm, fx_from, copy).await?;
+
+        let copies = MealBmc::list_by_date(&ctx, &mm, fx_to).await?;
+        assert_eq!(copy_ids.len(), 2);
+        assert_eq!(copies.len(), 2);
+        assert_eq!(copies[0].kcal, 1000);
+        assert_eq!(copies[0].slot, MealSlot::Lunch);
+        assert_eq!(
+            copies[1].consumed_at,
+            fx_to.with_hms(13, 45, 0)?.assume_utc()
+        );
+
+        for id in ids.into_iter().chain(copy_ids) {
+            MealBmc::delete(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_trash_restore_purge() -> Result<()> {
+        let mm = dev_init_tests().await;
+        // Purges only reach the trash of their user.
+        let user_id = FullUserBmc::create_new_user(
+            &Ctx::root_ctx(),
+            &mm,
+            &FullUserForCreate {
+                username: "test_trash_restore_purge".to_string(),
+                password_clear: "Welcome".to_string(),
+                age: 30,
+                size_cm: 170,
+                weight: 70.,
+                ..Default::default()
+            },
+        )
+        .await?;
+        let ctx = Ctx::new(user_id)?;
+        let demo1_ctx = Ctx::demo1_ctx();
+        let fx_day = Date::from_calendar_date(2023, time::Month::May, 2)?;
+        let fx_meal_c = || -> Result<MealForCreate> {
+            Ok(MealForCreate {
+                name: "test_trash_restore_purge".to_string(),
+                kcal: Some(300),
+                consumed_at: Some(fx_day.with_hms(12, 0, 0)?.assume_utc()),
+                ..Default::default()
+            })
+        };
+        let id = MealBmc::create(&ctx, &mm, fx_meal_c()?).await?;
+        let demo1_id = MealBmc::create(&demo1_ctx, &mm, fx_meal_c()?).await?;
+        MealBmc::delete(&demo1_ctx, &mm, demo1_id).await?;
+
+        MealBmc::delete(&ctx, &mm, id).await?;
+
+        let res = MealBmc::get(&ctx, &mm, id).await;
+        assert!(matches!(res, Err(Error::ItemNotFound { .. })));
+        assert!(MealBmc::list_by_date(&ctx, &mm, fx_day).await?.is_empty());
+        let summary = MealBmc::daily_summary(&ctx, &mm, fx_day, None).await?;
+        assert_eq!(summary.meal_count, 0);
+        let res = MealBmc::delete(&ctx, &mm, id).await;
+        assert!(matches!(res, Err(Error::ItemNotFound { .. })));
+
+        let trashed = MealBmc::get_trashed(&ctx, &mm, id).await?;
+        assert_eq!(trashed.meal.kcal, 300);
+        assert_eq!(
+            trashed.purge_at - trashed.deleted_at,
+            time::Duration::days(config().MEAL_TRASH_RETENTION_DAYS.into())
+        );
+        let trash = MealBmc::list_trash(&ctx, &mm).await?;
+        assert!(trash.iter().any(|t| t.meal.id == id));
+
+        MealBmc::restore(&ctx, &mm, id).await?;
+        assert_eq!(MealBmc::list_by_date(&ctx, &mm, fx_day).await?.len(), 1);
+
+        // Only meals deleted before the cutoff are purged.
+        MealBmc::delete(&ctx, &mm, id).await?;
+        let deleted_at = MealBmc::get_trashed(&ctx, &mm, id).await?.deleted_at;
+        MealBmc::purge_trash(&ctx, &mm, deleted_at).await?;
+        MealBmc::get_trashed(&ctx, &mm, id).await?;
+        let purged = MealBmc::purge_trash(&ctx, &mm, now_utc()).await?;
+        assert_eq!(purged, 1);
+
+        let res = MealBmc::restore(&ctx, &mm, id).await;
+        assert!(matches!(res, Err(Error::ItemNotFound { .. })));
+        MealBmc::get_trashed(&demo1_ctx, &mm, demo1_id).await?;
+
+        MealBmc::purge(&demo1_ctx, &mm, demo1_id).await?;
+        UserBmc::delete(&Ctx::root_ctx(), &mm, user_id).await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,531 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, Postgres, Transaction};
+
+use crate::ctx::Ctx;
+
+use crate::model::{Error, Result};
+
+use super::{
+    meal::MealBmc,
+    share::SharePermission,
+    unit::{Quantity, UnitBmc},
+    validation::FieldErrors,
+    ModelManager,
+};
+
+/// A food eaten as part of a meal, nutrition is computed from the food
+/// per 100 g values and the quantity.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct MealEntry {
+    pub id: i64,
+    pub meal_id: i64,
+    pub food_id: i64,
+    pub food_name: String,
+    pub quantity_g: f32,
+    pub kcal: f32,
+    pub carbs: f32,
+    pub proteins: f32,
+    pub lipids: f32,
+}
+
+/// The quantity is given in grams or in any unit the food can be measured
+/// in, it is stored in grams.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MealEntryForCreate {
+    pub food_id: i64,
+    #[serde(flatten)]
+    pub quantity: Quantity,
+}
+
+#[derive(Deserialize)]
+pub struct MealEntryForUpdate {
+    pub food_id: Option<i64>,
+    /// Kept as is when empty.
+    #[serde(flatten)]
+    pub quantity: Quantity,
+}
+
+pub(in crate::model) const ENTRY_MIN_QUANTITY_G: f64 = 0.1;
+pub(in crate::model) const ENTRY_MAX_QUANTITY_G: f64 = 10_000.;
+
+impl MealEntryForCreate {
+    /// Entry with its quantity scaled, None when it falls under the entry
+    /// minimum and weighs nothing worth logging.
+    pub fn scaled(food_id: i64, quantity_g: f32, factor: f64) -> Option<Self> {
+        let quantity_g = f64::from(quantity_g) * factor;
+
+        (quantity_g >= ENTRY_MIN_QUANTITY_G).then_some(MealEntryForCreate {
+            food_id,
+            quantity: Quantity::grams(quantity_g as f32),
+        })
+    }
+
+    pub(in crate::model) fn check(&self, errors: &mut FieldErrors, field: &'static str) {
+        self.quantity.check(errors, field);
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+        self.check(&mut errors, "quantity_g");
+        errors.into_result("meal_entry")
+    }
+}
+
+impl MealEntryForUpdate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+        if !self.quantity.is_empty() {
+            self.quantity.check(&mut errors, "quantity_g");
+        }
+        errors.into_result("meal_entry")
+    }
+}
+
+const MEAL_ENTRY_SELECT: &str = "SELECT e.id, e.meal_id, e.food_id, f.name AS food_name, e.quantity_g,
+        (f.kcal_100g * e.quantity_g / 100)::REAL AS kcal,
+        (f.carbs_100g * e.quantity_g / 100)::REAL AS carbs,
+        (f.proteins_100g * e.quantity_g / 100)::REAL AS proteins,
+        (f.lipids_100g * e.quantity_g / 100)::REAL AS lipids
+    FROM meal_entry e
+    JOIN food f ON f.id = e.food_id
+    JOIN meal m ON m.id = e.meal_id";
+
+pub struct MealEntryBmc {}
+
+impl MealEntryBmc {
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        meal_id: i64,
+        entry_c: MealEntryForCreate,
+    ) -> Result<i64> {
+        entry_c.validate()?;
+        MealBmc::get(ctx, mm, meal_id).await?;
+
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let id = MealEntryBmc::insert(ctx, &mut transaction_manager, meal_id, entry_c).await?;
+        MealBmc::refresh_totals(ctx, &mut transaction_manager, meal_id).await?;
+
+        transaction_manager.commit().await?;
+        Ok(id)
+    }
+
+    /// Insert an entry without refreshing the meal totals, the meal ownership
+    /// must already have been checked by the caller.
+    pub(in crate::model) async fn insert(
+        ctx: &Ctx,
+        transaction_manager: &mut Transaction<'_, Postgres>,
+        meal_id: i64,
+        entry_c: MealEntryForCreate,
+    ) -> Result<i64> {
+        let food_id = entry_c.food_id;
+        let quantity_g = UnitBmc::quantity_g(
+            ctx,
+            transaction_manager,
+            food_id,
+            &entry_c.quantity,
+            "meal_entry",
+            "quantity_g",
+        )
+        .await?;
+
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO meal_entry (meal_id, food_id, quantity_g)
+            SELECT $1, id, $2 FROM food WHERE id = $3 AND (owner IS NULL OR owner = $4)
+            RETURNING id",
+        )
+        .bind(meal_id)
+        .bind(quantity_g)
+        .bind(food_id)
+        .bind(ctx.user_id())
+        .fetch_optional(&mut *transaction_manager)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "food",
+            id: food_id,
+        })?;
+
+        Ok(id)
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, meal_id: i64, id: i64) -> Result<MealEntry> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, MealEntry>(&format!(
+            "{MEAL_ENTRY_SELECT} WHERE e.id = $1 AND e.meal_id = $2 AND m.owner = $3
+                AND m.deleted_at IS NULL"
+        ))
+        .bind(id)
+        .bind(meal_id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "meal_entry",
+            id,
+        })
+    }
+
+    /// Entries of a meal of the user, or of a meal shared with them.
+    pub async fn list(ctx: &Ctx, mm: &ModelManager, meal_id: i64) -> Result<Vec<MealEntry>> {
+        MealBmc::get_shared(ctx, mm, meal_id, SharePermission::Read).await?;
+
+        let db = mm.db();
+
+        let entries = sqlx::query_as(&format!(
+            "{MEAL_ENTRY_SELECT} WHERE e.meal_id = $1 ORDER BY e.id"
+        ))
+        .bind(meal_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Entries of meals already checked by the caller, locked until the
+    /// end of its transaction.
+    pub(in crate::model) async fn list_for_copy(
+        transaction_manager: &mut Transaction<'_, Postgres>,
+        meal_ids: &[i64],
+    ) -> Result<Vec<MealEntry>> {
+        let entries = sqlx::query_as(&format!(
+            "{MEAL_ENTRY_SELECT} WHERE e.meal_id = ANY($1) ORDER BY e.id FOR SHARE OF e"
+        ))
+        .bind(meal_ids)
+        .fetch_all(transaction_manager)
+        .await?;
+
+        Ok(entries)
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        meal_id: i64,
+        id: i64,
+        entry_u: MealEntryForUpdate,
+    ) -> Result<()> {
+        entry_u.validate()?;
+
+        let entry_to_update = MealEntryBmc::get(ctx, mm, meal_id, id).await?;
+        let food_id = entry_u.food_id.unwrap_or(entry_to_update.food_id);
+
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let quantity_g = if entry_u.quantity.is_empty() {
+            entry_to_update.quantity_g
+        } else {
+            UnitBmc::quantity_g(
+                ctx,
+                &mut transaction_manager,
+                food_id,
+                &entry_u.quantity,
+                "meal_entry",
+                "quantity_g",
+            )
+            .await?
+        };
+
+        let count = sqlx::query(
+            "UPDATE meal_entry SET food_id = food.id, quantity_g = $1
+            FROM food
+            WHERE meal_entry.id = $2 AND meal_entry.meal_id = $3
+                AND food.id = $4 AND (food.owner IS NULL OR food.owner = $5)",
+        )
+        .bind(quantity_g)
+        .bind(id)
+        .bind(meal_id)
+        .bind(food_id)
+        .bind(ctx.user_id())
+        .execute(&mut transaction_manager)
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            return Err(Error::ItemNotFound {
+                entity: "food",
+                id: food_id,
+            });
+        }
+
+        MealBmc::refresh_totals(ctx, &mut transaction_manager, meal_id).await?;
+
+        transaction_manager.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, meal_id: i64, id: i64) -> Result<()> {
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let count = sqlx::query(
+            "DELETE FROM meal_entry USING meal
+            WHERE meal_entry.id = $1 AND meal_entry.meal_id = $2
+                AND meal.id = meal_entry.meal_id AND meal.owner = $3 AND meal.deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(meal_id)
+        .bind(ctx.user_id())
+        .execute(&mut transaction_manager)
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            return Err(Error::ItemNotFound {
+                entity: "meal_entry",
+                id,
+            });
+        }
+
+        MealBmc::refresh_totals(ctx, &mut transaction_manager, meal_id).await?;
+
+        transaction_manager.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        _dev_utils::dev_init_tests,
+        model::{
+            food::{FoodBmc, FoodForCreate},
+            food_portion::{FoodPortionBmc, FoodPortionForCreate},
+            meal::{MealForCreate, MealForUpdate},
+            unit::Unit,
+        },
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    async fn fixture_food(ctx: &Ctx, mm: &ModelManager, name: &str) -> Result<i64> {
+        let id = FoodBmc::create(
+            ctx,
+            mm,
+            FoodForCreate {
+                name: name.to_string(),
+                kcal_100g: 200.,
+                carbs_100g: 20.,
+                proteins_100g: 10.,
+                lipids_100g: 8.,
+                alcohol_100g: 0.,
+                density_g_ml: Some(0.5),
+            },
+        )
+        .await?;
+
+        Ok(id)
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_meal_with_entries_ok() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let food_id = fixture_food(&ctx, &mm, "test_create_meal_with_entries_ok food").await?;
+
+        let meal_id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_create_meal_with_entries_ok".to_string(),
+                entries: vec![
+                    MealEntryForCreate {
+                        food_id,
+                        quantity: Quantity::grams(150.),
+                    },
+                    MealEntryForCreate {
+                        food_id,
+                        quantity: Quantity::grams(50.),
+                    },
+                ],
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let meal = MealBmc::get(&ctx, &mm, meal_id).await?;
+        let entries = MealEntryBmc::list(&ctx, &mm, meal_id).await?;
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(meal.kcal, 400);
+        assert_eq!(meal.carbs, 40);
+        assert_eq!(meal.proteins, 20);
+        assert_eq!(meal.lipids, 16);
+
+        // Totals derived from the entries can't be overwritten.
+        let res = MealBmc::update(
+            &ctx,
+            &mm,
+            meal_id,
+            MealForUpdate {
+                kcal: Some(100),
+                name: Some("renamed".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(
+            matches!(&res, Err(Error::Validation { errors, .. }) if errors[0].field == "kcal"),
+            "Should reject the kcal, was {res:?}"
+        );
+        assert_eq!(MealBmc::get(&ctx, &mm, meal_id).await?.name, meal.name);
+
+        MealBmc::delete(&ctx, &mm, meal_id).await?;
+        MealBmc::purge(&ctx, &mm, meal_id).await?;
+        FoodBmc::delete(&ctx, &mm, food_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_update_and_delete_refresh_totals() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let food_id = fixture_food(&ctx, &mm, "test_update_and_delete_refresh_totals").await?;
+
+        let meal_id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_update_and_delete_refresh_totals".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let entry_id = MealEntryBmc::create(
+            &ctx,
+            &mm,
+            meal_id,
+            MealEntryForCreate {
+                food_id,
+                quantity: Quantity::grams(100.),
+            },
+        )
+        .await?;
+        assert_eq!(MealBmc::get(&ctx, &mm, meal_id).await?.kcal, 200);
+
+        MealEntryBmc::update(
+            &ctx,
+            &mm,
+            meal_id,
+            entry_id,
+            MealEntryForUpdate {
+                food_id: None,
+                quantity: Quantity::grams(250.),
+            },
+        )
+        .await?;
+        assert_eq!(MealBmc::get(&ctx, &mm, meal_id).await?.kcal, 500);
+
+        MealEntryBmc::delete(&ctx, &mm, meal_id, entry_id).await?;
+        assert_eq!(MealBmc::get(&ctx, &mm, meal_id).await?.kcal, 0);
+
+        MealBmc::delete(&ctx, &mm, meal_id).await?;
+        MealBmc::purge(&ctx, &mm, meal_id).await?;
+        FoodBmc::delete(&ctx, &mm, food_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_entries_in_units() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let food_id = fixture_food(&ctx, &mm, "test_create_entries_in_units").await?;
+        let in_unit = |amount: f32, unit: &str| MealEntryForCreate {
+            food_id,
+            quantity: Quantity {
+                amount: Some(amount),
+                unit: Some(Unit::from(unit.to_string())),
+                ..Default::default()
+            },
+        };
+
+        let meal_id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_create_entries_in_units".to_string(),
+                entries: vec![in_unit(2., "oz"), in_unit(1., "cup")],
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        // No slice portion yet.
+        let res = MealEntryBmc::create(&ctx, &mm, meal_id, in_unit(2., "slices")).await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+
+        FoodPortionBmc::create(
+            &ctx,
+            &mm,
+            food_id,
+            FoodPortionForCreate {
+                unit: Unit::from("slice".to_string()),
+                amount: None,
+                description: None,
+                gram_weight: 30.,
+            },
+        )
+        .await?;
+        MealEntryBmc::create(&ctx, &mm, meal_id, in_unit(2., "slices")).await?;
+
+        let quantities: Vec<f32> = MealEntryBmc::list(&ctx, &mm, meal_id)
+            .await?
+            .iter()
+            .map(|e| (e.quantity_g * 100.).round() / 100.)
+            .collect();
+        // Cups go through the food density of 0.5 g/ml.
+        assert_eq!(quantities, [56.7, 118.29, 60.]);
+
+        MealBmc::delete(&ctx, &mm, meal_id).await?;
+        MealBmc::purge(&ctx, &mm, meal_id).await?;
+        FoodBmc::delete(&ctx, &mm, food_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_delete_food_in_use() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let food_id = fixture_food(&ctx, &mm, "test_delete_food_in_use").await?;
+
+        let meal_id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_delete_food_in_use".to_string(),
+                entries: vec![MealEntryForCreate {
+                    food_id,
+                    quantity: Quantity::grams(100.),
+                }],
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let res = FoodBmc::delete(&ctx, &mm, food_id).await;
+
+        assert!(
+            matches!(res, Err(Error::ItemInUse { entity: "food", .. })),
+            "Food used by a meal should not be deletable"
+        );
+
+        // Still in use from the trash, until purged.
+        MealBmc::delete(&ctx, &mm, meal_id).await?;
+        let res = FoodBmc::delete(&ctx, &mm, food_id).await;
+        assert!(matches!(res, Err(Error::ItemInUse { entity: "food", .. })));
+        MealBmc::purge(&ctx, &mm, meal_id).await?;
+        FoodBmc::delete(&ctx, &mm, food_id).await?;
+
+        Ok(())
+    }
+}
@@ -1,7 +1,29 @@
+pub mod alcohol;
+pub mod energy;
 mod error;
+pub mod favorite;
+pub mod food;
+pub mod food_portion;
+pub mod goal;
+pub mod household;
+pub mod hydration;
+pub mod list_options;
 pub mod meal;
+pub mod meal_entry;
+pub mod nutrient;
+pub mod photo;
+pub mod product;
+pub mod quick_add;
+pub mod recipe;
+pub mod report;
+pub mod search;
+pub mod share;
 mod store;
+pub mod tag;
+pub mod unit;
 pub mod user;
+pub mod validation;
+pub mod weight;
 
 use store::{init_db_bool, Db};
 
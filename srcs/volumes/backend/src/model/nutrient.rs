@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+
+use super::validation::{FieldErrorKind, FieldErrors};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NutrientUnit {
+    G,
+    Mg,
+    Ug,
+}
+
+/// A nutrient tracked besides kcal and macros.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct NutrientInfo {
+    pub code: &'static str,
+    #[serde(skip)]
+    pub field: &'static str,
+    pub name: &'static str,
+    pub unit: NutrientUnit,
+    /// Highest amount accepted for a single meal.
+    pub max_per_meal: f64,
+}
+
+macro_rules! nutrient {
+    ($code:literal, $name:literal, $unit:ident, $max:expr) => {
+        NutrientInfo {
+            code: $code,
+            field: concat!("nutrients.", $code),
+            name: $name,
+            unit: NutrientUnit::$unit,
+            max_per_meal: $max,
+        }
+    };
+}
+
+/// Every known nutrient, a new one only needs an entry here.
+pub const NUTRIENTS: &[NutrientInfo] = &[
+    nutrient!("alcohol", "Alcohol", G, 1_000.),
+    nutrient!("fiber", "Fiber", G, 500.),
+    nutrient!("sugars", "Sugars", G, 2_000.),
+    nutrient!("saturated_fat", "Saturated fat", G, 2_000.),
+    nutrient!("sodium", "Sodium", Mg, 100_000.),
+    nutrient!("potassium", "Potassium", Mg, 100_000.),
+    nutrient!("cholesterol", "Cholesterol", Mg, 10_000.),
+    nutrient!("calcium", "Calcium", Mg, 20_000.),
+    nutrient!("iron", "Iron", Mg, 1_000.),
+    nutrient!("magnesium", "Magnesium", Mg, 10_000.),
+    nutrient!("zinc", "Zinc", Mg, 1_000.),
+    nutrient!("vitamin_a", "Vitamin A", Ug, 100_000.),
+    nutrient!("vitamin_b12", "Vitamin B12", Ug, 10_000.),
+    nutrient!("vitamin_c", "Vitamin C", Mg, 10_000.),
+    nutrient!("vitamin_d", "Vitamin D", Ug, 10_000.),
+    nutrient!("vitamin_e", "Vitamin E", Mg, 10_000.),
+    nutrient!("vitamin_k", "Vitamin K", Ug, 10_000.),
+    nutrient!("folate", "Folate", Ug, 10_000.),
+];
+
+/// Ethanol, the only extended nutrient carrying energy.
+pub const ALCOHOL: &str = "alcohol";
+
+pub fn nutrient_info(code: &str) -> Option<&'static NutrientInfo> {
+    NUTRIENTS.iter().find(|info| info.code == code)
+}
+
+/// Amounts of extended nutrients by code, in the unit of the nutrient.
+/// Nutrients left out are unknown rather than zero.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Nutrients(pub BTreeMap<String, f64>);
+
+impl Nutrients {
+    pub fn scaled(&self, factor: f64) -> Self {
+        Nutrients(
+            self.0
+                .iter()
+                .map(|(code, amount)| (code.clone(), amount * factor))
+                .collect(),
+        )
+    }
+
+    /// Grams of alcohol, zero when unknown.
+    pub fn alcohol_g(&self) -> f64 {
+        self.0.get(ALCOHOL).copied().unwrap_or_default()
+    }
+
+    pub fn check(&self, errors: &mut FieldErrors) {
+        for (code, amount) in &self.0 {
+            match nutrient_info(code) {
+                Some(info) => errors.check_range(info.field, *amount, 0., info.max_per_meal),
+                None => errors.push(
+                    "nutrients",
+                    FieldErrorKind::UnknownKey { key: code.clone() },
+                ),
+            }
+        }
+    }
+}
+
+/// Database form of `Nutrients`, a JSONB object.
+pub type NutrientsJson = Json<Nutrients>;
+
+impl From<NutrientsJson> for Nutrients {
+    fn from(value: NutrientsJson) -> Self {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Error;
+    use anyhow::Result;
+
+    #[test]
+    fn test_check_nutrients() -> Result<()> {
+        let nutrients = Nutrients(BTreeMap::from([
+            ("sodium".to_string(), 1_200.),
+            ("sugars".to_string(), -3.),
+            ("unobtainium".to_string(), 1.),
+        ]));
+
+        let mut errors = FieldErrors::default();
+        nutrients.check(&mut errors);
+
+        let Err(Error::Validation { errors, .. }) = errors.into_result("meal") else {
+            panic!("Should have failed validation");
+        };
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, ["nutrients.sugars", "nutrients"]);
+
+        Ok(())
+    }
+}
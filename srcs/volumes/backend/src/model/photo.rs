@@ -0,0 +1,446 @@
+use std::io::{self, Cursor};
+use std::path::PathBuf;
+
+use image::{
+    codecs::jpeg::JpegEncoder, DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits,
+};
+use serde::Serialize;
+use sqlx::prelude::FromRow;
+use time::OffsetDateTime;
+
+use crate::config::config;
+use crate::ctx::Ctx;
+
+use crate::model::{Error, Result};
+
+use super::{
+    meal::MealBmc,
+    validation::{FieldError, FieldErrorKind},
+    ModelManager,
+};
+
+pub const PHOTO_MAX_BYTES: usize = 10 * 1024 * 1024;
+const PHOTO_MAX_DIMENSION: u32 = 12_000;
+const PHOTOS_PER_MEAL_MAX: usize = 10;
+const PHOTO_FORMATS: &[&str] = &["jpeg", "png", "webp"];
+const PHOTO_JPEG_QUALITY: u8 = 85;
+const THUMBNAIL_SIZE: u32 = 320;
+
+/// Photo attached to a meal. Uploads are decoded and encoded again, which
+/// drops their EXIF metadata, GPS position included.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct MealPhoto {
+    pub id: i64,
+    pub meal_id: i64,
+    pub owner: i64,
+    pub content_type: String,
+    pub size_bytes: i32,
+    pub width: i32,
+    pub height: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PhotoVariant {
+    Original,
+    Thumbnail,
+}
+
+/// Upload once stripped of its metadata, with its thumbnail.
+struct ProcessedPhoto {
+    format: ImageFormat,
+    bytes: Vec<u8>,
+    thumbnail: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+const PHOTO_COLUMNS: &str =
+    "id, meal_id, owner, content_type, size_bytes, width, height, created_at";
+
+/// Photos of the meals out of the trash.
+const PHOTO_SELECT: &str = "SELECT p.id, p.meal_id, p.owner, p.content_type, p.size_bytes,
+        p.width, p.height, p.created_at
+    FROM meal_photo p
+    JOIN meal m ON m.id = p.meal_id AND m.deleted_at IS NULL";
+
+impl MealPhoto {
+    pub fn path(&self, variant: PhotoVariant) -> PathBuf {
+        let file_name = match variant {
+            PhotoVariant::Original => {
+                let extension = ImageFormat::from_mime_type(&self.content_type)
+                    .and_then(|format| format.extensions_str().first())
+                    .unwrap_or(&"bin");
+                format!("{}.{extension}", self.id)
+            }
+            PhotoVariant::Thumbnail => format!("{}_thumb.jpg", self.id),
+        };
+
+        meal_folder(self.owner, self.meal_id).join(file_name)
+    }
+
+    pub fn content_type(&self, variant: PhotoVariant) -> &str {
+        match variant {
+            PhotoVariant::Original => &self.content_type,
+            PhotoVariant::Thumbnail => ImageFormat::Jpeg.to_mime_type(),
+        }
+    }
+}
+
+fn meal_folder(owner: i64, meal_id: i64) -> PathBuf {
+    PathBuf::from(&config().PHOTO_FOLDER)
+        .join(owner.to_string())
+        .join(meal_id.to_string())
+}
+
+fn invalid_photo(kind: FieldErrorKind) -> Error {
+    Error::Validation {
+        entity: "meal_photo",
+        errors: vec![FieldError {
+            field: "photo",
+            kind,
+        }],
+    }
+}
+
+fn encode_jpeg(image: &DynamicImage) -> image::ImageResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut bytes, PHOTO_JPEG_QUALITY);
+    // JPEG has no alpha channel.
+    image.to_rgb8().write_with_encoder(encoder)?;
+
+    Ok(bytes)
+}
+
+/// Decode the upload with its EXIF orientation applied, then encode it again.
+/// PNG stays lossless, other formats are stored as JPEG.
+fn process_photo(bytes: &[u8]) -> Result<ProcessedPhoto> {
+    if bytes.len() > PHOTO_MAX_BYTES {
+        return Err(invalid_photo(FieldErrorKind::FileTooLarge {
+            max_bytes: PHOTO_MAX_BYTES,
+        }));
+    }
+
+    let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    let format = reader.format().filter(|format| {
+        matches!(
+            format,
+            ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP
+        )
+    });
+    let Some(format) = format else {
+        return Err(invalid_photo(FieldErrorKind::UnsupportedFormat {
+            allowed: PHOTO_FORMATS,
+        }));
+    };
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(PHOTO_MAX_DIMENSION);
+    limits.max_image_height = Some(PHOTO_MAX_DIMENSION);
+    reader.limits(limits);
+
+    let decode = || -> image::ImageResult<DynamicImage> {
+        let mut decoder = reader.into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        Ok(image)
+    };
+    let image = decode().map_err(|_| invalid_photo(FieldErrorKind::InvalidImage))?;
+
+    let encode = || -> image::ImageResult<ProcessedPhoto> {
+        let (format, bytes) = match format {
+            ImageFormat::Png => {
+                let mut bytes = Cursor::new(Vec::new());
+                image.write_to(&mut bytes, ImageFormat::Png)?;
+                (ImageFormat::Png, bytes.into_inner())
+            }
+            _ => (ImageFormat::Jpeg, encode_jpeg(&image)?),
+        };
+
+        Ok(ProcessedPhoto {
+            format,
+            bytes,
+            thumbnail: encode_jpeg(&image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE))?,
+            width: image.width(),
+            height: image.height(),
+        })
+    };
+
+    encode().map_err(|_| invalid_photo(FieldErrorKind::InvalidImage))
+}
+
+pub struct MealPhotoBmc {}
+
+impl MealPhotoBmc {
+    /// Attach photos to an owned meal, all of them or none. The images are
+    /// processed off the async runtime before anything is stored, the files
+    /// are removed again when the rows can't be committed.
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        meal_id: i64,
+        uploads: Vec<Vec<u8>>,
+    ) -> Result<Vec<i64>> {
+        let processed = tokio::task::spawn_blocking(move || {
+            uploads
+                .iter()
+                .map(|bytes| process_photo(bytes))
+                .collect::<Result<Vec<_>>>()
+        })
+        .await
+        .map_err(|err| Error::Io(io::Error::other(err)))??;
+
+        let mut transaction_manager = mm.db().begin().await?;
+
+        // Locks the meal so concurrent uploads can't both pass the cap.
+        sqlx::query(
+            "SELECT id FROM meal WHERE id = $1 AND owner = $2 AND deleted_at IS NULL FOR UPDATE",
+        )
+        .bind(meal_id)
+        .bind(ctx.user_id())
+        .fetch_optional(&mut *transaction_manager)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "meal",
+            id: meal_id,
+        })?;
+
+        let (count,) =
+            sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM meal_photo WHERE meal_id = $1")
+                .bind(meal_id)
+                .fetch_one(&mut *transaction_manager)
+                .await?;
+        if count as usize + processed.len() > PHOTOS_PER_MEAL_MAX {
+            return Err(invalid_photo(FieldErrorKind::TooMany {
+                max: PHOTOS_PER_MEAL_MAX,
+            }));
+        }
+
+        let mut written = Vec::new();
+        let res = async {
+            let mut ids = Vec::with_capacity(processed.len());
+            for processed in &processed {
+                let photo = sqlx::query_as::<_, MealPhoto>(&format!(
+                    "INSERT INTO meal_photo (meal_id, owner, content_type, size_bytes, width, height)
+                    VALUES ($1, $2, $3, $4, $5, $6) RETURNING {PHOTO_COLUMNS}"
+                ))
+                .bind(meal_id)
+                .bind(ctx.user_id())
+                .bind(processed.format.to_mime_type())
+                .bind(processed.bytes.len() as i32)
+                .bind(processed.width as i32)
+                .bind(processed.height as i32)
+                .fetch_one(&mut *transaction_manager)
+                .await?;
+
+                tokio::fs::create_dir_all(meal_folder(photo.owner, meal_id)).await?;
+                for (variant, bytes) in [
+                    (PhotoVariant::Original, &processed.bytes),
+                    (PhotoVariant::Thumbnail, &processed.thumbnail),
+                ] {
+                    let path = photo.path(variant);
+                    written.push(path.clone());
+                    tokio::fs::write(path, bytes).await?;
+                }
+                ids.push(photo.id);
+            }
+
+            transaction_manager.commit().await?;
+            Ok(ids)
+        }
+        .await;
+
+        if res.is_err() {
+            for path in written {
+                let _ = tokio::fs::remove_file(path).await;
+            }
+        }
+        res
+    }
+
+    /// Photos of a trashed meal are hidden until it is restored.
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, meal_id: i64, id: i64) -> Result<MealPhoto> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, MealPhoto>(&format!(
+            "{PHOTO_SELECT} WHERE p.id = $1 AND p.meal_id = $2 AND p.owner = $3"
+        ))
+        .bind(id)
+        .bind(meal_id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "meal_photo",
+            id,
+        })
+    }
+
+    pub async fn list(ctx: &Ctx, mm: &ModelManager, meal_id: i64) -> Result<Vec<MealPhoto>> {
+        MealBmc::get(ctx, mm, meal_id).await?;
+
+        let db = mm.db();
+
+        let photos = sqlx::query_as(&format!(
+            "SELECT {PHOTO_COLUMNS} FROM meal_photo WHERE meal_id = $1 AND owner = $2 ORDER BY id"
+        ))
+        .bind(meal_id)
+        .bind(ctx.user_id())
+        .fetch_all(db)
+        .await?;
+
+        Ok(photos)
+    }
+
+    /// File of the photo, only readable by its owner.
+    pub async fn read(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        meal_id: i64,
+        id: i64,
+        variant: PhotoVariant,
+    ) -> Result<(MealPhoto, Vec<u8>)> {
+        let photo = MealPhotoBmc::get(ctx, mm, meal_id, id).await?;
+
+        let bytes = match tokio::fs::read(photo.path(variant)).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::ItemNotFound {
+                    entity: "meal_photo",
+                    id,
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok((photo, bytes))
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, meal_id: i64, id: i64) -> Result<()> {
+        let photo = MealPhotoBmc::get(ctx, mm, meal_id, id).await?;
+
+        let db = mm.db();
+
+        sqlx::query("DELETE FROM meal_photo WHERE id = $1")
+            .bind(id)
+            .execute(db)
+            .await?;
+
+        for variant in [PhotoVariant::Original, PhotoVariant::Thumbnail] {
+            remove_if_exists(tokio::fs::remove_file(photo.path(variant)).await)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the files of a purged meal, its rows are dropped by the cascade.
+    pub(in crate::model) async fn remove_meal_files(owner: i64, meal_id: i64) -> Result<()> {
+        let folder = meal_folder(owner, meal_id);
+
+        remove_if_exists(tokio::fs::remove_dir_all(folder).await)
+    }
+}
+
+fn remove_if_exists(res: io::Result<()>) -> Result<()> {
+    match res {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use crate::{
+        _dev_utils::dev_init_tests,
+        model::meal::{MealBmc, MealForCreate},
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    /// JPEG with an EXIF segment, the one a phone adds with the GPS position.
+    fn fx_jpeg_with_exif(width: u32, height: u32) -> Result<Vec<u8>> {
+        let image =
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb([200, 80, 40])));
+        let jpeg = encode_jpeg(&image)?;
+
+        // Little endian TIFF header followed by an empty IFD.
+        let exif = b"Exif\0\0II*\0\x08\0\0\0\0\0\0\0\0\0";
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend_from_slice(&[0xFF, 0xE1, 0, exif.len() as u8 + 2]);
+        bytes.extend_from_slice(exif);
+        bytes.extend_from_slice(&jpeg[2..]);
+
+        Ok(bytes)
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_photo_strips_exif() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_bytes = fx_jpeg_with_exif(800, 400)?;
+        assert!(fx_bytes.windows(4).any(|w| w == b"Exif"));
+
+        let meal_id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_create_photo_strips_exif".to_string(),
+                kcal: Some(500),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let ids = MealPhotoBmc::create(&ctx, &mm, meal_id, vec![fx_bytes.clone()]).await?;
+        let id = ids[0];
+
+        let (photo, bytes) =
+            MealPhotoBmc::read(&ctx, &mm, meal_id, id, PhotoVariant::Original).await?;
+        assert_eq!(photo.content_type, "image/jpeg");
+        assert_eq!((photo.width, photo.height), (800, 400));
+        assert!(!bytes.windows(4).any(|w| w == b"Exif"));
+
+        let (_, thumbnail) =
+            MealPhotoBmc::read(&ctx, &mm, meal_id, id, PhotoVariant::Thumbnail).await?;
+        let thumbnail = image::load_from_memory(&thumbnail)?;
+        assert_eq!((thumbnail.width(), thumbnail.height()), (320, 160));
+
+        // A bad file fails the whole upload.
+        let batch = vec![fx_bytes.clone(), b"GIF89a not a photo".to_vec()];
+        let res = MealPhotoBmc::create(&ctx, &mm, meal_id, batch).await;
+        assert!(
+            matches!(&res, Err(Error::Validation { errors, .. })
+                if matches!(errors[0].kind, FieldErrorKind::UnsupportedFormat { .. })),
+            "Should have rejected the format, got {res:?}"
+        );
+        assert_eq!(MealPhotoBmc::list(&ctx, &mm, meal_id).await?.len(), 1);
+
+        // The cap counts the whole batch.
+        let batch = vec![fx_bytes.clone(); PHOTOS_PER_MEAL_MAX];
+        let res = MealPhotoBmc::create(&ctx, &mm, meal_id, batch).await;
+        assert!(
+            matches!(&res, Err(Error::Validation { errors, .. })
+                if matches!(errors[0].kind, FieldErrorKind::TooMany { .. })),
+            "Should have rejected the batch, got {res:?}"
+        );
+        assert_eq!(MealPhotoBmc::list(&ctx, &mm, meal_id).await?.len(), 1);
+
+        // The trash keeps the files until the meal is purged, hidden.
+        let path = photo.path(PhotoVariant::Original);
+        MealBmc::delete(&ctx, &mm, meal_id).await?;
+        assert!(path.exists());
+        let res = MealPhotoBmc::read(&ctx, &mm, meal_id, id, PhotoVariant::Original).await;
+        assert!(matches!(res, Err(Error::ItemNotFound { .. })));
+        MealBmc::purge(&ctx, &mm, meal_id).await?;
+        assert!(!path.exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use serde::Serialize;
+use sqlx::prelude::FromRow;
+
+use crate::ctx::Ctx;
+use crate::utils::gtin::normalize_gtin;
+
+use crate::model::{Error, Result};
+
+use super::{
+    food::{Food, FoodBmc},
+    food_portion::FoodPortion,
+    meal_entry::MealEntryForCreate,
+    recipe::NutrientAmounts,
+    unit::{Quantity, UnitBmc},
+    validation::{FieldError, FieldErrorKind, FieldErrors},
+    ModelManager,
+};
+
+/// Packaged product, its nutrition is the one of the linked food.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Product {
+    pub gtin: String,
+    pub food_id: i64,
+    pub brand: Option<String>,
+    pub serving_g: Option<f32>,
+}
+
+/// Meal payload matching `POST /api/meals/`, so a scan can be logged as is.
+#[derive(Debug, Clone, Serialize)]
+pub struct MealSuggestion {
+    pub name: String,
+    pub entries: Vec<MealEntryForCreate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProductLookup {
+    #[serde(flatten)]
+    pub product: Product,
+    pub food: Food,
+    /// Weight of the looked up quantity, by default the serving size or
+    /// 100 g when unknown.
+    pub quantity_g: f32,
+    pub nutrition: NutrientAmounts,
+    pub meal: MealSuggestion,
+}
+
+/// Quantity suggested when a product has no known serving size.
+const DEFAULT_QUANTITY_G: f32 = 100.;
+/// Unit of the product serving, on top of the food ones.
+const SERVING_UNIT: &str = "serving";
+
+pub struct ProductBmc {}
+
+impl ProductBmc {
+    pub async fn get_by_code(ctx: &Ctx, mm: &ModelManager, code: &str) -> Result<Product> {
+        let gtin = normalize_gtin(code).ok_or(Error::Validation {
+            entity: "product",
+            errors: vec![FieldError {
+                field: "code",
+                kind: FieldErrorKind::InvalidGtin,
+            }],
+        })?;
+
+        let db = mm.db();
+
+        sqlx::query_as::<_, Product>(
+            "SELECT p.gtin, p.food_id, p.brand, p.serving_g
+            FROM product p JOIN food f ON f.id = p.food_id
+            WHERE p.gtin = $1 AND (f.owner IS NULL OR f.owner = $2)",
+        )
+        .bind(&gtin)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "product",
+            // Safe, a GTIN-14 always fits an i64.
+            id: gtin.parse().unwrap(),
+        })
+    }
+
+    /// Look a scanned barcode up, with the nutrition of `quantity`. An empty
+    /// quantity stands for a serving, the unit "serving" is available when
+    /// the product has one.
+    pub async fn lookup(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        code: &str,
+        quantity: &Quantity,
+    ) -> Result<ProductLookup> {
+        let product = ProductBmc::get_by_code(ctx, mm, code).await?;
+        let food = FoodBmc::get(ctx, mm, product.food_id).await?;
+
+        let quantity = if quantity.is_empty() {
+            Quantity::grams(product.serving_g.unwrap_or(DEFAULT_QUANTITY_G))
+        } else {
+            quantity.clone()
+        };
+        let mut errors = FieldErrors::default();
+        quantity.check(&mut errors, "quantity_g");
+        errors.into_result("product")?;
+
+        let mut connection = mm.db().acquire().await?;
+        let mut measures = UnitBmc::food_measures(ctx, &mut connection, food.id).await?;
+        if let Some(serving_g) = product.serving_g {
+            measures.portions.push(FoodPortion {
+                id: 0,
+                food_id: food.id,
+                unit: SERVING_UNIT.to_string(),
+                amount: 1.,
+                description: None,
+                gram_weight: serving_g,
+            });
+        }
+        let quantity_g = measures.checked_grams(&quantity, "product", "quantity_g")?;
+
+        let nutrition = NutrientAmounts {
+            kcal: food.kcal_100g.into(),
+            carbs: food.carbs_100g.into(),
+            proteins: food.proteins_100g.into(),
+            lipids: food.lipids_100g.into(),
+        }
+        .scaled(f64::from(quantity_g) / 100.);
+
+        let name = match &product.brand {
+            Some(brand) => format!("{brand} {}", food.name),
+            None => food.name.clone(),
+        };
+
+        Ok(ProductLookup {
+            meal: MealSuggestion {
+                name,
+                entries: vec![MealEntryForCreate {
+                    food_id: food.id,
+                    quantity: Quantity::grams(quantity_g),
+                }],
+            },
+            product,
+            food,
+            quantity_g,
+            nutrition,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{_dev_utils::dev_init_tests, model::unit::Unit};
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_lookup_ok() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+
+        let lookup = ProductBmc::lookup(&ctx, &mm, "4006381333931", &Quantity::default()).await?;
+        let servings = Quantity {
+            amount: Some(2.),
+            unit: Some(Unit::from("servings".to_string())),
+            ..Default::default()
+        };
+        let two_servings = ProductBmc::lookup(&ctx, &mm, "4006381333931", &servings).await?;
+        let ounce = Quantity {
+            unit: Some(Unit::Oz),
+            ..Default::default()
+        };
+        let ounce = ProductBmc::lookup(&ctx, &mm, "4006381333931", &ounce).await?;
+        let wedge = Quantity {
+            unit: Some(Unit::from("wedge".to_string())),
+            ..Default::default()
+        };
+        let wedge = ProductBmc::lookup(&ctx, &mm, "4006381333931", &wedge).await;
+
+        assert_eq!(lookup.product.gtin, "04006381333931");
+        assert_eq!(lookup.food.name, "Olive oil");
+        assert_eq!(lookup.quantity_g, 15.);
+        assert_eq!(lookup.meal.entries[0].food_id, lookup.food.id);
+        assert_eq!(two_servings.quantity_g, 30.);
+        assert_eq!(two_servings.meal.entries[0].quantity, Quantity::grams(30.));
+        assert!((ounce.quantity_g - 28.35).abs() < 0.01);
+        assert!(matches!(wedge, Err(Error::Validation { .. })));
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_lookup_invalid_and_unknown() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+
+        let res = ProductBmc::lookup(&ctx, &mm, "4006381333932", &Quantity::default()).await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+
+        let res = ProductBmc::lookup(&ctx, &mm, "96385074", &Quantity::default()).await;
+        assert!(matches!(
+            res,
+            Err(Error::ItemNotFound {
+                entity: "product",
+                id: 96385074
+            })
+        ));
+
+        Ok(())
+    }
+}
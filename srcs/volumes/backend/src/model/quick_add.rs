@@ -0,0 +1,227 @@
+use serde::Serialize;
+use sqlx::prelude::FromRow;
+use time::OffsetDateTime;
+
+use crate::ctx::Ctx;
+use crate::utils::time_utils::now_utc;
+
+use crate::model::Result;
+
+use super::{
+    meal::{Meal, MEAL_COLUMNS},
+    ModelManager,
+};
+
+/// Days after which a log counts half as much in the ranking.
+const RECENCY_HALF_LIFE_DAYS: f64 = 14.;
+/// Logs older than this are left out of the ranking.
+const RECENCY_WINDOW_DAYS: i32 = 180;
+
+/// Meals logged under the same name, represented by the latest of them.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct FrequentMeal {
+    #[sqlx(flatten)]
+    pub meal: Meal,
+    pub log_count: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_logged_at: OffsetDateTime,
+    /// Recency-weighted frequency, each log weighs 1 when fresh and halves
+    /// every `RECENCY_HALF_LIFE_DAYS`.
+    pub score: f64,
+    pub favorite: bool,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct FrequentFood {
+    pub food_id: i64,
+    pub food_name: String,
+    pub log_count: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_logged_at: OffsetDateTime,
+    /// Quantity of the latest entry, a sensible default for the next one.
+    pub last_quantity_g: f32,
+    pub score: f64,
+}
+
+pub struct QuickAddBmc {}
+
+impl QuickAddBmc {
+    pub async fn meals(ctx: &Ctx, mm: &ModelManager, limit: i64) -> Result<Vec<FrequentMeal>> {
+        let db = mm.db();
+
+        let meals = sqlx::query_as(&format!(
+            "WITH scored AS (
+                SELECT COUNT(*) AS log_count, MAX(consumed_at) AS last_logged_at,
+                    SUM(POWER(0.5, GREATEST(EXTRACT(EPOCH FROM $2 - consumed_at)::FLOAT8, 0)
+                        / 86400 / $3))::FLOAT8 AS score,
+                    (ARRAY_AGG(id ORDER BY consumed_at DESC, id DESC))[1] AS meal_id
+                FROM meal
+                WHERE owner = $1 AND deleted_at IS NULL
+                    AND consumed_at >= $2 - make_interval(days => $4)
+                GROUP BY LOWER(TRIM(name))
+            )
+            SELECT {MEAL_COLUMNS}, log_count, last_logged_at, score,
+                EXISTS (
+                    SELECT 1 FROM favorite_meal fav WHERE fav.owner = $1 AND fav.meal_id = meal.id
+                ) AS favorite
+            FROM meal JOIN scored ON scored.meal_id = meal.id
+            ORDER BY score DESC, last_logged_at DESC
+            LIMIT $5"
+        ))
+        .bind(ctx.user_id())
+        .bind(now_utc())
+        .bind(RECENCY_HALF_LIFE_DAYS)
+        .bind(RECENCY_WINDOW_DAYS)
+        .bind(limit)
+        .fetch_all(db)
+        .await?;
+
+        Ok(meals)
+    }
+
+    pub async fn foods(ctx: &Ctx, mm: &ModelManager, limit: i64) -> Result<Vec<FrequentFood>> {
+        let db = mm.db();
+
+        let foods = sqlx::query_as(
+            "SELECT f.id AS food_id, f.name AS food_name, COUNT(*) AS log_count,
+                MAX(m.consumed_at) AS last_logged_at,
+                (ARRAY_AGG(e.quantity_g ORDER BY m.consumed_at DESC, e.id DESC))[1] AS last_quantity_g,
+                SUM(POWER(0.5, GREATEST(EXTRACT(EPOCH FROM $2 - m.consumed_at)::FLOAT8, 0)
+                    / 86400 / $3))::FLOAT8 AS score
+            FROM meal_entry e
+            JOIN meal m ON m.id = e.meal_id
+            JOIN food f ON f.id = e.food_id
+            WHERE m.owner = $1 AND m.deleted_at IS NULL
+                AND m.consumed_at >= $2 - make_interval(days => $4)
+                AND (f.owner IS NULL OR f.owner = $1)
+            GROUP BY f.id, f.name
+            ORDER BY score DESC, last_logged_at DESC
+            LIMIT $5",
+        )
+        .bind(ctx.user_id())
+        .bind(now_utc())
+        .bind(RECENCY_HALF_LIFE_DAYS)
+        .bind(RECENCY_WINDOW_DAYS)
+        .bind(limit)
+        .fetch_all(db)
+        .await?;
+
+        Ok(foods)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use crate::{
+        _dev_utils::dev_init_tests,
+        model::{
+            favorite::FavoriteBmc,
+            meal::{MealBmc, MealForCreate, MealRelog},
+            meal_entry::MealEntryForCreate,
+            unit::Quantity,
+        },
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_meals_ranked_by_recency_weighted_frequency() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let now = now_utc();
+        // Three old logs weigh less than two fresh ones.
+        let fx_logs = [
+            ("test_quick_add porridge", 60),
+            ("test_quick_add porridge", 61),
+            ("test_quick_add porridge", 62),
+            ("test_quick_add salad", 1),
+            ("Test_quick_add salad ", 2),
+        ];
+
+        let mut ids = Vec::new();
+        for (name, days_ago) in fx_logs {
+            let meal_c = MealForCreate {
+                name: name.to_string(),
+                kcal: Some(300),
+                consumed_at: Some(now - Duration::days(days_ago)),
+                ..Default::default()
+            };
+            ids.push(MealBmc::create(&ctx, &mm, meal_c).await?);
+        }
+        FavoriteBmc::add(&ctx, &mm, ids[3]).await?;
+
+        let meals: Vec<FrequentMeal> = QuickAddBmc::meals(&ctx, &mm, 100)
+            .await?
+            .into_iter()
+            .filter(|m| m.meal.name.to_lowercase().starts_with("test_quick_add"))
+            .collect();
+
+        assert_eq!(meals.len(), 2);
+        assert_eq!(meals[0].meal.id, ids[3]);
+        assert_eq!(meals[0].log_count, 2);
+        assert!(meals[0].favorite);
+        assert_eq!(meals[1].log_count, 3);
+        assert!(meals[0].score > meals[1].score);
+
+        for id in ids {
+            MealBmc::delete(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_relog_favorite_scaled() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_food_id = 1000;
+
+        let id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_relog_favorite_scaled".to_string(),
+                entries: vec![MealEntryForCreate {
+                    food_id: fx_food_id,
+                    quantity: Quantity::grams(200.),
+                }],
+                ..Default::default()
+            },
+        )
+        .await?;
+        FavoriteBmc::add(&ctx, &mm, id).await?;
+
+        assert!(FavoriteBmc::list(&ctx, &mm).await?.iter().any(|m| m.id == id));
+
+        let relog = MealRelog {
+            multiplier: Some(1.5),
+            ..Default::default()
+        };
+        let relogged_id = MealBmc::relog(&ctx, &mm, id, relog).await?;
+
+        let meal = MealBmc::get(&ctx, &mm, id).await?;
+        let relogged = MealBmc::get(&ctx, &mm, relogged_id).await?;
+        assert_eq!(relogged.name, meal.name);
+        assert_eq!(relogged.kcal, 390);
+
+        let foods = QuickAddBmc::foods(&ctx, &mm, 100).await?;
+        let food = foods
+            .iter()
+            .find(|f| f.food_id == fx_food_id)
+            .expect("Should list the relogged food");
+        assert_eq!(food.log_count, 2);
+        assert_eq!(food.last_quantity_g, 300.);
+
+        FavoriteBmc::remove(&ctx, &mm, id).await?;
+        MealBmc::delete(&ctx, &mm, id).await?;
+        MealBmc::delete(&ctx, &mm, relogged_id).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,843 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, Postgres, Transaction};
+use time::OffsetDateTime;
+
+use crate::ctx::Ctx;
+use crate::utils::serde_utils::nullable;
+
+use crate::model::{Error, Result};
+
+use super::{
+    meal::{MealBmc, MealForCreate, MealSlot},
+    meal_entry::{MealEntryForCreate, ENTRY_MAX_QUANTITY_G},
+    share::{without_foreign_foods, ShareBmc, SharePermission, ShareResource},
+    unit::{FoodMeasures, Quantity, Unit, UnitBmc},
+    validation::{FieldErrorKind, FieldErrors},
+    ModelManager,
+};
+
+/// A recipe without servings is considered as a single serving, without
+/// cooked weight its yield is the raw weight of its ingredients.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Recipe {
+    pub id: i64,
+    pub owner: i64,
+    pub name: String,
+    pub servings: Option<f32>,
+    pub cooked_weight_g: Option<f32>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct RecipeForCreate {
+    pub name: String,
+    pub servings: Option<f32>,
+    pub cooked_weight_g: Option<f32>,
+    #[serde(default)]
+    pub ingredients: Vec<RecipeIngredientForCreate>,
+}
+
+/// `null` clears the servings or the cooked weight.
+#[derive(Deserialize, Default)]
+pub struct RecipeForUpdate {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub servings: Option<Option<f32>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub cooked_weight_g: Option<Option<f32>>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct RecipeIngredient {
+    pub id: i64,
+    pub recipe_id: i64,
+    pub food_id: i64,
+    pub food_name: String,
+    pub quantity_g: f32,
+    pub kcal: f32,
+    pub carbs: f32,
+    pub proteins: f32,
+    pub lipids: f32,
+}
+
+/// The quantity is given in grams or in any unit the food can be measured
+/// in, it is stored in grams.
+#[derive(Deserialize)]
+pub struct RecipeIngredientForCreate {
+    pub food_id: i64,
+    #[serde(flatten)]
+    pub quantity: Quantity,
+}
+
+#[derive(Deserialize)]
+pub struct RecipeIngredientForUpdate {
+    pub food_id: Option<i64>,
+    /// Kept as is when empty.
+    #[serde(flatten)]
+    pub quantity: Quantity,
+}
+
+#[derive(Debug, Clone, Default, FromRow, Serialize)]
+pub struct NutrientAmounts {
+    pub kcal: f64,
+    pub carbs: f64,
+    pub proteins: f64,
+    pub lipids: f64,
+}
+
+impl NutrientAmounts {
+    pub fn scaled(&self, factor: f64) -> Self {
+        NutrientAmounts {
+            kcal: self.kcal * factor,
+            carbs: self.carbs * factor,
+            proteins: self.proteins * factor,
+            lipids: self.lipids * factor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeNutrition {
+    pub recipe_id: i64,
+    pub servings: f32,
+    pub raw_weight_g: f64,
+    pub yield_weight_g: f64,
+    pub total: NutrientAmounts,
+    pub per_serving: NutrientAmounts,
+    /// Per 100 g of the cooked recipe.
+    pub per_100g: NutrientAmounts,
+}
+
+/// Portion of a recipe, given either in servings or as a weight of the
+/// cooked recipe, in grams or any mass unit.
+#[derive(Deserialize, Default)]
+pub struct RecipePortion {
+    pub servings: Option<f32>,
+    #[serde(flatten)]
+    pub quantity: Quantity,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub consumed_at: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub slot: MealSlot,
+}
+
+const RECIPE_NAME_MAX_LEN: usize = 128;
+const RECIPE_MAX_SERVINGS: f64 = 1_000.;
+const RECIPE_MAX_WEIGHT_G: f64 = 100_000.;
+
+const RECIPE_COLUMNS: &str = "id, owner, name, servings, cooked_weight_g";
+
+const RECIPE_INGREDIENT_SELECT: &str = "SELECT i.id, i.recipe_id, i.food_id, f.name AS food_name, i.quantity_g,
+        (f.kcal_100g * i.quantity_g / 100)::REAL AS kcal,
+        (f.carbs_100g * i.quantity_g / 100)::REAL AS carbs,
+        (f.proteins_100g * i.quantity_g / 100)::REAL AS proteins,
+        (f.lipids_100g * i.quantity_g / 100)::REAL AS lipids
+    FROM recipe_ingredient i
+    JOIN food f ON f.id = i.food_id
+    JOIN recipe r ON r.id = i.recipe_id";
+
+fn check_yield(errors: &mut FieldErrors, servings: Option<f32>, cooked_weight_g: Option<f32>) {
+    if let Some(servings) = servings {
+        errors.check_range("servings", servings.into(), 0.1, RECIPE_MAX_SERVINGS);
+    }
+    if let Some(cooked_weight_g) = cooked_weight_g {
+        errors.check_range(
+            "cooked_weight_g",
+            cooked_weight_g.into(),
+            1.,
+            RECIPE_MAX_WEIGHT_G,
+        );
+    }
+}
+
+impl RecipeForCreate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        errors.check_text("name", &self.name, RECIPE_NAME_MAX_LEN);
+        check_yield(&mut errors, self.servings, self.cooked_weight_g);
+        for ingredient_c in &self.ingredients {
+            ingredient_c
+                .quantity
+                .check(&mut errors, "ingredients.quantity_g");
+        }
+
+        errors.into_result("recipe")
+    }
+}
+
+impl RecipeForUpdate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        if let Some(name) = &self.name {
+            errors.check_text("name", name, RECIPE_NAME_MAX_LEN);
+        }
+        check_yield(
+            &mut errors,
+            self.servings.flatten(),
+            self.cooked_weight_g.flatten(),
+        );
+
+        errors.into_result("recipe")
+    }
+}
+
+impl RecipePortion {
+    /// Share of the whole recipe eaten.
+    pub fn factor(&self, nutrition: &RecipeNutrition) -> Result<f64> {
+        let mut errors = FieldErrors::default();
+
+        let factor = match (self.servings, self.quantity.is_empty()) {
+            (Some(servings), true) => {
+                errors.check_range("servings", servings.into(), 0.01, RECIPE_MAX_SERVINGS);
+                f64::from(servings) / f64::from(nutrition.servings)
+            }
+            // A cooked recipe has no density nor portions, only mass units
+            // convert.
+            (None, false) => match FoodMeasures::default().grams(&self.quantity) {
+                Some(grams) => {
+                    errors.check_range("quantity_g", grams, 1., RECIPE_MAX_WEIGHT_G);
+                    grams / nutrition.yield_weight_g
+                }
+                None => {
+                    let unit = self.quantity.unit.as_ref().map(Unit::as_str);
+                    errors.push(
+                        "quantity_g",
+                        FieldErrorKind::UnconvertibleUnit {
+                            unit: unit.unwrap_or_default().to_string(),
+                        },
+                    );
+                    0.
+                }
+            },
+            (Some(_), false) => {
+                errors.push(
+                    "quantity_g",
+                    FieldErrorKind::Conflicting { with: "servings" },
+                );
+                0.
+            }
+            (None, true) => {
+                errors.push("servings", FieldErrorKind::Missing);
+                0.
+            }
+        };
+
+        errors.into_result("recipe_portion")?;
+        Ok(factor)
+    }
+
+    /// The largest ingredient, scaled by `factor`, must still fit in a meal
+    /// entry. The error gives the largest portion that does.
+    fn check_entries(
+        &self,
+        nutrition: &RecipeNutrition,
+        factor: f64,
+        largest_g: f64,
+    ) -> Result<()> {
+        if largest_g * factor <= ENTRY_MAX_QUANTITY_G {
+            return Ok(());
+        }
+
+        let max_factor = ENTRY_MAX_QUANTITY_G / largest_g;
+        let (field, min, max) = match self.servings {
+            Some(_) => ("servings", 0.01, max_factor * f64::from(nutrition.servings)),
+            None => ("quantity_g", 1., max_factor * nutrition.yield_weight_g),
+        };
+
+        let mut errors = FieldErrors::default();
+        errors.push(field, FieldErrorKind::OutOfRange { min, max });
+        errors.into_result("recipe_portion")
+    }
+}
+
+pub struct RecipeBmc {}
+
+impl RecipeBmc {
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, recipe_c: RecipeForCreate) -> Result<i64> {
+        recipe_c.validate()?;
+
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO recipe (owner, name, servings, cooked_weight_g)
+            VALUES ($1, $2, $3, $4) RETURNING id",
+        )
+        .bind(ctx.user_id())
+        .bind(recipe_c.name)
+        .bind(recipe_c.servings)
+        .bind(recipe_c.cooked_weight_g)
+        .fetch_one(&mut transaction_manager)
+        .await?;
+
+        for ingredient_c in recipe_c.ingredients {
+            RecipeBmc::insert_ingredient(ctx, &mut transaction_manager, id, ingredient_c).await?;
+        }
+
+        transaction_manager.commit().await?;
+        Ok(id)
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Recipe> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, Recipe>(&format!(
+            "SELECT {RECIPE_COLUMNS} FROM recipe WHERE id = $1 AND owner = $2"
+        ))
+        .bind(id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "recipe",
+            id,
+        })
+    }
+
+    /// Recipe owned by the user, or shared with them with at least `permission`.
+    pub async fn get_shared(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        permission: SharePermission,
+    ) -> Result<Recipe> {
+        let db = mm.db();
+
+        let recipe = sqlx::query_as::<_, Recipe>(&format!(
+            "SELECT {RECIPE_COLUMNS} FROM recipe WHERE id = $1"
+        ))
+        .bind(id)
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "recipe",
+            id,
+        })?;
+
+        if recipe.owner != ctx.user_id() {
+            ShareBmc::require(ctx, mm, ShareResource::Recipe, id, permission).await?;
+        }
+
+        Ok(recipe)
+    }
+
+    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<Recipe>> {
+        let db = mm.db();
+
+        let recipes = sqlx::query_as(&format!(
+            "SELECT {RECIPE_COLUMNS} FROM recipe WHERE owner = $1 ORDER BY name, id"
+        ))
+        .bind(ctx.user_id())
+        .fetch_all(db)
+        .await?;
+
+        Ok(recipes)
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        recipe_u: RecipeForUpdate,
+    ) -> Result<()> {
+        recipe_u.validate()?;
+
+        let recipe_to_update = RecipeBmc::get(ctx, mm, id).await?;
+
+        let db = mm.db();
+
+        let count = sqlx::query(
+            "UPDATE recipe SET name = $1, servings = $2, cooked_weight_g = $3
+            WHERE id = $4 AND owner = $5",
+        )
+        .bind(recipe_u.name.unwrap_or(recipe_to_update.name))
+        .bind(recipe_u.servings.unwrap_or(recipe_to_update.servings))
+        .bind(
+            recipe_u
+                .cooked_weight_g
+                .unwrap_or(recipe_to_update.cooked_weight_g),
+        )
+        .bind(id)
+        .bind(ctx.user_id())
+        .execute(db)
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "recipe",
+                id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query("DELETE FROM recipe WHERE id = $1 AND owner = $2")
+            .bind(id)
+            .bind(ctx.user_id())
+            .execute(db)
+            .await?
+            .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "recipe",
+                id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    // -- Ingredients
+
+    async fn insert_ingredient(
+        ctx: &Ctx,
+        transaction_manager: &mut Transaction<'_, Postgres>,
+        recipe_id: i64,
+        ingredient_c: RecipeIngredientForCreate,
+    ) -> Result<i64> {
+        let food_id = ingredient_c.food_id;
+        let quantity_g = UnitBmc::quantity_g(
+            ctx,
+            transaction_manager,
+            food_id,
+            &ingredient_c.quantity,
+            "recipe_ingredient",
+            "quantity_g",
+        )
+        .await?;
+
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO recipe_ingredient (recipe_id, food_id, quantity_g)
+            SELECT $1, id, $2 FROM food WHERE id = $3 AND (owner IS NULL OR owner = $4)
+            RETURNING id",
+        )
+        .bind(recipe_id)
+        .bind(quantity_g)
+        .bind(food_id)
+        .bind(ctx.user_id())
+        .fetch_optional(&mut *transaction_manager)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "food",
+            id: food_id,
+        })?;
+
+        Ok(id)
+    }
+
+    pub async fn add_ingredient(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        recipe_id: i64,
+        ingredient_c: RecipeIngredientForCreate,
+    ) -> Result<i64> {
+        RecipeBmc::get(ctx, mm, recipe_id).await?;
+
+        let mut transaction_manager = mm.db().begin().await?;
+        let id =
+            RecipeBmc::insert_ingredient(ctx, &mut transaction_manager, recipe_id, ingredient_c)
+                .await?;
+        transaction_manager.commit().await?;
+
+        Ok(id)
+    }
+
+    pub async fn get_ingredient(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        recipe_id: i64,
+        id: i64,
+    ) -> Result<RecipeIngredient> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, RecipeIngredient>(&format!(
+            "{RECIPE_INGREDIENT_SELECT} WHERE i.id = $1 AND i.recipe_id = $2 AND r.owner = $3"
+        ))
+        .bind(id)
+        .bind(recipe_id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "recipe_ingredient",
+            id,
+        })
+    }
+
+    pub async fn list_ingredients(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        recipe_id: i64,
+    ) -> Result<Vec<RecipeIngredient>> {
+        RecipeBmc::get_shared(ctx, mm, recipe_id, SharePermission::Read).await?;
+
+        let db = mm.db();
+
+        let ingredients = sqlx::query_as(&format!(
+            "{RECIPE_INGREDIENT_SELECT} WHERE i.recipe_id = $1 ORDER BY i.id"
+        ))
+        .bind(recipe_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(ingredients)
+    }
+
+    /// Ingredients needed to cook the given number of servings.
+    pub async fn scaled_ingredients(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        recipe_id: i64,
+        servings: f32,
+    ) -> Result<Vec<RecipeIngredient>> {
+        let mut errors = FieldErrors::default();
+        errors.check_range("servings", servings.into(), 0.01, RECIPE_MAX_SERVINGS);
+        errors.into_result("recipe_portion")?;
+
+        let recipe = RecipeBmc::get_shared(ctx, mm, recipe_id, SharePermission::Read).await?;
+        let factor = servings / recipe.servings.unwrap_or(1.);
+
+        let ingredients = RecipeBmc::list_ingredients(ctx, mm, recipe_id)
+            .await?
+            .into_iter()
+            .map(|i| RecipeIngredient {
+                quantity_g: i.quantity_g * factor,
+                kcal: i.kcal * factor,
+                carbs: i.carbs * factor,
+                proteins: i.proteins * factor,
+                lipids: i.lipids * factor,
+                ..i
+            })
+            .collect();
+
+        Ok(ingredients)
+    }
+
+    pub async fn update_ingredient(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        recipe_id: i64,
+        id: i64,
+        ingredient_u: RecipeIngredientForUpdate,
+    ) -> Result<()> {
+        let ingredient_to_update = RecipeBmc::get_ingredient(ctx, mm, recipe_id, id).await?;
+        let food_id = ingredient_u.food_id.unwrap_or(ingredient_to_update.food_id);
+
+        let mut connection = mm.db().acquire().await?;
+
+        let quantity_g = if ingredient_u.quantity.is_empty() {
+            ingredient_to_update.quantity_g
+        } else {
+            UnitBmc::quantity_g(
+                ctx,
+                &mut connection,
+                food_id,
+                &ingredient_u.quantity,
+                "recipe_ingredient",
+                "quantity_g",
+            )
+            .await?
+        };
+
+        let count = sqlx::query(
+            "UPDATE recipe_ingredient SET food_id = food.id, quantity_g = $1
+            FROM food
+            WHERE recipe_ingredient.id = $2 AND recipe_ingredient.recipe_id = $3
+                AND food.id = $4 AND (food.owner IS NULL OR food.owner = $5)",
+        )
+        .bind(quantity_g)
+        .bind(id)
+        .bind(recipe_id)
+        .bind(food_id)
+        .bind(ctx.user_id())
+        .execute(&mut connection)
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "food",
+                id: food_id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn remove_ingredient(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        recipe_id: i64,
+        id: i64,
+    ) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query(
+            "DELETE FROM recipe_ingredient USING recipe
+            WHERE recipe_ingredient.id = $1 AND recipe_ingredient.recipe_id = $2
+                AND recipe.id = recipe_ingredient.recipe_id AND recipe.owner = $3",
+        )
+        .bind(id)
+        .bind(recipe_id)
+        .bind(ctx.user_id())
+        .execute(db)
+        .await?
+        .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "recipe_ingredient",
+                id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    // -- Nutrition
+
+    pub async fn nutrition(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<RecipeNutrition> {
+        let recipe = RecipeBmc::get_shared(ctx, mm, id, SharePermission::Read).await?;
+
+        let db = mm.db();
+
+        let (raw_weight_g, kcal, carbs, proteins, lipids) =
+            sqlx::query_as::<_, (f64, f64, f64, f64, f64)>(
+                "SELECT COALESCE(SUM(i.quantity_g), 0)::FLOAT8,
+                    COALESCE(SUM(f.kcal_100g * i.quantity_g / 100), 0)::FLOAT8,
+                    COALESCE(SUM(f.carbs_100g * i.quantity_g / 100), 0)::FLOAT8,
+                    COALESCE(SUM(f.proteins_100g * i.quantity_g / 100), 0)::FLOAT8,
+                    COALESCE(SUM(f.lipids_100g * i.quantity_g / 100), 0)::FLOAT8
+                FROM recipe_ingredient i JOIN food f ON f.id = i.food_id
+                WHERE i.recipe_id = $1",
+            )
+            .bind(id)
+            .fetch_one(db)
+            .await?;
+
+        let total = NutrientAmounts {
+            kcal,
+            carbs,
+            proteins,
+            lipids,
+        };
+        let servings = recipe.servings.unwrap_or(1.);
+        let yield_weight_g = recipe
+            .cooked_weight_g
+            .map(f64::from)
+            .unwrap_or(raw_weight_g);
+        let per_100g = if yield_weight_g > 0. {
+            total.scaled(100. / yield_weight_g)
+        } else {
+            NutrientAmounts::default()
+        };
+
+        Ok(RecipeNutrition {
+            recipe_id: id,
+            servings,
+            raw_weight_g,
+            yield_weight_g,
+            per_serving: total.scaled(1. / f64::from(servings)),
+            per_100g,
+            total,
+        })
+    }
+
+    /// Log a portion of the recipe as a new meal, made of the ingredients
+    /// scaled to the portion. Returns the id of the meal.
+    ///
+    /// A recipe shared with the `log` permission is logged into the diary of
+    /// the user, with their own portion.
+    pub async fn log_portion(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        portion: RecipePortion,
+    ) -> Result<i64> {
+        let recipe = RecipeBmc::get_shared(ctx, mm, id, SharePermission::Log).await?;
+        let nutrition = RecipeBmc::nutrition(ctx, mm, id).await?;
+        let factor = portion.factor(&nutrition)?;
+
+        let ingredients = RecipeBmc::list_ingredients(ctx, mm, id).await?;
+        let largest_g = ingredients
+            .iter()
+            .map(|i| f64::from(i.quantity_g))
+            .fold(0., f64::max);
+        portion.check_entries(&nutrition, factor, largest_g)?;
+
+        let entries = ingredients
+            .into_iter()
+            .filter_map(|i| MealEntryForCreate::scaled(i.food_id, i.quantity_g, factor))
+            .collect();
+
+        let meal_c = MealForCreate {
+            name: recipe.name,
+            entries,
+            consumed_at: portion.consumed_at,
+            slot: portion.slot,
+            ..Default::default()
+        };
+        let meal_c = without_foreign_foods(ctx, mm, meal_c).await?;
+
+        MealBmc::create(ctx, mm, meal_c).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        _dev_utils::dev_init_tests,
+        model::food::{FoodBmc, FoodForCreate},
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    async fn fixture_recipe(ctx: &Ctx, mm: &ModelManager, name: &str) -> Result<(i64, i64)> {
+        let food_id = FoodBmc::create(
+            ctx,
+            mm,
+            FoodForCreate {
+                name: format!("{name} food"),
+                kcal_100g: 100.,
+                carbs_100g: 20.,
+                proteins_100g: 5.,
+                lipids_100g: 0.,
+                alcohol_100g: 0.,
+                density_g_ml: None,
+            },
+        )
+        .await?;
+
+        let recipe_id = RecipeBmc::create(
+            ctx,
+            mm,
+            RecipeForCreate {
+                name: name.to_string(),
+                servings: Some(4.),
+                cooked_weight_g: Some(1600.),
+                ingredients: vec![RecipeIngredientForCreate {
+                    food_id,
+                    quantity: Quantity::grams(2000.),
+                }],
+            },
+        )
+        .await?;
+
+        Ok((recipe_id, food_id))
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_nutrition_ok() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let (recipe_id, food_id) = fixture_recipe(&ctx, &mm, "test_nutrition_ok").await?;
+
+        let nutrition = RecipeBmc::nutrition(&ctx, &mm, recipe_id).await?;
+
+        assert_eq!(nutrition.raw_weight_g, 2000.);
+        assert_eq!(nutrition.total.kcal, 2000.);
+        assert_eq!(nutrition.per_serving.kcal, 500.);
+        assert_eq!(nutrition.per_100g.kcal, 125.);
+
+        let scaled = RecipeBmc::scaled_ingredients(&ctx, &mm, recipe_id, 2.).await?;
+        assert_eq!(scaled[0].quantity_g, 1000.);
+
+        RecipeBmc::delete(&ctx, &mm, recipe_id).await?;
+        FoodBmc::delete(&ctx, &mm, food_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_log_portion_ok() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let (recipe_id, food_id) = fixture_recipe(&ctx, &mm, "test_log_portion_ok").await?;
+
+        let by_serving = RecipePortion {
+            servings: Some(1.),
+            ..Default::default()
+        };
+        let by_weight = RecipePortion {
+            quantity: Quantity {
+                amount: Some(0.2),
+                unit: Some(Unit::Kg),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let by_volume = RecipePortion {
+            quantity: Quantity {
+                unit: Some(Unit::Cup),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let serving_meal_id = RecipeBmc::log_portion(&ctx, &mm, recipe_id, by_serving).await?;
+        let weight_meal_id = RecipeBmc::log_portion(&ctx, &mm, recipe_id, by_weight).await?;
+
+        let serving_meal = MealBmc::get(&ctx, &mm, serving_meal_id).await?;
+        let weight_meal = MealBmc::get(&ctx, &mm, weight_meal_id).await?;
+
+        assert_eq!(serving_meal.name, "test_log_portion_ok");
+        assert_eq!(serving_meal.kcal, 500);
+        assert_eq!(weight_meal.kcal, 250);
+
+        let res = RecipeBmc::log_portion(&ctx, &mm, recipe_id, RecipePortion::default()).await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+        let res = RecipeBmc::log_portion(&ctx, &mm, recipe_id, by_volume).await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+
+        // 21 servings need 10.5 kg of the 2 kg ingredient.
+        let oversized = RecipePortion {
+            servings: Some(21.),
+            ..Default::default()
+        };
+        let res = RecipeBmc::log_portion(&ctx, &mm, recipe_id, oversized).await;
+        let Err(Error::Validation { entity, errors }) = res else {
+            panic!("Oversized portion should fail, was {res:?}");
+        };
+        assert_eq!(entity, "recipe_portion");
+        assert_eq!(errors[0].field, "servings");
+
+        for id in [serving_meal_id, weight_meal_id] {
+            MealBmc::delete(&ctx, &mm, id).await?;
+            MealBmc::purge(&ctx, &mm, id).await?;
+        }
+        RecipeBmc::delete(&ctx, &mm, recipe_id).await?;
+        FoodBmc::delete(&ctx, &mm, food_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_update_clears_yield() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let (recipe_id, food_id) = fixture_recipe(&ctx, &mm, "test_update_clears_yield").await?;
+
+        let recipe_u: RecipeForUpdate = serde_json::from_str(r#"{"cooked_weight_g": null}"#)?;
+        RecipeBmc::update(&ctx, &mm, recipe_id, recipe_u).await?;
+        let recipe = RecipeBmc::get(&ctx, &mm, recipe_id).await?;
+
+        assert_eq!(recipe.cooked_weight_g, None);
+        assert_eq!(recipe.servings, Some(4.));
+
+        RecipeBmc::delete(&ctx, &mm, recipe_id).await?;
+        FoodBmc::delete(&ctx, &mm, food_id).await?;
+
+        Ok(())
+    }
+}
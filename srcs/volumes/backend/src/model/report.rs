@@ -0,0 +1,396 @@
+use std::num::NonZeroU32;
+
+use serde::Serialize;
+use sqlx::prelude::FromRow;
+use time::{Date, Duration};
+
+use crate::ctx::Ctx;
+use crate::utils::time_utils::day_range_utc;
+
+use crate::model::{Error, Result};
+
+use super::{alcohol::DrinkGuideline, meal::NutritionTotals, ModelManager};
+
+/// Longest range a report can cover.
+const REPORT_MAX_DAYS: i64 = 366;
+
+/// Period covered by a report, week and month are the ones containing the date.
+#[derive(Debug, Clone, Copy)]
+pub enum ReportRange {
+    Week(Date),
+    Month(Date),
+    Custom { from: Date, to: Date },
+}
+
+impl ReportRange {
+    /// First and last day of the range, both included. Weeks start on monday.
+    pub fn bounds(&self) -> Result<(Date, Date)> {
+        let (from, to) = match *self {
+            ReportRange::Week(date) => {
+                let days_from_monday = date.weekday().number_days_from_monday();
+                let from = date
+                    .checked_sub(Duration::days(days_from_monday.into()))
+                    .ok_or(Error::InvalidDateRange {
+                        from: date,
+                        to: date,
+                    })?;
+                let to = from
+                    .checked_add(Duration::days(6))
+                    .ok_or(Error::InvalidDateRange { from, to: date })?;
+                (from, to)
+            }
+            ReportRange::Month(date) => {
+                let last_day = date.month().length(date.year());
+                // Safe, every month has a first and a last day.
+                (
+                    date.replace_day(1).unwrap(),
+                    date.replace_day(last_day).unwrap(),
+                )
+            }
+            ReportRange::Custom { from, to } => (from, to),
+        };
+
+        if from > to || (to - from).whole_days() >= REPORT_MAX_DAYS {
+            return Err(Error::InvalidDateRange { from, to });
+        }
+
+        Ok((from, to))
+    }
+}
+
+/// Kcal target a report checks adherence against, a day is on target when
+/// its kcal are within `tolerance_pct` percent of the target.
+#[derive(Debug, Clone, Copy)]
+pub struct AdherenceTarget {
+    pub kcal: NonZeroU32,
+    pub tolerance_pct: u32,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct DayNutrition {
+    pub date: Date,
+    pub meal_count: i64,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub totals: NutritionTotals,
+    pub alcohol_g: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NutrientStats {
+    pub average: f64,
+    pub min: i64,
+    pub max: i64,
+}
+
+/// Statistics over the logged days of a report, days without any meal are
+/// left out so they don't drag the averages down.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrendStats {
+    pub kcal: NutrientStats,
+    pub carbs: NutrientStats,
+    pub proteins: NutrientStats,
+    pub lipids: NutrientStats,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Adherence {
+    pub kcal_target: u32,
+    pub tolerance_pct: u32,
+    pub days_on_target: i64,
+    /// Share of the logged days on target.
+    pub percent: f64,
+}
+
+/// Standard drinks over the whole range, days without meals included.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlcoholStats {
+    pub standard_drinks: f64,
+    pub drinking_days: i64,
+    pub days_over_day_limit: i64,
+    pub weekly_average: f64,
+    pub over_week_limit: bool,
+    pub guideline: DrinkGuideline,
+}
+
+/// Meals carrying a tag, and the kcal of the days they were eaten compared
+/// with the other logged days.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct TagStats {
+    pub tag: String,
+    pub meal_count: i64,
+    pub days: i64,
+    /// Kcal of the tagged meals only.
+    pub kcal: i64,
+    pub day_kcal_average: f64,
+    /// None when every logged day has the tag.
+    pub other_days_kcal_average: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NutritionReport {
+    pub from: Date,
+    pub to: Date,
+    pub days_total: i64,
+    pub days_logged: i64,
+    pub series: Vec<DayNutrition>,
+    pub stats: Option<TrendStats>,
+    pub adherence: Option<Adherence>,
+    pub alcohol: AlcoholStats,
+    pub tags: Vec<TagStats>,
+}
+
+pub struct ReportBmc {}
+
+impl ReportBmc {
+    pub async fn nutrition(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        range: ReportRange,
+        target: Option<AdherenceTarget>,
+    ) -> Result<NutritionReport> {
+        let db = mm.db();
+
+        let (from, to) = range.bounds()?;
+        let (start, end) = day_range_utc(from, to).ok_or(Error::InvalidDateRange { from, to })?;
+
+        let series: Vec<DayNutrition> = sqlx::query_as(
+            "SELECT (d AT TIME ZONE 'UTC')::DATE AS date, COUNT(m.id) AS meal_count,
+                COALESCE(SUM(m.kcal), 0)::BIGINT AS kcal,
+                COALESCE(SUM(m.carbs), 0)::BIGINT AS carbs,
+                COALESCE(SUM(m.proteins), 0)::BIGINT AS proteins,
+                COALESCE(SUM(m.lipids), 0)::BIGINT AS lipids,
+                COALESCE(SUM((m.nutrients->>'alcohol')::FLOAT8), 0) AS alcohol_g
+            FROM generate_series($2, $3 - INTERVAL '1 day', INTERVAL '1 day') AS d
+            LEFT JOIN meal m ON m.owner = $1 AND m.deleted_at IS NULL
+                AND m.consumed_at >= d AND m.consumed_at < d + INTERVAL '1 day'
+            GROUP BY d
+            ORDER BY d",
+        )
+        .bind(ctx.user_id())
+        .bind(start)
+        .bind(end)
+        .fetch_all(db)
+        .await?;
+
+        let tags: Vec<TagStats> = sqlx::query_as(
+            "WITH days AS (
+                SELECT (consumed_at AT TIME ZONE 'UTC')::DATE AS date, SUM(kcal) AS kcal
+                FROM meal
+                WHERE owner = $1 AND deleted_at IS NULL AND consumed_at >= $2 AND consumed_at < $3
+                GROUP BY 1
+            ), tagged AS (
+                SELECT t.tag, (m.consumed_at AT TIME ZONE 'UTC')::DATE AS date,
+                    COUNT(*) AS meal_count, SUM(m.kcal) AS kcal
+                FROM meal m, unnest(m.tags) AS t(tag)
+                WHERE m.owner = $1 AND m.deleted_at IS NULL
+                    AND m.consumed_at >= $2 AND m.consumed_at < $3
+                GROUP BY 1, 2
+            )
+            SELECT t.tag, SUM(t.meal_count)::BIGINT AS meal_count, COUNT(*) AS days,
+                SUM(t.kcal)::BIGINT AS kcal, AVG(d.kcal)::FLOAT8 AS day_kcal_average,
+                (SELECT AVG(o.kcal) FROM days o
+                    WHERE o.date NOT IN (SELECT date FROM tagged WHERE tag = t.tag)
+                )::FLOAT8 AS other_days_kcal_average
+            FROM tagged t JOIN days d ON d.date = t.date
+            GROUP BY t.tag
+            ORDER BY t.tag",
+        )
+        .bind(ctx.user_id())
+        .bind(start)
+        .bind(end)
+        .fetch_all(db)
+        .await?;
+
+        let logged: Vec<&DayNutrition> = series.iter().filter(|d| d.meal_count > 0).collect();
+
+        Ok(NutritionReport {
+            from,
+            to,
+            days_total: series.len() as i64,
+            days_logged: logged.len() as i64,
+            stats: trend_stats(&logged),
+            adherence: target.map(|target| adherence(&logged, target)),
+            alcohol: alcohol_stats(&series, DrinkGuideline::from_config()),
+            series,
+            tags,
+        })
+    }
+}
+
+fn nutrient_stats(days: &[&DayNutrition], value: fn(&NutritionTotals) -> i64) -> NutrientStats {
+    let values: Vec<i64> = days.iter().map(|d| value(&d.totals)).collect();
+
+    NutrientStats {
+        average: values.iter().sum::<i64>() as f64 / values.len() as f64,
+        min: values.iter().copied().min().unwrap_or_default(),
+        max: values.iter().copied().max().unwrap_or_default(),
+    }
+}
+
+fn trend_stats(days: &[&DayNutrition]) -> Option<TrendStats> {
+    if days.is_empty() {
+        return None;
+    }
+
+    Some(TrendStats {
+        kcal: nutrient_stats(days, |t| t.kcal),
+        carbs: nutrient_stats(days, |t| t.carbs),
+        proteins: nutrient_stats(days, |t| t.proteins),
+        lipids: nutrient_stats(days, |t| t.lipids),
+    })
+}
+
+fn adherence(days: &[&DayNutrition], target: AdherenceTarget) -> Adherence {
+    let kcal_target = target.kcal.get();
+    let tolerance = f64::from(kcal_target) * f64::from(target.tolerance_pct) / 100.;
+
+    let days_on_target = days
+        .iter()
+        .filter(|d| (d.totals.kcal as f64 - f64::from(kcal_target)).abs() <= tolerance)
+        .count() as i64;
+
+    let percent = if days.is_empty() {
+        0.
+    } else {
+        days_on_target as f64 * 100. / days.len() as f64
+    };
+
+    Adherence {
+        kcal_target,
+        tolerance_pct: target.tolerance_pct,
+        days_on_target,
+        percent,
+    }
+}
+
+fn alcohol_stats(days: &[DayNutrition], guideline: DrinkGuideline) -> AlcoholStats {
+    let drinks: Vec<f64> = days
+        .iter()
+        .map(|d| guideline.standard_drinks(d.alcohol_g))
+        .collect();
+
+    let standard_drinks: f64 = drinks.iter().sum();
+    let weekly_average = if days.is_empty() {
+        0.
+    } else {
+        standard_drinks * 7. / days.len() as f64
+    };
+
+    AlcoholStats {
+        standard_drinks,
+        drinking_days: drinks.iter().filter(|&&d| d > 0.).count() as i64,
+        days_over_day_limit: drinks.iter().filter(|&&d| d > guideline.day_limit).count() as i64,
+        weekly_average,
+        over_week_limit: weekly_average > guideline.week_limit,
+        guideline,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use crate::{
+        _dev_utils::dev_init_tests,
+        model::meal::{MealBmc, MealForCreate},
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    #[test]
+    fn test_range_bounds_ok() -> Result<()> {
+        let fx_date = Date::from_calendar_date(2024, Month::February, 14)?;
+
+        let (from, to) = ReportRange::Week(fx_date).bounds()?;
+        assert_eq!(from, Date::from_calendar_date(2024, Month::February, 12)?);
+        assert_eq!(to, Date::from_calendar_date(2024, Month::February, 18)?);
+
+        let (from, to) = ReportRange::Month(fx_date).bounds()?;
+        assert_eq!(from, Date::from_calendar_date(2024, Month::February, 1)?);
+        assert_eq!(to, Date::from_calendar_date(2024, Month::February, 29)?);
+
+        let res = ReportRange::Custom {
+            from: fx_date,
+            to: fx_date - Duration::days(1),
+        }
+        .bounds();
+        assert!(matches!(res, Err(Error::InvalidDateRange { .. })));
+
+        // The week of the last representable day ends after it.
+        let res = ReportRange::Week(Date::MAX).bounds();
+        assert!(matches!(res, Err(Error::InvalidDateRange { .. })));
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_nutrition_report_ok() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_from = Date::from_calendar_date(2022, Month::January, 3)?;
+        let fx_days = [(0, 1800), (1, 2500), (3, 2000)];
+
+        let mut ids = Vec::new();
+        for (offset, kcal) in fx_days {
+            let day = fx_from + Duration::days(offset);
+            let meal_c = MealForCreate {
+                name: "test_nutrition_report_ok".to_string(),
+                kcal: Some(kcal),
+                consumed_at: Some(day.with_hms(12, 0, 0)?.assume_utc()),
+                // Out on the 2500 kcal day only.
+                tags: if kcal == 2500 {
+                    vec!["Eating out".to_string()]
+                } else {
+                    Vec::new()
+                },
+                ..Default::default()
+            };
+            ids.push(MealBmc::create(&ctx, &mm, meal_c).await?);
+        }
+
+        let report = ReportBmc::nutrition(
+            &ctx,
+            &mm,
+            ReportRange::Week(fx_from),
+            Some(AdherenceTarget {
+                kcal: NonZeroU32::new(2000).unwrap(),
+                tolerance_pct: 10,
+            }),
+        )
+        .await?;
+
+        assert_eq!(report.days_total, 7);
+        assert_eq!(report.days_logged, 3);
+        assert_eq!(report.series[1].totals.kcal, 2500);
+        assert_eq!(report.series[2].meal_count, 0);
+
+        let stats = report.stats.expect("Should have stats");
+        assert_eq!(stats.kcal.average, 2100.);
+        assert_eq!(stats.kcal.min, 1800);
+        assert_eq!(stats.kcal.max, 2500);
+
+        let adherence = report.adherence.expect("Should have adherence");
+        assert_eq!(adherence.days_on_target, 2);
+
+        assert_eq!(report.alcohol.standard_drinks, 0.);
+        assert_eq!(report.alcohol.drinking_days, 0);
+
+        let [tag] = report.tags.as_slice() else {
+            panic!("Should have a single tag, got {:?}", report.tags);
+        };
+        assert_eq!(tag.tag, "eating out");
+        assert_eq!(tag.days, 1);
+        assert_eq!(tag.day_kcal_average, 2500.);
+        assert_eq!(tag.other_days_kcal_average, Some(1900.));
+
+        for id in ids {
+            MealBmc::delete(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use crate::ctx::Ctx;
+
+use crate::model::Result;
+
+use super::{
+    tag::{check_tags, normalize_tag},
+    validation::FieldErrors,
+    ModelManager,
+};
+
+/// Lowest word similarity, from 0 to 1, for a name to match the query.
+const SEARCH_SIMILARITY_THRESHOLD: f64 = 0.3;
+/// Weight of the user history in the ranking.
+const SEARCH_HISTORY_BOOST: f64 = 0.25;
+const SEARCH_QUERY_MAX_LEN: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchKind {
+    Food,
+    Meal,
+}
+
+impl SearchKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchKind::Food => "food",
+            SearchKind::Meal => "meal",
+        }
+    }
+}
+
+impl From<String> for SearchKind {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "meal" => SearchKind::Meal,
+            _ => SearchKind::Food,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub text: String,
+    /// Both foods and meals when None.
+    pub kind: Option<SearchKind>,
+    /// Only meals carrying the tag, foods have no tags.
+    pub tag: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// A food, or the latest of the meals logged under a name.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct SearchHit {
+    #[sqlx(try_from = "String")]
+    pub kind: SearchKind,
+    pub id: i64,
+    pub name: String,
+    /// Food hits only.
+    pub kcal_100g: Option<f64>,
+    /// Meal hits only, for the whole meal.
+    pub kcal: Option<f64>,
+    pub similarity: f64,
+    /// Times the user logged the food or the meal.
+    pub log_count: i64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    /// Offset of the next page, None on the last one.
+    pub next_offset: Option<i64>,
+}
+
+impl SearchQuery {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+        errors.check_text("q", &self.text, SEARCH_QUERY_MAX_LEN);
+        if let Some(tag) = &self.tag {
+            check_tags(&mut errors, &[normalize_tag(tag)]);
+        }
+        errors.into_result("search")
+    }
+}
+
+pub struct SearchBmc {}
+
+impl SearchBmc {
+    /// Typo tolerant search over the visible foods and the user meals,
+    /// ranked by similarity boosted by how often the user logged them.
+    pub async fn search(ctx: &Ctx, mm: &ModelManager, query: SearchQuery) -> Result<SearchResults> {
+        query.validate()?;
+
+        let mut transaction_manager = mm.db().begin().await?;
+
+        // Lets the trigram indexes serve the `<%` operator with our threshold.
+        sqlx::query(&format!(
+            "SET LOCAL pg_trgm.word_similarity_threshold = {SEARCH_SIMILARITY_THRESHOLD}"
+        ))
+        .execute(&mut transaction_manager)
+        .await?;
+
+        let mut hits: Vec<SearchHit> = sqlx::query_as(
+            "WITH hits AS (
+                SELECT 'food' AS kind, f.id, f.name, f.kcal_100g::FLOAT8 AS kcal_100g,
+                    NULL::FLOAT8 AS kcal,
+                    word_similarity($2, f.name)::FLOAT8 AS similarity,
+                    (SELECT COUNT(*) FROM meal_entry e JOIN meal m ON m.id = e.meal_id
+                        WHERE e.food_id = f.id AND m.owner = $1 AND m.deleted_at IS NULL) AS log_count
+                FROM food f
+                WHERE (f.owner IS NULL OR f.owner = $1) AND $2 <% f.name AND $7::TEXT IS NULL
+                UNION ALL
+                SELECT 'meal', (ARRAY_AGG(id ORDER BY consumed_at DESC, id DESC))[1],
+                    (ARRAY_AGG(name ORDER BY consumed_at DESC, id DESC))[1],
+                    NULL,
+                    (ARRAY_AGG(kcal ORDER BY consumed_at DESC, id DESC))[1]::FLOAT8,
+                    MAX(word_similarity($2, name))::FLOAT8,
+                    COUNT(*)
+                FROM meal
+                WHERE owner = $1 AND deleted_at IS NULL AND $2 <% name
+                    AND ($7::TEXT IS NULL OR tags @> ARRAY[$7])
+                GROUP BY LOWER(TRIM(name))
+            )
+            SELECT kind, id, name, kcal_100g, kcal, similarity, log_count,
+                (similarity * (1 + LN(1 + log_count) * $3))::FLOAT8 AS score
+            FROM hits
+            WHERE $4::TEXT IS NULL OR kind = $4
+            ORDER BY score DESC, name, id
+            LIMIT $5 OFFSET $6",
+        )
+        .bind(ctx.user_id())
+        .bind(query.text.trim())
+        .bind(SEARCH_HISTORY_BOOST)
+        .bind(query.kind.map(|kind| kind.as_str()))
+        // One more hit tells whether there is a next page.
+        .bind(query.limit + 1)
+        .bind(query.offset)
+        .bind(query.tag.as_deref().map(normalize_tag))
+        .fetch_all(&mut transaction_manager)
+        .await?;
+
+        transaction_manager.commit().await?;
+
+        let next_offset = (hits.len() as i64 > query.limit).then(|| {
+            hits.truncate(query.limit as usize);
+            query.offset + query.limit
+        });
+
+        Ok(SearchResults { hits, next_offset })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        _dev_utils::dev_init_tests,
+        model::{
+            meal::{MealBmc, MealForCreate},
+            meal_entry::MealEntryForCreate,
+            unit::Quantity,
+        },
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    fn fx_query(text: &str, kind: Option<SearchKind>) -> SearchQuery {
+        SearchQuery {
+            text: text.to_string(),
+            kind,
+            tag: None,
+            limit: 10,
+            offset: 0,
+        }
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_search_typo_tolerant() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+
+        let results = SearchBmc::search(&ctx, &mm, fx_query("brocoli", None)).await?;
+
+        assert_eq!(results.hits[0].name, "Broccoli, raw");
+        assert_eq!(results.hits[0].kind, SearchKind::Food);
+        assert!(results.next_offset.is_none());
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_search_history_boost_and_pages() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_cooked = ["Rice, white, cooked", "Chicken breast, cooked"];
+
+        // Both foods match "cooked" equally, history decides.
+        let before =
+            SearchBmc::search(&ctx, &mm, fx_query("cooked", Some(SearchKind::Food))).await?;
+        let second = before.hits[1].clone();
+        assert!(fx_cooked.contains(&second.name.as_str()));
+
+        let meal_id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_search_history_boost_and_pages".to_string(),
+                entries: vec![MealEntryForCreate {
+                    food_id: second.id,
+                    quantity: Quantity::grams(100.),
+                }],
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let mut query = fx_query("cooked", Some(SearchKind::Food));
+        query.limit = 1;
+        let first_page = SearchBmc::search(&ctx, &mm, query.clone()).await?;
+        assert_eq!(first_page.hits[0].id, second.id);
+        assert_eq!(first_page.hits[0].log_count, 1);
+        assert_eq!(first_page.next_offset, Some(1));
+        assert!(first_page.hits[0].kcal_100g.is_some());
+        assert!(first_page.hits[0].kcal.is_none());
+
+        let meals = SearchBmc::search(
+            &ctx,
+            &mm,
+            fx_query("test_search_history_boost", Some(SearchKind::Meal)),
+        )
+        .await?;
+        let meal = MealBmc::get(&ctx, &mm, meal_id).await?;
+        assert_eq!(meals.hits[0].id, meal_id);
+        assert_eq!(meals.hits[0].kcal, Some(f64::from(meal.kcal)));
+        assert!(meals.hits[0].kcal_100g.is_none());
+
+        query.offset = 1;
+        let second_page = SearchBmc::search(&ctx, &mm, query).await?;
+        assert_ne!(second_page.hits[0].id, second.id);
+
+        MealBmc::delete(&ctx, &mm, meal_id).await?;
+
+        Ok(())
+    }
+}
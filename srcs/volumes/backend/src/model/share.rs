@@ -0,0 +1,673 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use time::OffsetDateTime;
+
+use crate::ctx::Ctx;
+
+use crate::model::{Error, Result};
+
+use super::{
+    household::HouseholdBmc,
+    meal::{MealBmc, MealForCreate},
+    nutrient::ALCOHOL,
+    recipe::RecipeBmc,
+    user::user::{User, UserBmc},
+    validation::{FieldError, FieldErrorKind, FieldErrors},
+    ModelManager,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareResource {
+    Meal,
+    Recipe,
+}
+
+impl ShareResource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareResource::Meal => "meal",
+            ShareResource::Recipe => "recipe",
+        }
+    }
+
+    /// Column of `share_grant` referencing the resource.
+    fn column(&self) -> &'static str {
+        match self {
+            ShareResource::Meal => "meal_id",
+            ShareResource::Recipe => "recipe_id",
+        }
+    }
+}
+
+impl TryFrom<String> for ShareResource {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        match value.as_str() {
+            "meal" => Ok(ShareResource::Meal),
+            "recipe" => Ok(ShareResource::Recipe),
+            _ => Err(Error::UnknownValue {
+                kind: "share_resource",
+                value,
+            }),
+        }
+    }
+}
+
+/// Ordered so that a higher permission implies the lower ones, logging a
+/// resource into one's own diary implies reading it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SharePermission {
+    #[default]
+    Read,
+    Log,
+}
+
+impl SharePermission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SharePermission::Read => "read",
+            SharePermission::Log => "log",
+        }
+    }
+}
+
+impl TryFrom<String> for SharePermission {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        match value.as_str() {
+            "read" => Ok(SharePermission::Read),
+            "log" => Ok(SharePermission::Log),
+            _ => Err(Error::UnknownValue {
+                kind: "share_permission",
+                value,
+            }),
+        }
+    }
+}
+
+/// Access to a meal or a recipe granted by its owner to another user or
+/// to a household.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ShareGrant {
+    pub id: i64,
+    pub owner: i64,
+    #[sqlx(try_from = "String")]
+    pub resource: ShareResource,
+    pub resource_id: i64,
+    /// Set when granted to a user, `household_id` is set otherwise.
+    pub grantee: Option<i64>,
+    pub household_id: Option<i64>,
+    #[sqlx(try_from = "String")]
+    pub permission: SharePermission,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// The grantee is given either by username or by household. Granting the
+/// same grantee again replaces the permission.
+#[derive(Deserialize)]
+pub struct ShareGrantForCreate {
+    pub resource: ShareResource,
+    pub resource_id: i64,
+    pub username: Option<String>,
+    pub household_id: Option<i64>,
+    #[serde(default)]
+    pub permission: SharePermission,
+}
+
+/// Meal or recipe of another user the user has been granted access to.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct SharedItem {
+    pub grant_id: i64,
+    #[sqlx(try_from = "String")]
+    pub resource: ShareResource,
+    pub resource_id: i64,
+    pub name: String,
+    pub owner: i64,
+    pub owner_username: String,
+    /// Set when shared through a household rather than with the user.
+    pub household_id: Option<i64>,
+    #[sqlx(try_from = "String")]
+    pub permission: SharePermission,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+const SHARE_GRANT_COLUMNS: &str = "id, owner,
+    CASE WHEN meal_id IS NOT NULL THEN 'meal' ELSE 'recipe' END AS resource,
+    COALESCE(meal_id, recipe_id) AS resource_id, grantee, household_id, permission, created_at";
+
+const SHARE_USERNAME_MAX_LEN: usize = 128;
+
+impl ShareGrantForCreate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        match (&self.username, self.household_id) {
+            (Some(username), None) => {
+                errors.check_text("username", username, SHARE_USERNAME_MAX_LEN)
+            }
+            (None, Some(_)) => {}
+            (Some(_), Some(_)) => errors.push(
+                "household_id",
+                FieldErrorKind::Conflicting { with: "username" },
+            ),
+            (None, None) => errors.push("username", FieldErrorKind::Missing),
+        }
+
+        errors.into_result("share_grant")
+    }
+}
+
+/// Id of the account with the username, a validation error of `entity`
+/// when there is none.
+pub(in crate::model) async fn user_id_by_username(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    username: &str,
+    entity: &'static str,
+) -> Result<i64> {
+    let user: Option<User> =
+        UserBmc::first_by_username(ctx, mm, username.trim(), "id, username").await?;
+
+    user.map(|user| user.id).ok_or_else(|| Error::Validation {
+        entity,
+        errors: vec![FieldError {
+            field: "username",
+            kind: FieldErrorKind::UnknownUser {
+                username: username.trim().to_string(),
+            },
+        }],
+    })
+}
+
+/// Entries may only reference foods that are public or owned by the user,
+/// a meal made of another user private foods is logged with the totals of
+/// its entries instead.
+pub(in crate::model) async fn without_foreign_foods(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    meal_c: MealForCreate,
+) -> Result<MealForCreate> {
+    let (food_ids, quantities): (Vec<i64>, Vec<f32>) = meal_c
+        .entries
+        .iter()
+        .filter_map(|e| Some((e.food_id, e.quantity.quantity_g?)))
+        .unzip();
+
+    let db = mm.db();
+
+    let (foreign_count, kcal, carbs, proteins, lipids, alcohol_g) =
+        sqlx::query_as::<_, (i64, i32, i32, i32, i32, f64)>(
+            "SELECT COUNT(*) FILTER (WHERE f.owner IS NOT NULL AND f.owner <> $3),
+                COALESCE(ROUND(SUM(f.kcal_100g * q.quantity_g / 100)), 0)::INT,
+                COALESCE(ROUND(SUM(f.carbs_100g * q.quantity_g / 100)), 0)::INT,
+                COALESCE(ROUND(SUM(f.proteins_100g * q.quantity_g / 100)), 0)::INT,
+                COALESCE(ROUND(SUM(f.lipids_100g * q.quantity_g / 100)), 0)::INT,
+                COALESCE(SUM(f.alcohol_100g * q.quantity_g / 100), 0)::FLOAT8
+            FROM unnest($1::BIGINT[], $2::REAL[]) AS q(food_id, quantity_g)
+            JOIN food f ON f.id = q.food_id",
+        )
+        .bind(food_ids)
+        .bind(quantities)
+        .bind(ctx.user_id())
+        .fetch_one(db)
+        .await?;
+
+    if foreign_count == 0 {
+        return Ok(meal_c);
+    }
+
+    let mut nutrients = meal_c.nutrients.clone();
+    if alcohol_g > 0. {
+        nutrients.0.insert(ALCOHOL.to_string(), alcohol_g);
+    } else {
+        nutrients.0.remove(ALCOHOL);
+    }
+
+    Ok(MealForCreate {
+        kcal: Some(kcal),
+        carbs,
+        proteins,
+        lipids,
+        entries: Vec::new(),
+        nutrients,
+        ..meal_c
+    })
+}
+
+pub struct ShareBmc {}
+
+impl ShareBmc {
+    /// Share a meal or a recipe the user owns, returns the id of the grant.
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, grant_c: ShareGrantForCreate) -> Result<i64> {
+        grant_c.validate()?;
+
+        // Only the owner shares a resource.
+        match grant_c.resource {
+            ShareResource::Meal => {
+                MealBmc::get(ctx, mm, grant_c.resource_id).await?;
+            }
+            ShareResource::Recipe => {
+                RecipeBmc::get(ctx, mm, grant_c.resource_id).await?;
+            }
+        }
+
+        let grantee = match &grant_c.username {
+            Some(username) => Some(user_id_by_username(ctx, mm, username, "share_grant").await?),
+            None => None,
+        };
+
+        let db = mm.db();
+
+        // A grant must reach someone else than the owner.
+        let mut errors = FieldErrors::default();
+        if grantee == Some(ctx.user_id()) {
+            errors.push("username", FieldErrorKind::Conflicting { with: "owner" });
+        }
+        if let Some(household_id) = grant_c.household_id {
+            HouseholdBmc::get(ctx, mm, household_id).await?;
+
+            let (has_others,) = sqlx::query_as::<_, (bool,)>(
+                "SELECT EXISTS (SELECT 1 FROM household_member WHERE household_id = $1 AND member <> $2)",
+            )
+            .bind(household_id)
+            .bind(ctx.user_id())
+            .fetch_one(db)
+            .await?;
+            if !has_others {
+                errors.push(
+                    "household_id",
+                    FieldErrorKind::Conflicting { with: "owner" },
+                );
+            }
+        }
+        errors.into_result("share_grant")?;
+
+        let resource_id =
+            |resource: ShareResource| (grant_c.resource == resource).then_some(grant_c.resource_id);
+
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO share_grant (owner, meal_id, recipe_id, grantee, household_id, permission)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (COALESCE(meal_id, 0), COALESCE(recipe_id, 0), COALESCE(grantee, 0),
+                COALESCE(household_id, 0))
+            DO UPDATE SET permission = EXCLUDED.permission
+            RETURNING id",
+        )
+        .bind(ctx.user_id())
+        .bind(resource_id(ShareResource::Meal))
+        .bind(resource_id(ShareResource::Recipe))
+        .bind(grantee)
+        .bind(grant_c.household_id)
+        .bind(grant_c.permission.as_str())
+        .fetch_one(db)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Only visible to the user who granted it.
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<ShareGrant> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, ShareGrant>(&format!(
+            "SELECT {SHARE_GRANT_COLUMNS} FROM share_grant WHERE id = $1 AND owner = $2"
+        ))
+        .bind(id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "share_grant",
+            id,
+        })
+    }
+
+    /// Grants made by the user, latest first.
+    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<ShareGrant>> {
+        let db = mm.db();
+
+        let grants = sqlx::query_as(&format!(
+            "SELECT {SHARE_GRANT_COLUMNS} FROM share_grant WHERE owner = $1
+            ORDER BY created_at DESC, id DESC"
+        ))
+        .bind(ctx.user_id())
+        .fetch_all(db)
+        .await?;
+
+        Ok(grants)
+    }
+
+    /// Grants made by the user on one of their meals or recipes.
+    pub async fn list_for(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        resource: ShareResource,
+        resource_id: i64,
+    ) -> Result<Vec<ShareGrant>> {
+        let db = mm.db();
+
+        let grants = sqlx::query_as(&format!(
+            "SELECT {SHARE_GRANT_COLUMNS} FROM share_grant WHERE owner = $1 AND {} = $2
+            ORDER BY created_at DESC, id DESC",
+            resource.column()
+        ))
+        .bind(ctx.user_id())
+        .bind(resource_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(grants)
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let db = mm.db();
+
+        let count = sqlx::query("DELETE FROM share_grant WHERE id = $1 AND owner = $2")
+            .bind(id)
+            .bind(ctx.user_id())
+            .execute(db)
+            .await?
+            .rows_affected();
+
+        if count == 0 {
+            Err(Error::ItemNotFound {
+                entity: "share_grant",
+                id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Meals and recipes of other users shared with the user, directly or
+    /// through a household, latest first.
+    pub async fn shared_with_me(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<SharedItem>> {
+        let db = mm.db();
+
+        let items = sqlx::query_as(
+            "SELECT g.id AS grant_id,
+                CASE WHEN g.meal_id IS NOT NULL THEN 'meal' ELSE 'recipe' END AS resource,
+                COALESCE(g.meal_id, g.recipe_id) AS resource_id,
+                COALESCE(m.name, r.name) AS name,
+                g.owner, u.username AS owner_username, g.household_id, g.permission, g.created_at
+            FROM share_grant g
+            JOIN \"user\" u ON u.id = g.owner
+            LEFT JOIN meal m ON m.id = g.meal_id
+            LEFT JOIN recipe r ON r.id = g.recipe_id
+            WHERE g.owner <> $1 AND m.deleted_at IS NULL AND (g.grantee = $1
+                OR g.household_id IN (SELECT household_id FROM household_member WHERE member = $1))
+            ORDER BY g.created_at DESC, g.id DESC",
+        )
+        .bind(ctx.user_id())
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Highest permission granted to the user on another user resource,
+    /// None when it is not shared with them.
+    pub async fn permission(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        resource: ShareResource,
+        resource_id: i64,
+    ) -> Result<Option<SharePermission>> {
+        let db = mm.db();
+
+        let permissions: Vec<(String,)> = sqlx::query_as(&format!(
+            "SELECT DISTINCT permission FROM share_grant
+            WHERE {} = $1 AND (grantee = $2
+                OR household_id IN (SELECT household_id FROM household_member WHERE member = $2))",
+            resource.column()
+        ))
+        .bind(resource_id)
+        .bind(ctx.user_id())
+        .fetch_all(db)
+        .await?;
+
+        let permissions = permissions
+            .into_iter()
+            .map(|(permission,)| SharePermission::try_from(permission))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(permissions.into_iter().max())
+    }
+
+    /// `ItemNotFound` when the resource isn't shared with the user, so its
+    /// existence isn't disclosed, `AccessDenied` when the permission is too low.
+    pub(in crate::model) async fn require(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        resource: ShareResource,
+        resource_id: i64,
+        needed: SharePermission,
+    ) -> Result<()> {
+        match ShareBmc::permission(ctx, mm, resource, resource_id).await? {
+            None => Err(Error::ItemNotFound {
+                entity: resource.as_str(),
+                id: resource_id,
+            }),
+            Some(permission) if permission < needed => Err(Error::AccessDenied {
+                entity: resource.as_str(),
+                id: resource_id,
+            }),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        _dev_utils::dev_init_tests,
+        model::{
+            food::{FoodBmc, FoodForCreate},
+            household::{HouseholdBmc, HouseholdForCreate, HouseholdMemberForAdd},
+            meal::MealRelog,
+            meal_entry::MealEntryBmc,
+            recipe::{RecipeForCreate, RecipeIngredientForCreate, RecipePortion},
+            unit::Quantity,
+            user::{user::UserBmc, FullUserBmc, FullUserForCreate},
+        },
+    };
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_household_member_logs_shared_recipe() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_member = FullUserForCreate {
+            username: "test_share_member".to_string(),
+            password_clear: "Welcome".to_string(),
+            age: 30,
+            size_cm: 170,
+            weight: 60.,
+            ..Default::default()
+        };
+        let member_id = FullUserBmc::create_new_user(&Ctx::root_ctx(), &mm, &fx_member).await?;
+        let member_ctx = Ctx::new(member_id)?;
+
+        // Private to demo1, the member can't log it as an entry.
+        let food_id = FoodBmc::create(
+            &ctx,
+            &mm,
+            FoodForCreate {
+                name: "test_household_member_logs_shared_recipe food".to_string(),
+                kcal_100g: 100.,
+                carbs_100g: 20.,
+                proteins_100g: 5.,
+                lipids_100g: 0.,
+                alcohol_100g: 0.,
+                density_g_ml: None,
+            },
+        )
+        .await?;
+        let recipe_id = RecipeBmc::create(
+            &ctx,
+            &mm,
+            RecipeForCreate {
+                name: "test_household_member_logs_shared_recipe".to_string(),
+                servings: Some(4.),
+                cooked_weight_g: None,
+                ingredients: vec![RecipeIngredientForCreate {
+                    food_id,
+                    quantity: Quantity::grams(2000.),
+                }],
+            },
+        )
+        .await?;
+        let fx_portion = || RecipePortion {
+            servings: Some(1.),
+            ..Default::default()
+        };
+
+        let res = RecipeBmc::get_shared(&member_ctx, &mm, recipe_id, SharePermission::Read).await;
+        assert!(matches!(res, Err(Error::ItemNotFound { .. })));
+
+        let household_id = HouseholdBmc::create(
+            &ctx,
+            &mm,
+            HouseholdForCreate {
+                name: "test_household_member_logs_shared_recipe".to_string(),
+            },
+        )
+        .await?;
+        HouseholdBmc::add_member(
+            &ctx,
+            &mm,
+            household_id,
+            HouseholdMemberForAdd {
+                username: fx_member.username.clone(),
+            },
+        )
+        .await?;
+        let fx_grant = |permission| ShareGrantForCreate {
+            resource: ShareResource::Recipe,
+            resource_id: recipe_id,
+            username: None,
+            household_id: Some(household_id),
+            permission,
+        };
+
+        let grant_id = ShareBmc::create(&ctx, &mm, fx_grant(SharePermission::Read)).await?;
+
+        let shared = ShareBmc::shared_with_me(&member_ctx, &mm).await?;
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].owner_username, "demo1");
+        let nutrition = RecipeBmc::nutrition(&member_ctx, &mm, recipe_id).await?;
+        assert_eq!(nutrition.per_serving.kcal, 500.);
+        let res = RecipeBmc::log_portion(&member_ctx, &mm, recipe_id, fx_portion()).await;
+        assert!(matches!(res, Err(Error::AccessDenied { .. })));
+
+        // Granting again only raises the permission.
+        let log_grant_id = ShareBmc::create(&ctx, &mm, fx_grant(SharePermission::Log)).await?;
+        assert_eq!(log_grant_id, grant_id);
+
+        let meal_id = RecipeBmc::log_portion(&member_ctx, &mm, recipe_id, fx_portion()).await?;
+        let meal = MealBmc::get(&member_ctx, &mm, meal_id).await?;
+        assert_eq!(meal.owner, member_id);
+        assert_eq!((meal.kcal, meal.carbs, meal.proteins), (500, 100, 25));
+        assert!(MealEntryBmc::list(&member_ctx, &mm, meal_id)
+            .await?
+            .is_empty());
+
+        HouseholdBmc::remove_member(&member_ctx, &mm, household_id, member_id).await?;
+        assert!(ShareBmc::shared_with_me(&member_ctx, &mm).await?.is_empty());
+
+        MealBmc::delete(&member_ctx, &mm, meal_id).await?;
+        MealBmc::purge(&member_ctx, &mm, meal_id).await?;
+        HouseholdBmc::delete(&ctx, &mm, household_id).await?;
+        RecipeBmc::delete(&ctx, &mm, recipe_id).await?;
+        FoodBmc::delete(&ctx, &mm, food_id).await?;
+        UserBmc::delete(&Ctx::root_ctx(), &mm, member_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_user_relogs_meal_shared_by_username() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let ctx = Ctx::demo1_ctx();
+        let fx_friend = FullUserForCreate {
+            username: "test_share_friend".to_string(),
+            password_clear: "Welcome".to_string(),
+            age: 30,
+            size_cm: 170,
+            weight: 60.,
+            ..Default::default()
+        };
+        let friend_id = FullUserBmc::create_new_user(&Ctx::root_ctx(), &mm, &fx_friend).await?;
+        let friend_ctx = Ctx::new(friend_id)?;
+
+        let meal_id = MealBmc::create(
+            &ctx,
+            &mm,
+            MealForCreate {
+                name: "test_user_relogs_meal_shared_by_username".to_string(),
+                kcal: Some(600),
+                notes: Some("private".to_string()),
+                tags: vec!["cheat_day".to_string()],
+                ..Default::default()
+            },
+        )
+        .await?;
+        let fx_grant = |username: &str, household_id| ShareGrantForCreate {
+            resource: ShareResource::Meal,
+            resource_id: meal_id,
+            username: (!username.is_empty()).then(|| username.to_string()),
+            household_id,
+            permission: SharePermission::Log,
+        };
+
+        let res = ShareBmc::create(&ctx, &mm, fx_grant("demo1", None)).await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+
+        let household_id = HouseholdBmc::create(
+            &ctx,
+            &mm,
+            HouseholdForCreate {
+                name: "test_user_relogs_meal_shared_by_username".to_string(),
+            },
+        )
+        .await?;
+        let res = ShareBmc::create(&ctx, &mm, fx_grant("", Some(household_id))).await;
+        assert!(matches!(res, Err(Error::Validation { .. })));
+
+        let grant_id = ShareBmc::create(&ctx, &mm, fx_grant(&fx_friend.username, None)).await?;
+        let grant = ShareBmc::get(&ctx, &mm, grant_id).await?;
+        assert_eq!(grant.resource, ShareResource::Meal);
+        assert_eq!(grant.grantee, Some(friend_id));
+
+        let shared = ShareBmc::shared_with_me(&friend_ctx, &mm).await?;
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].resource, ShareResource::Meal);
+
+        let relog_id = MealBmc::relog(&friend_ctx, &mm, meal_id, MealRelog::default()).await?;
+        let relogged = MealBmc::get(&friend_ctx, &mm, relog_id).await?;
+        assert_eq!(relogged.owner, friend_id);
+        assert_eq!(relogged.kcal, 600);
+        assert!(relogged.notes.is_none());
+        assert!(relogged.tags.is_empty());
+
+        MealBmc::delete(&friend_ctx, &mm, relog_id).await?;
+        MealBmc::purge(&friend_ctx, &mm, relog_id).await?;
+        MealBmc::delete(&ctx, &mm, meal_id).await?;
+        MealBmc::purge(&ctx, &mm, meal_id).await?;
+        HouseholdBmc::delete(&ctx, &mm, household_id).await?;
+        UserBmc::delete(&Ctx::root_ctx(), &mm, friend_id).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use serde::Serialize;
+use sqlx::prelude::FromRow;
+use time::OffsetDateTime;
+
+use crate::ctx::Ctx;
+
+use crate::model::Result;
+
+use super::{
+    validation::{FieldErrorKind, FieldErrors},
+    ModelManager,
+};
+
+const MEAL_TAG_MAX_LEN: usize = 32;
+const MEAL_TAGS_MAX: usize = 16;
+
+/// Tags are compared trimmed and lowercased, "Eating out" and "eating out"
+/// are the same tag.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Normalized, sorted and without duplicates.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = tags.iter().map(|tag| normalize_tag(tag)).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+pub(in crate::model) fn check_tags(errors: &mut FieldErrors, tags: &[String]) {
+    if tags.len() > MEAL_TAGS_MAX {
+        errors.push("tags", FieldErrorKind::TooMany { max: MEAL_TAGS_MAX });
+    }
+    for tag in tags {
+        errors.check_text("tags", tag, MEAL_TAG_MAX_LEN);
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct TagUsage {
+    pub tag: String,
+    pub meal_count: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_used_at: OffsetDateTime,
+}
+
+pub struct TagBmc {}
+
+impl TagBmc {
+    /// Tags of the user meals, most used first.
+    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<TagUsage>> {
+        let db = mm.db();
+
+        let tags = sqlx::query_as(
+            "SELECT t.tag, COUNT(*) AS meal_count, MAX(m.consumed_at) AS last_used_at
+            FROM meal m, unnest(m.tags) AS t(tag)
+            WHERE m.owner = $1 AND m.deleted_at IS NULL
+            GROUP BY t.tag
+            ORDER BY meal_count DESC, t.tag",
+        )
+        .bind(ctx.user_id())
+        .fetch_all(db)
+        .await?;
+
+        Ok(tags)
+    }
+}
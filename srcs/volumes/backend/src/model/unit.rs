@@ -0,0 +1,486 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+use crate::ctx::Ctx;
+
+use crate::model::{Error, Result};
+
+use super::{
+    food_portion::FoodPortion,
+    meal_entry::{ENTRY_MAX_QUANTITY_G, ENTRY_MIN_QUANTITY_G},
+    validation::{FieldErrorKind, FieldErrors},
+    ModelManager,
+};
+
+/// Unit a food quantity can be given in. Mass units convert on their own,
+/// volume units need the food density or one of its volume portions, any
+/// other name is a portion of the food, e.g. "slice" or "medium".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Unit {
+    G,
+    Kg,
+    Oz,
+    Lb,
+    Ml,
+    L,
+    Tsp,
+    Tbsp,
+    FlOz,
+    Cup,
+    Portion(String),
+}
+
+/// Units that convert without food data, US customary for volumes.
+const STANDARD_UNITS: [Unit; 10] = [
+    Unit::G,
+    Unit::Kg,
+    Unit::Oz,
+    Unit::Lb,
+    Unit::Ml,
+    Unit::L,
+    Unit::Tsp,
+    Unit::Tbsp,
+    Unit::FlOz,
+    Unit::Cup,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitKind {
+    Mass,
+    Volume,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnitInfo {
+    pub code: String,
+    pub kind: UnitKind,
+    /// Grams or millilitres in one unit.
+    pub per_unit: f64,
+}
+
+impl Unit {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Unit::G => "g",
+            Unit::Kg => "kg",
+            Unit::Oz => "oz",
+            Unit::Lb => "lb",
+            Unit::Ml => "ml",
+            Unit::L => "l",
+            Unit::Tsp => "tsp",
+            Unit::Tbsp => "tbsp",
+            Unit::FlOz => "fl_oz",
+            Unit::Cup => "cup",
+            Unit::Portion(name) => name,
+        }
+    }
+
+    /// Grams in one unit, for mass units.
+    pub fn grams(&self) -> Option<f64> {
+        match self {
+            Unit::G => Some(1.),
+            Unit::Kg => Some(1_000.),
+            Unit::Oz => Some(28.349_523_125),
+            Unit::Lb => Some(453.592_37),
+            _ => None,
+        }
+    }
+
+    /// Millilitres in one unit, for volume units.
+    pub fn millilitres(&self) -> Option<f64> {
+        match self {
+            Unit::Ml => Some(1.),
+            Unit::L => Some(1_000.),
+            Unit::Tsp => Some(4.928_921_593_75),
+            Unit::Tbsp => Some(14.786_764_781_25),
+            Unit::FlOz => Some(29.573_529_562_5),
+            Unit::Cup => Some(236.588_236_5),
+            _ => None,
+        }
+    }
+
+    pub fn info(&self) -> Option<UnitInfo> {
+        let (kind, per_unit) = match (self.grams(), self.millilitres()) {
+            (Some(grams), _) => (UnitKind::Mass, grams),
+            (_, Some(millilitres)) => (UnitKind::Volume, millilitres),
+            _ => return None,
+        };
+
+        Some(UnitInfo {
+            code: self.as_str().to_string(),
+            kind,
+            per_unit,
+        })
+    }
+}
+
+pub fn standard_units() -> Vec<UnitInfo> {
+    STANDARD_UNITS.iter().filter_map(Unit::info).collect()
+}
+
+impl From<String> for Unit {
+    fn from(value: String) -> Self {
+        let value = value.trim().to_lowercase();
+        match value.as_str() {
+            "g" | "gram" | "grams" => Unit::G,
+            "kg" | "kilogram" | "kilograms" => Unit::Kg,
+            "oz" | "ounce" | "ounces" => Unit::Oz,
+            "lb" | "lbs" | "pound" | "pounds" => Unit::Lb,
+            "ml" | "millilitre" | "milliliter" | "millilitres" | "milliliters" => Unit::Ml,
+            "l" | "litre" | "liter" | "litres" | "liters" => Unit::L,
+            "tsp" | "teaspoon" | "teaspoons" => Unit::Tsp,
+            "tbsp" | "tablespoon" | "tablespoons" => Unit::Tbsp,
+            "fl_oz" | "fl oz" | "fluid ounce" | "fluid ounces" => Unit::FlOz,
+            "cup" | "cups" => Unit::Cup,
+            _ => Unit::Portion(value),
+        }
+    }
+}
+
+impl From<Unit> for String {
+    fn from(value: Unit) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+/// Quantity of a food, either in grams or as an amount of a unit.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Quantity {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity_g: Option<f32>,
+    /// Amount of `unit`, defaults to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<Unit>,
+}
+
+const QUANTITY_MIN_AMOUNT: f64 = 0.001;
+const QUANTITY_MAX_AMOUNT: f64 = 10_000.;
+const PORTION_NAME_MAX_LEN: usize = 64;
+
+impl Quantity {
+    pub fn grams(quantity_g: f32) -> Self {
+        Quantity {
+            quantity_g: Some(quantity_g),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quantity_g.is_none() && self.amount.is_none() && self.unit.is_none()
+    }
+
+    /// Errors are all reported on the grams field, `field`.
+    pub(in crate::model) fn check(&self, errors: &mut FieldErrors, field: &'static str) {
+        match (self.quantity_g, &self.unit) {
+            (Some(quantity_g), None) => {
+                if self.amount.is_some() {
+                    errors.push(field, FieldErrorKind::Conflicting { with: "amount" });
+                }
+                errors.check_range(
+                    field,
+                    quantity_g.into(),
+                    ENTRY_MIN_QUANTITY_G,
+                    ENTRY_MAX_QUANTITY_G,
+                );
+            }
+            (None, Some(unit)) => {
+                if let Some(amount) = self.amount {
+                    errors.check_range(
+                        field,
+                        amount.into(),
+                        QUANTITY_MIN_AMOUNT,
+                        QUANTITY_MAX_AMOUNT,
+                    );
+                }
+                errors.check_text(field, unit.as_str(), PORTION_NAME_MAX_LEN);
+            }
+            (Some(_), Some(_)) => errors.push(field, FieldErrorKind::Conflicting { with: "unit" }),
+            (None, None) => errors.push(field, FieldErrorKind::Missing),
+        }
+    }
+}
+
+/// What a food offers to convert quantities besides the mass units.
+#[derive(Debug, Clone, Default)]
+pub struct FoodMeasures {
+    pub density_g_ml: Option<f64>,
+    pub portions: Vec<FoodPortion>,
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+impl FoodMeasures {
+    /// Grams in one `unit` of the food, None when it can't be measured so.
+    pub fn unit_grams(&self, unit: &Unit) -> Option<f64> {
+        if let Some(grams) = unit.grams() {
+            return Some(grams);
+        }
+
+        if let Some(millilitres) = unit.millilitres() {
+            // A portion of the same measure, e.g. a cup of chopped broccoli,
+            // beats the density as it accounts for how the food is cut.
+            if let Some(portion) = self.portions.iter().find(|p| p.unit == unit.as_str()) {
+                return Some(portion.unit_grams());
+            }
+
+            let density = self.density_g_ml.or_else(|| {
+                self.portions
+                    .iter()
+                    .find_map(|p| Some(p.unit_grams() / Unit::from(p.unit.clone()).millilitres()?))
+            });
+            return density.map(|density| density * millilitres);
+        }
+
+        let name = unit.as_str();
+        let name_words = words(name);
+
+        // The exact name first, so "glass" isn't read as a plural.
+        self.portions
+            .iter()
+            .find(|p| p.unit == name)
+            .or_else(|| {
+                let singular = name.strip_suffix('s')?;
+                self.portions.iter().find(|p| p.unit == singular)
+            })
+            // "medium egg" matches the "1 medium" portion, and the other way
+            // around.
+            .or_else(|| {
+                self.portions.iter().find(|p| {
+                    let description = words(p.description.as_deref().unwrap_or_default());
+                    !description.is_empty()
+                        && (description.iter().all(|w| name_words.contains(w))
+                            || name_words.iter().all(|w| description.contains(w)))
+                })
+            })
+            .map(FoodPortion::unit_grams)
+    }
+
+    /// Weight of a quantity, None when its unit can't be converted.
+    pub fn grams(&self, quantity: &Quantity) -> Option<f64> {
+        match (&quantity.unit, quantity.quantity_g) {
+            (Some(unit), _) => {
+                Some(f64::from(quantity.amount.unwrap_or(1.)) * self.unit_grams(unit)?)
+            }
+            (None, quantity_g) => quantity_g.map(f64::from),
+        }
+    }
+
+    /// Weight of a checked quantity, within the entry bounds once
+    /// converted. Errors are reported on `field`.
+    pub(in crate::model) fn checked_grams(
+        &self,
+        quantity: &Quantity,
+        entity: &'static str,
+        field: &'static str,
+    ) -> Result<f32> {
+        let mut errors = FieldErrors::default();
+        let quantity_g = match self.grams(quantity) {
+            Some(quantity_g) => {
+                errors.check_range(
+                    field,
+                    quantity_g,
+                    ENTRY_MIN_QUANTITY_G,
+                    ENTRY_MAX_QUANTITY_G,
+                );
+                quantity_g
+            }
+            None => {
+                let unit = quantity.unit.as_ref().map(Unit::as_str).unwrap_or_default();
+                errors.push(
+                    field,
+                    FieldErrorKind::UnconvertibleUnit {
+                        unit: unit.to_string(),
+                    },
+                );
+                0.
+            }
+        };
+        errors.into_result(entity)?;
+
+        Ok(quantity_g as f32)
+    }
+}
+
+/// Weight of a quantity of a food, as needed by the food based APIs.
+#[derive(Debug, Clone, Serialize)]
+pub struct Conversion {
+    pub food_id: i64,
+    pub amount: f32,
+    pub unit: Unit,
+    pub quantity_g: f32,
+}
+
+pub struct UnitBmc {}
+
+impl UnitBmc {
+    pub async fn food_measures(
+        ctx: &Ctx,
+        conn: &mut PgConnection,
+        food_id: i64,
+    ) -> Result<FoodMeasures> {
+        let (density_g_ml,) = sqlx::query_as::<_, (Option<f32>,)>(
+            "SELECT density_g_ml FROM food WHERE id = $1 AND (owner IS NULL OR owner = $2)",
+        )
+        .bind(food_id)
+        .bind(ctx.user_id())
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "food",
+            id: food_id,
+        })?;
+
+        let portions = sqlx::query_as(
+            "SELECT id, food_id, unit, amount, description, gram_weight
+            FROM food_portion WHERE food_id = $1 ORDER BY id",
+        )
+        .bind(food_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(FoodMeasures {
+            density_g_ml: density_g_ml.map(f64::from),
+            portions,
+        })
+    }
+
+    /// Weight in grams of a quantity of a food, checked against the entry
+    /// bounds once converted. Errors are reported on `field`.
+    pub async fn quantity_g(
+        ctx: &Ctx,
+        conn: &mut PgConnection,
+        food_id: i64,
+        quantity: &Quantity,
+        entity: &'static str,
+        field: &'static str,
+    ) -> Result<f32> {
+        let mut errors = FieldErrors::default();
+        quantity.check(&mut errors, field);
+        errors.into_result(entity)?;
+
+        if let (Some(quantity_g), None) = (quantity.quantity_g, &quantity.unit) {
+            return Ok(quantity_g);
+        }
+
+        let measures = UnitBmc::food_measures(ctx, conn, food_id).await?;
+
+        measures.checked_grams(quantity, entity, field)
+    }
+
+    pub async fn convert(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        food_id: i64,
+        amount: f32,
+        unit: Unit,
+    ) -> Result<Conversion> {
+        let quantity = Quantity {
+            quantity_g: None,
+            amount: Some(amount),
+            unit: Some(unit),
+        };
+        let mut connection = mm.db().acquire().await?;
+        let quantity_g = UnitBmc::quantity_g(
+            ctx,
+            &mut connection,
+            food_id,
+            &quantity,
+            "conversion",
+            "amount",
+        )
+        .await?;
+
+        Ok(Conversion {
+            food_id,
+            amount,
+            unit: quantity.unit.unwrap_or(Unit::G),
+            quantity_g,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn fx_portion(
+        unit: &str,
+        amount: f32,
+        description: Option<&str>,
+        gram_weight: f32,
+    ) -> FoodPortion {
+        FoodPortion {
+            id: 0,
+            food_id: 0,
+            unit: unit.to_string(),
+            amount,
+            description: description.map(str::to_string),
+            gram_weight,
+        }
+    }
+
+    #[test]
+    fn test_unit_grams() -> Result<()> {
+        let measures = FoodMeasures {
+            density_g_ml: None,
+            portions: vec![
+                fx_portion("cup", 0.5, Some("chopped"), 44.),
+                fx_portion("piece", 1., Some("1 medium"), 50.),
+                fx_portion("slice", 2., None, 60.),
+                fx_portion("glass", 1., None, 250.),
+            ],
+        };
+        let grams = |unit: &str| measures.unit_grams(&Unit::from(unit.to_string()));
+
+        assert_eq!(grams("Ounces"), Some(28.349_523_125));
+        assert_eq!(grams("cup"), Some(88.));
+        // Density of the cup portion.
+        let tbsp = grams("tbsp").unwrap_or_default();
+        assert!((tbsp - 88. / 16.).abs() < 0.01);
+        assert_eq!(grams("slice"), Some(30.));
+        assert_eq!(grams("medium egg"), Some(50.));
+        assert_eq!(grams("glass"), Some(250.));
+        assert_eq!(grams("wedge"), None);
+
+        let quantity = Quantity {
+            amount: Some(3.),
+            unit: Some(Unit::from("slices".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(measures.grams(&quantity), Some(90.));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantity_check() -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        Quantity::grams(100.).check(&mut errors, "ok");
+        Quantity::default().check(&mut errors, "missing");
+        Quantity {
+            quantity_g: Some(10.),
+            unit: Some(Unit::Cup),
+            ..Default::default()
+        }
+        .check(&mut errors, "conflicting");
+
+        let Err(Error::Validation { errors, .. }) = errors.into_result("meal_entry") else {
+            panic!("Should have failed validation");
+        };
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, ["missing", "conflicting"]);
+
+        Ok(())
+    }
+}
@@ -1,23 +1,28 @@
-use public_user::{PublicUserBmc, PublicUserForCreate};
+use public_user::{check_body_fat, PublicUserBmc, PublicUserForCreate};
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, Transaction};
 use user::{User, UserBmc, UserForCreate, UserForInsert};
 
 
-use crate::{ctx::Ctx, model::Error};
+use crate::{ctx::Ctx, model::{energy::{ActivityLevel, EnergyProfile, Sex}, validation::FieldErrors, weight::{WeightBmc, WeightLogForCreate}, Error}};
 
 use super::{ModelManager, Result};
 
 pub mod user;
 pub mod public_user;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct FullUserForCreate {
     pub username: String,
     pub password_clear: String,
     pub age: i32,
     pub size_cm: i32,
     pub weight: f32,
+    #[serde(default)]
+    pub sex: Sex,
+    #[serde(default)]
+    pub activity_level: ActivityLevel,
+    pub body_fat_pct: Option<f32>,
 }
 
 #[derive(Serialize, FromRow)]
@@ -27,12 +32,36 @@ pub struct FullUser {
     pub age: i32,
     pub size_cm: i32,
     pub weight: f32,
+    #[sqlx(try_from = "String")]
+    pub sex: Sex,
+    #[sqlx(try_from = "String")]
+    pub activity_level: ActivityLevel,
+    pub body_fat_pct: Option<f32>,
+}
+
+/// Account with its energy expenditure, None when the profile lacks the
+/// inputs of every formula.
+#[derive(Serialize)]
+pub struct FullUserWithEnergy {
+    #[serde(flatten)]
+    pub user: FullUser,
+    pub energy: Option<EnergyProfile>,
+}
+
+impl From<FullUser> for FullUserWithEnergy {
+    fn from(user: FullUser) -> Self {
+        let energy = EnergyProfile::new(&(&user).into());
+        FullUserWithEnergy { user, energy }
+    }
 }
 
 pub struct FullUserBmc {}
 
 impl FullUserBmc {
     pub async fn create_new_user(ctx: &Ctx, mm: &ModelManager, full_user_c: &FullUserForCreate) -> Result<i64> {
+        let mut errors = FieldErrors::default();
+        check_body_fat(&mut errors, full_user_c.body_fat_pct);
+        errors.into_result("public_user")?;
 
         let mut transaction_manager = mm.db().begin().await?;
         let user_c = UserForInsert {
@@ -45,11 +74,20 @@ impl FullUserBmc {
             owner: auth_user_id,
             age: full_user_c.age,
             size_cm : full_user_c.size_cm,
-            weight: full_user_c.weight
+            weight: full_user_c.weight,
+            sex: full_user_c.sex,
+            activity_level: full_user_c.activity_level,
+            body_fat_pct: full_user_c.body_fat_pct,
         };
 
         let _public_user_id = PublicUserBmc::create(ctx, & mut transaction_manager, pub_user_c).await?;
 
+        let weight_c = WeightLogForCreate {
+            weight_kg: full_user_c.weight,
+            logged_at: None,
+        };
+        WeightBmc::insert(&mut transaction_manager, auth_user_id, weight_c).await?;
+
         transaction_manager.commit().await?; 
         Ok(auth_user_id)
     }
@@ -57,7 +95,7 @@ impl FullUserBmc {
     pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<FullUser> {
         let db = mm.db();
 
-        sqlx::query_as::<_, FullUser>("SELECT \"user\".id, username, age, size_cm, weight FROM \"user\"
+        sqlx::query_as::<_, FullUser>("SELECT \"user\".id, username, age, size_cm, weight, sex, activity_level, body_fat_pct FROM \"user\"
              JOIN public_user ON public_user.owner = \"user\".id WHERE \"user\".id = $1")
             .bind(id)
             .fetch_optional(db)
@@ -86,7 +124,8 @@ mod tests {
             password_clear: "Welcome".to_string(),
             age: 44,
             size_cm: 182,
-            weight: 51.5
+            weight: 51.5,
+            ..Default::default()
         };
 
         let id = FullUserBmc::create_new_user(&ctx, &mm, &fixture_user).await?;
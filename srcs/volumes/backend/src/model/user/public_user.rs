@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, Transaction};
 
-use crate::{ctx::Ctx, model::{Error, ModelManager, Result}};
+use crate::{ctx::Ctx, model::{energy::{ActivityLevel, Sex}, validation::FieldErrors, weight::{WeightBmc, WeightLogForCreate}, Error, ModelManager, Result}, utils::serde_utils::nullable};
+
+const BODY_FAT_PCT_MIN: f64 = 2.;
+const BODY_FAT_PCT_MAX: f64 = 75.;
+
+const PUBLIC_USER_COLUMNS: &str = "id, owner, age, size_cm, weight, sex, activity_level, body_fat_pct";
 
 
 pub struct PublicUserForCreate {
@@ -9,6 +14,9 @@ pub struct PublicUserForCreate {
     pub age: i32,
     pub size_cm: i32,
     pub weight: f32,
+    pub sex: Sex,
+    pub activity_level: ActivityLevel,
+    pub body_fat_pct: Option<f32>,
 }
 
 #[derive(Debug, FromRow, Serialize)]
@@ -18,13 +26,37 @@ pub struct PublicUser {
     pub age: i32,
     pub size_cm: i32,
     pub weight: f32,
+    #[sqlx(try_from = "String")]
+    pub sex: Sex,
+    #[sqlx(try_from = "String")]
+    pub activity_level: ActivityLevel,
+    pub body_fat_pct: Option<f32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct PublicUserForUpdate {
     pub age: Option<i32>,
     pub size_cm: Option<i32>,
     pub weight: Option<f32>,
+    pub sex: Option<Sex>,
+    pub activity_level: Option<ActivityLevel>,
+    /// `null` clears the body fat.
+    #[serde(default, deserialize_with = "nullable")]
+    pub body_fat_pct: Option<Option<f32>>,
+}
+
+pub(in crate::model) fn check_body_fat(errors: &mut FieldErrors, body_fat_pct: Option<f32>) {
+    if let Some(body_fat_pct) = body_fat_pct {
+        errors.check_range("body_fat_pct", body_fat_pct.into(), BODY_FAT_PCT_MIN, BODY_FAT_PCT_MAX);
+    }
+}
+
+impl PublicUserForUpdate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+        check_body_fat(&mut errors, self.body_fat_pct.flatten());
+        errors.into_result("public_user")
+    }
 }
 
 pub struct PublicUserBmc {}
@@ -33,35 +65,28 @@ impl PublicUserBmc {
     pub async fn create(_ctx: &Ctx, transaction_manager: &mut Transaction<'_, sqlx::Postgres>, pub_user_c: PublicUserForCreate) -> Result<i64> {
 
         let (id, ) = sqlx::query_as::<_, (i64,)>(
-            "INSERT INTO public_user (owner, age, size_cm, weight) VALUES ($1, $2, $3, $4) RETURNING id"
+            "INSERT INTO public_user (owner, age, size_cm, weight, sex, activity_level, body_fat_pct)
+            VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id"
             )
             .bind(pub_user_c.owner)
             .bind(pub_user_c.age)
             .bind(pub_user_c.size_cm)
             .bind(pub_user_c.weight)
+            .bind(pub_user_c.sex.as_str())
+            .bind(pub_user_c.activity_level.as_str())
+            .bind(pub_user_c.body_fat_pct)
             .fetch_one(transaction_manager)
             .await?;
         Ok(id)
     }
 
-    pub async fn get(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<PublicUser> {
-        let db = mm.db();
-
-
-        sqlx::query_as::<_, PublicUser>("SELECT id, owner, age, size_cm, weight FROM public_user WHERE id = $1")
-            .bind(id)
-            .fetch_optional(db)
-            .await?
-            .ok_or(Error::ItemNotFound { entity: "public_user", id })
-    }
-
     pub async fn first_by_owner(ctx: &Ctx, mm: &ModelManager) -> Result<PublicUser> {
         let db = mm.db();
 
         let id = ctx.user_id();
 
 
-        sqlx::query_as::<_, PublicUser>("SELECT id, owner, age, size_cm, weight FROM public_user WHERE owner = $1")
+        sqlx::query_as::<_, PublicUser>(&format!("SELECT {PUBLIC_USER_COLUMNS} FROM public_user WHERE owner = $1"))
             .bind(id)
             .fetch_optional(db)
             .await?
@@ -69,30 +94,112 @@ impl PublicUserBmc {
 
     } 
 
+    /// A new weight is logged in the weight history, the profile weight
+    /// follows its latest entry.
     pub async fn update(ctx: &Ctx, mm: &ModelManager, pub_user_u: PublicUserForUpdate) -> Result<()> {
-        let db = mm.db();
+        pub_user_u.validate()?;
 
         let id = ctx.user_id();
 
         let current_pub_user_data = PublicUserBmc::first_by_owner(ctx, mm)
             .await?;
 
+        let mut transaction_manager = mm.db().begin().await?;
+
         let count = sqlx::query(
-            "UPDATE public_user SET age = $1, size_cm = $2, weight = $3
+            "UPDATE public_user SET age = $1, size_cm = $2, sex = $3, activity_level = $4, body_fat_pct = $5
             WHERE id = $6",
         )
         .bind(pub_user_u.age.unwrap_or(current_pub_user_data.age))
         .bind(pub_user_u.size_cm.unwrap_or(current_pub_user_data.size_cm))
-        .bind(pub_user_u.weight.unwrap_or(current_pub_user_data.weight))
+        .bind(pub_user_u.sex.unwrap_or(current_pub_user_data.sex).as_str())
+        .bind(pub_user_u.activity_level.unwrap_or(current_pub_user_data.activity_level).as_str())
+        .bind(pub_user_u.body_fat_pct.unwrap_or(current_pub_user_data.body_fat_pct))
         .bind(current_pub_user_data.id)
-        .execute(db)
+        .execute(&mut transaction_manager)
         .await?
         .rows_affected();
 
         if count == 0 {
-            Err(Error::ItemNotFound { entity: "public_user", id })
-        } else {
-            Ok(())
+            return Err(Error::ItemNotFound { entity: "public_user", id });
+        }
+
+        if let Some(weight_kg) = pub_user_u.weight {
+            let weight_c = WeightLogForCreate { weight_kg, logged_at: None };
+            WeightBmc::insert(&mut transaction_manager, id, weight_c).await?;
+            WeightBmc::sync_profile(&mut transaction_manager, id).await?;
         }
+
+        transaction_manager.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serial_test::serial;
+
+    use crate::{
+        _dev_utils::dev_init_tests,
+        ctx::Ctx,
+        model::{
+            energy::{ActivityLevel, Sex},
+            user::{user::UserBmc, FullUserBmc, FullUserForCreate},
+        },
+    };
+
+    use super::{PublicUserBmc, PublicUserForUpdate};
+
+    #[serial]
+    #[tokio::test]
+    async fn test_update_energy_fields() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let user_id = FullUserBmc::create_new_user(
+            &Ctx::root_ctx(),
+            &mm,
+            &FullUserForCreate {
+                username: "test_update_energy_fields".to_string(),
+                password_clear: "Welcome".to_string(),
+                age: 30,
+                size_cm: 170,
+                weight: 70.,
+                ..Default::default()
+            },
+        )
+        .await?;
+        let ctx = Ctx::new(user_id)?;
+
+        PublicUserBmc::update(
+            &ctx,
+            &mm,
+            PublicUserForUpdate {
+                sex: Some(Sex::Female),
+                activity_level: Some(ActivityLevel::Active),
+                body_fat_pct: Some(Some(24.)),
+                ..Default::default()
+            },
+        )
+        .await?;
+        let updated = PublicUserBmc::first_by_owner(&ctx, &mm).await?;
+        assert_eq!(updated.sex, Sex::Female);
+        assert_eq!(updated.activity_level, ActivityLevel::Active);
+        assert_eq!(updated.body_fat_pct, Some(24.));
+
+        let pub_user_u: PublicUserForUpdate = serde_json::from_str(r#"{"age": 31}"#)?;
+        PublicUserBmc::update(&ctx, &mm, pub_user_u).await?;
+        let kept = PublicUserBmc::first_by_owner(&ctx, &mm).await?;
+        assert_eq!(kept.age, 31);
+        assert_eq!(kept.body_fat_pct, Some(24.));
+
+        let pub_user_u: PublicUserForUpdate = serde_json::from_str(r#"{"body_fat_pct": null}"#)?;
+        PublicUserBmc::update(&ctx, &mm, pub_user_u).await?;
+        let cleared = PublicUserBmc::first_by_owner(&ctx, &mm).await?;
+        assert_eq!(cleared.body_fat_pct, None);
+        assert_eq!(cleared.sex, Sex::Female);
+
+        UserBmc::delete(&Ctx::root_ctx(), &mm, user_id).await?;
+
+        Ok(())
     }
 }
@@ -0,0 +1,115 @@
+use serde::Serialize;
+
+use crate::model::{Error, Result};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    #[serde(flatten)]
+    pub kind: FieldErrorKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "code")]
+pub enum FieldErrorKind {
+    Empty,
+    Missing,
+    /// Field can't be given together with another one.
+    Conflicting { with: &'static str },
+    TooLong { max: usize },
+    OutOfRange { min: f64, max: f64 },
+    /// Declared kcal too far from the kcal computed from the macros.
+    KcalMismatch { declared: i32, computed: i32 },
+    /// Sum of the macros above the weight they are given for.
+    MacrosOverWeight { total_g: f64, max_g: f64 },
+    /// Kcal of the macros above the kcal target they are given for.
+    MacrosOverKcal { total_kcal: f64, max_kcal: f64 },
+    /// Key outside of a known set, e.g. an unknown nutrient code.
+    UnknownKey { key: String },
+    /// Barcode not made of 8, 12, 13 or 14 digits with a valid check digit.
+    InvalidGtin,
+    /// Unit the food has neither a density nor a portion for.
+    UnconvertibleUnit { unit: String },
+    /// Lower bound of a range above its upper bound field.
+    ReversedRange { upper: &'static str },
+    /// Pagination cursor not issued for the requested sort.
+    InvalidCursor,
+    FileTooLarge { max_bytes: usize },
+    UnsupportedFormat { allowed: &'static [&'static str] },
+    /// File of a supported format that can't be decoded.
+    InvalidImage,
+    /// Collection already holding as many items as allowed.
+    TooMany { max: usize },
+    /// No account with this username.
+    UnknownUser { username: String },
+    /// Shares not adding up to the expected total.
+    WrongTotal { total: f64, expected: f64 },
+}
+
+/// Collects every field error of a payload so they can be reported at once.
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn push(&mut self, field: &'static str, kind: FieldErrorKind) {
+        self.0.push(FieldError { field, kind });
+    }
+
+    pub fn check_text(&mut self, field: &'static str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.push(field, FieldErrorKind::Empty);
+        } else if value.chars().count() > max {
+            self.push(field, FieldErrorKind::TooLong { max });
+        }
+    }
+
+    pub fn check_range(&mut self, field: &'static str, value: f64, min: f64, max: f64) {
+        // Written as a negation so NaN is rejected too.
+        if !(min..=max).contains(&value) {
+            self.push(field, FieldErrorKind::OutOfRange { min, max });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn into_result(self, entity: &'static str) -> Result<()> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation {
+                entity,
+                errors: self.0,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_field_errors_collects_all() -> Result<()> {
+        let mut errors = FieldErrors::default();
+
+        errors.check_text("name", "  ", 10);
+        errors.check_text("slot", "a very long slot", 5);
+        errors.check_range("kcal", -1., 0., 100.);
+        errors.check_range("carbs", f64::NAN, 0., 100.);
+        errors.check_range("lipids", 12., 0., 100.);
+
+        let res = errors.into_result("meal");
+
+        let Err(Error::Validation { entity, errors }) = res else {
+            panic!("Should have failed validation, was {res:?}");
+        };
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(entity, "meal");
+        assert_eq!(fields, ["name", "slot", "kcal", "carbs"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, Postgres, Transaction};
+use time::{Date, OffsetDateTime};
+
+use crate::ctx::Ctx;
+use crate::utils::time_utils::now_utc;
+
+use crate::model::{Error, Result};
+
+use super::{validation::FieldErrors, ModelManager};
+
+const WEIGHT_LOG_MIN_KG: f64 = 2.;
+const WEIGHT_LOG_MAX_KG: f64 = 650.;
+
+const WEIGHT_LOG_COLUMNS: &str = "id, owner, weight_kg, logged_at";
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct WeightLog {
+    pub id: i64,
+    pub owner: i64,
+    pub weight_kg: f32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub logged_at: OffsetDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct WeightLogForCreate {
+    pub weight_kg: f32,
+    /// Defaults to now.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub logged_at: Option<OffsetDateTime>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct WeightLogForUpdate {
+    pub weight_kg: Option<f32>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub logged_at: Option<OffsetDateTime>,
+}
+
+pub(in crate::model) fn check_weight(
+    errors: &mut FieldErrors,
+    field: &'static str,
+    weight_kg: f32,
+) {
+    errors.check_range(
+        field,
+        weight_kg.into(),
+        WEIGHT_LOG_MIN_KG,
+        WEIGHT_LOG_MAX_KG,
+    );
+}
+
+impl WeightLogForCreate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+        check_weight(&mut errors, "weight_kg", self.weight_kg);
+        errors.into_result("weight_log")
+    }
+}
+
+impl WeightLogForUpdate {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::default();
+        if let Some(weight_kg) = self.weight_kg {
+            check_weight(&mut errors, "weight_kg", weight_kg);
+        }
+        errors.into_result("weight_log")
+    }
+}
+
+pub struct WeightBmc {}
+
+impl WeightBmc {
+    /// Log a weight for `owner` without syncing the profile, see
+    /// `sync_profile`.
+    pub(in crate::model) async fn insert(
+        transaction_manager: &mut Transaction<'_, Postgres>,
+        owner: i64,
+        weight_c: WeightLogForCreate,
+    ) -> Result<i64> {
+        weight_c.validate()?;
+
+        let (id,) = sqlx::query_as::<_, (i64,)>(
+            "INSERT INTO weight_log (owner, weight_kg, logged_at) VALUES ($1, $2, $3) RETURNING id",
+        )
+        .bind(owner)
+        .bind(weight_c.weight_kg)
+        .bind(weight_c.logged_at.unwrap_or_else(now_utc))
+        .fetch_one(transaction_manager)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Copy the latest logged weight to the profile. Without any entry left
+    /// the profile keeps its weight.
+    pub(in crate::model) async fn sync_profile(
+        transaction_manager: &mut Transaction<'_, Postgres>,
+        owner: i64,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE public_user SET weight = latest.weight_kg
+            FROM (SELECT weight_kg FROM weight_log WHERE owner = $1
+                ORDER BY logged_at DESC, id DESC LIMIT 1) AS latest
+            WHERE public_user.owner = $1",
+        )
+        .bind(owner)
+        .execute(transaction_manager)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, weight_c: WeightLogForCreate) -> Result<i64> {
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let id = WeightBmc::insert(&mut transaction_manager, ctx.user_id(), weight_c).await?;
+        WeightBmc::sync_profile(&mut transaction_manager, ctx.user_id()).await?;
+
+        transaction_manager.commit().await?;
+        Ok(id)
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<WeightLog> {
+        let db = mm.db();
+
+        sqlx::query_as::<_, WeightLog>(&format!(
+            "SELECT {WEIGHT_LOG_COLUMNS} FROM weight_log WHERE id = $1 AND owner = $2"
+        ))
+        .bind(id)
+        .bind(ctx.user_id())
+        .fetch_optional(db)
+        .await?
+        .ok_or(Error::ItemNotFound {
+            entity: "weight_log",
+            id,
+        })
+    }
+
+    /// Weights logged between two UTC days, both included, oldest first.
+    /// A missing bound leaves that side of the range open.
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        from: Option<Date>,
+        to: Option<Date>,
+    ) -> Result<Vec<WeightLog>> {
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(Error::InvalidDateRange { from, to });
+            }
+        }
+        let start = from.map(|from| from.midnight().assume_utc());
+        let end = match to {
+            Some(to) => {
+                let next_day = to.next_day().ok_or(Error::InvalidDateRange {
+                    from: from.unwrap_or(to),
+                    to,
+                })?;
+                Some(next_day.midnight().assume_utc())
+            }
+            None => None,
+        };
+
+        let db = mm.db();
+
+        let logs = sqlx::query_as(&format!(
+            "SELECT {WEIGHT_LOG_COLUMNS} FROM weight_log
+            WHERE owner = $1
+                AND ($2::TIMESTAMPTZ IS NULL OR logged_at >= $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR logged_at < $3)
+            ORDER BY logged_at, id"
+        ))
+        .bind(ctx.user_id())
+        .bind(start)
+        .bind(end)
+        .fetch_all(db)
+        .await?;
+
+        Ok(logs)
+    }
+
+    /// Weight in effect on a UTC day, the last one logged before its end.
+    /// Days before the history get its first weight, None without history.
+    pub async fn weight_on(ctx: &Ctx, mm: &ModelManager, date: Date) -> Result<Option<f32>> {
+        // The last representable day has no end, every weight is before it.
+        let end = date
+            .next_day()
+            .map(|next_day| next_day.midnight().assume_utc());
+
+        let db = mm.db();
+
+        let (weight_kg,) = sqlx::query_as::<_, (Option<f32>,)>(
+            "SELECT COALESCE(
+                (SELECT weight_kg FROM weight_log
+                    WHERE owner = $1 AND ($2::TIMESTAMPTZ IS NULL OR logged_at < $2)
+                    ORDER BY logged_at DESC, id DESC LIMIT 1),
+                (SELECT weight_kg FROM weight_log WHERE owner = $1
+                    ORDER BY logged_at, id LIMIT 1))",
+        )
+        .bind(ctx.user_id())
+        .bind(end)
+        .fetch_one(db)
+        .await?;
+
+        Ok(weight_kg)
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        weight_u: WeightLogForUpdate,
+    ) -> Result<()> {
+        weight_u.validate()?;
+
+        let weight_to_update = WeightBmc::get(ctx, mm, id).await?;
+
+        let mut transaction_manager = mm.db().begin().await?;
+
+        sqlx::query(
+            "UPDATE weight_log SET weight_kg = $1, logged_at = $2 WHERE id = $3 AND owner = $4",
+        )
+        .bind(weight_u.weight_kg.unwrap_or(weight_to_update.weight_kg))
+        .bind(weight_u.logged_at.unwrap_or(weight_to_update.logged_at))
+        .bind(id)
+        .bind(ctx.user_id())
+        .execute(&mut transaction_manager)
+        .await?;
+
+        WeightBmc::sync_profile(&mut transaction_manager, ctx.user_id()).await?;
+
+        transaction_manager.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let mut transaction_manager = mm.db().begin().await?;
+
+        let count = sqlx::query("DELETE FROM weight_log WHERE id = $1 AND owner = $2")
+            .bind(id)
+            .bind(ctx.user_id())
+            .execute(&mut transaction_manager)
+            .await?
+            .rows_affected();
+
+        if count == 0 {
+            return Err(Error::ItemNotFound {
+                entity: "weight_log",
+                id,
+            });
+        }
+
+        WeightBmc::sync_profile(&mut transaction_manager, ctx.user_id()).await?;
+
+        transaction_manager.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serial_test::serial;
+    use time::{Date, Month};
+
+    use crate::{
+        _dev_utils::dev_init_tests,
+        ctx::Ctx,
+        model::{
+            user::{
+                public_user::{PublicUserBmc, PublicUserForUpdate},
+                user::UserBmc,
+                FullUserBmc, FullUserForCreate,
+            },
+            Error,
+        },
+    };
+
+    use super::{WeightBmc, WeightLogForCreate, WeightLogForUpdate};
+
+    #[serial]
+    #[tokio::test]
+    async fn test_weight_history_keeps_profile_on_latest() -> Result<()> {
+        let mm = dev_init_tests().await;
+        let user_id = FullUserBmc::create_new_user(
+            &Ctx::root_ctx(),
+            &mm,
+            &FullUserForCreate {
+                username: "test_weight_history".to_string(),
+                password_clear: "Welcome".to_string(),
+                age: 30,
+                size_cm: 170,
+                weight: 80.,
+                ..Default::default()
+            },
+        )
+        .await?;
+        let ctx = Ctx::new(user_id)?;
+        let day = |day| -> Result<_> {
+            Ok(Date::from_calendar_date(2024, Month::March, day)?
+                .with_hms(8, 0, 0)?
+                .assume_utc())
+        };
+
+        let old_id = WeightBmc::create(
+            &ctx,
+            &mm,
+            WeightLogForCreate {
+                weight_kg: 78.5,
+                logged_at: Some(day(1)?),
+            },
+        )
+        .await?;
+        // The signup entry is newer than the backfilled one.
+        let profile = PublicUserBmc::first_by_owner(&ctx, &mm).await?;
+        assert_eq!(profile.weight, 80.);
+
+        PublicUserBmc::update(
+            &ctx,
+            &mm,
+            PublicUserForUpdate {
+                weight: Some(77.),
+                ..Default::default()
+            },
+        )
+        .await?;
+        let profile = PublicUserBmc::first_by_owner(&ctx, &mm).await?;
+        assert_eq!(profile.weight, 77.);
+
+        let all = WeightBmc::list(&ctx, &mm, None, None).await?;
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].id, old_id);
+        let march = WeightBmc::list(&ctx, &mm, Some(day(1)?.date()), Some(day(31)?.date())).await?;
+        assert_eq!(march.len(), 1);
+
+        let latest_id = all.last().unwrap().id;
+        WeightBmc::update(
+            &ctx,
+            &mm,
+            latest_id,
+            WeightLogForUpdate {
+                weight_kg: Some(76.5),
+                ..Default::default()
+            },
+        )
+        .await?;
+        let profile = PublicUserBmc::first_by_owner(&ctx, &mm).await?;
+        assert_eq!(profile.weight, 76.5);
+
+        WeightBmc::delete(&ctx, &mm, latest_id).await?;
+        let profile = PublicUserBmc::first_by_owner(&ctx, &mm).await?;
+        assert_eq!(profile.weight, 80.);
+
+        let res = WeightBmc::list(&ctx, &mm, Some(day(2)?.date()), Some(day(1)?.date())).await;
+        assert!(matches!(res, Err(Error::InvalidDateRange { .. })));
+        let res = WeightBmc::list(&ctx, &mm, None, Some(Date::MAX)).await;
+        assert!(matches!(res, Err(Error::InvalidDateRange { .. })));
+        assert_eq!(WeightBmc::weight_on(&ctx, &mm, Date::MAX).await?, Some(80.));
+
+        UserBmc::delete(&Ctx::root_ctx(), &mm, user_id).await?;
+
+        Ok(())
+    }
+}
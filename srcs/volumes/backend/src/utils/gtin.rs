@@ -0,0 +1,60 @@
+/// Length of a GTIN-14, shorter GTINs (EAN-8, UPC-A, EAN-13) are zero padded to it.
+pub const GTIN_LEN: usize = 14;
+
+/// Normalize a scanned EAN/UPC code to a GTIN-14, None when the code isn't
+/// made of 8, 12, 13 or 14 digits or when its check digit is wrong.
+pub fn normalize_gtin(code: &str) -> Option<String> {
+    let code = code.trim();
+
+    if !matches!(code.len(), 8 | 12 | 13 | 14) || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let gtin = format!("{code:0>GTIN_LEN$}");
+    let digits: Vec<u32> = gtin.bytes().map(|b| u32::from(b - b'0')).collect();
+    let (payload, check) = digits.split_at(GTIN_LEN - 1);
+
+    // Weights alternate 3 and 1 starting from the digit next to the check digit.
+    let sum: u32 = payload
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+
+    ((10 - sum % 10) % 10 == check[0]).then_some(gtin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_normalize_gtin_ok() -> Result<()> {
+        assert_eq!(
+            normalize_gtin("4006381333931").as_deref(),
+            Some("04006381333931")
+        );
+        assert_eq!(
+            normalize_gtin(" 036000291452 ").as_deref(),
+            Some("00036000291452")
+        );
+        assert_eq!(
+            normalize_gtin("96385074").as_deref(),
+            Some("00000096385074")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_gtin_invalid() -> Result<()> {
+        assert_eq!(normalize_gtin("4006381333932"), None);
+        assert_eq!(normalize_gtin("400638133393"), None);
+        assert_eq!(normalize_gtin("40063813339a1"), None);
+        assert_eq!(normalize_gtin(""), None);
+
+        Ok(())
+    }
+}
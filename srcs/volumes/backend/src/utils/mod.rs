@@ -1,7 +1,9 @@
 mod error;
 pub mod time_utils;
 pub mod b64;
+pub mod gtin;
 pub mod password;
+pub mod serde_utils;
 
 pub use self::error::{Error, Result};
 
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Deserializer};
+
+/// Tells an explicit `null` from a missing field, for fields that can be
+/// cleared. Used on an `Option<Option<T>>` with
+/// `#[serde(default, deserialize_with = "nullable")]`, a missing field is
+/// `None` and `null` is `Some(None)`.
+pub fn nullable<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -1,6 +1,6 @@
 
 use time::format_description::well_known::Rfc3339;
-use time::{Duration, OffsetDateTime};
+use time::{Date, Duration, OffsetDateTime};
 
 use super::{Error, Result};
 
@@ -21,3 +21,35 @@ pub fn parse_time(moment: &str) -> Result<OffsetDateTime> {
     OffsetDateTime::parse(moment, &Rfc3339)
         .map_err(|_| Error::DateTimeParseFail(moment.to_string()))
 }
+
+/// Half open UTC bounds covering every day from `from` to `to` included,
+/// None when the range is reversed or `to` is the last representable day.
+pub fn day_range_utc(from: Date, to: Date) -> Option<(OffsetDateTime, OffsetDateTime)> {
+    if from > to {
+        return None;
+    }
+
+    let start = from.midnight().assume_utc();
+    let end = to.next_day()?.midnight().assume_utc();
+
+    Some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use time::Month;
+
+    #[test]
+    fn test_day_range_utc_bounds() -> Result<()> {
+        let day = Date::from_calendar_date(2024, Month::February, 29)?;
+        let (start, end) = day_range_utc(day, day).unwrap();
+        assert_eq!(end - start, Duration::days(1));
+
+        assert!(day_range_utc(day.next_day().unwrap(), day).is_none());
+        assert!(day_range_utc(day, Date::MAX).is_none());
+
+        Ok(())
+    }
+}
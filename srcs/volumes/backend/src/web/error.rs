@@ -4,7 +4,7 @@ use axum::{http::StatusCode, response::IntoResponse};
 use serde::Serialize;
 use tracing::debug;
 
-use crate::{crypt, model, web};
+use crate::{crypt, model, model::validation::FieldError, web};
 
 use super::mw_auth::CtxExtError;
 
@@ -28,6 +28,9 @@ pub enum Error {
     UpdateFailedPasswordNotMatching,
     UpdateFailedPasswordTooWeak,
 
+    // Params
+    MissingQueryParam { param: &'static str },
+    InvalidUpload(String),
 }
 
 impl From<model::Error> for Error {
@@ -88,6 +91,47 @@ impl Error {
             Error::AccountCreationFailUsernameAlreadyTaken => {
                 (StatusCode::BAD_REQUEST, ClientError::USERNAME_ALREADY_TAKEN)
             },
+            Error::MissingQueryParam { param } => (
+                StatusCode::BAD_REQUEST,
+                ClientError::MISSING_PARAM { param },
+            ),
+            Error::InvalidUpload(reason) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::INVALID_UPLOAD {
+                    reason: reason.clone(),
+                },
+            ),
+
+            // Model
+            Error::Model(model::Error::ItemNotFound { entity, id }) => (
+                StatusCode::NOT_FOUND,
+                ClientError::ENTITY_NOT_FOUND { entity, id: *id },
+            ),
+            Error::Model(model::Error::ItemInUse { entity, id }) => (
+                StatusCode::CONFLICT,
+                ClientError::ENTITY_IN_USE { entity, id: *id },
+            ),
+            Error::Model(model::Error::AccessDenied { entity, id }) => (
+                StatusCode::FORBIDDEN,
+                ClientError::ACCESS_DENIED { entity, id: *id },
+            ),
+            Error::Model(model::Error::InvalidDateRange { .. }) => {
+                (StatusCode::BAD_REQUEST, ClientError::INVALID_DATE_RANGE)
+            }
+            Error::Model(model::Error::Validation { entity, errors }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::VALIDATION_FAIL {
+                    entity,
+                    errors: errors.clone(),
+                },
+            ),
+            Error::Model(model::Error::PublicUserNotFound { owner_id }) => (
+                StatusCode::NOT_FOUND,
+                ClientError::ENTITY_NOT_FOUND {
+                    entity: "public_user",
+                    id: *owner_id,
+                },
+            ),
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ClientError::SERVICE_ERROR,
@@ -96,7 +140,8 @@ impl Error {
     }
 }
 
-#[derive(Debug, strum_macros::AsRefStr)]
+#[derive(Debug, Serialize, strum_macros::AsRefStr)]
+#[serde(tag = "type", content = "data")]
 #[allow(non_camel_case_types)]
 pub enum ClientError {
     LOGIN_FAIL,
@@ -104,5 +149,15 @@ pub enum ClientError {
     WEAK_PASSWORD,
     USERNAME_ALREADY_TAKEN,
     WRONG_PASSWORD,
+    ENTITY_NOT_FOUND { entity: &'static str, id: i64 },
+    ENTITY_IN_USE { entity: &'static str, id: i64 },
+    ACCESS_DENIED { entity: &'static str, id: i64 },
+    INVALID_DATE_RANGE,
+    MISSING_PARAM { param: &'static str },
+    INVALID_UPLOAD { reason: String },
+    VALIDATION_FAIL {
+        entity: &'static str,
+        errors: Vec<FieldError>,
+    },
     SERVICE_ERROR,
 }
@@ -3,9 +3,21 @@ pub mod routes_login;
 
 pub mod mw_auth;
 pub mod mw_res_map;
+pub mod routes_diary;
+pub mod routes_foods;
+pub mod routes_goals;
+pub mod routes_households;
+pub mod routes_hydration;
+pub mod routes_meals;
+pub mod routes_photos;
+pub mod routes_quick_add;
+pub mod routes_recipes;
+pub mod routes_reports;
+pub mod routes_search;
+pub mod routes_shares;
 pub mod routes_static;
-pub mod routes_tickets;
 pub mod routes_user;
+pub mod routes_weights;
 
 pub const AUTH_TOKEN: &str = "auth-token";
 
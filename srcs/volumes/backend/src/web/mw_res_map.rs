@@ -6,7 +6,7 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde_json::{json, to_value};
 use tracing::debug;
 use uuid::Uuid;
 
@@ -36,9 +36,13 @@ pub async fn main_response_mapper(
     let error_response = client_status_error
         .as_ref()
         .map(|(status_code, client_error)| {
+            let client_error = to_value(client_error).ok();
+            let error_data = client_error.as_ref().and_then(|v| v.get("data"));
+
             let client_error_body = json!({
                 "error": {
-                    "type": client_error.as_ref(),
+                    "type": client_error.as_ref().and_then(|v| v.get("type")),
+                    "data": error_data,
                     "req_uuid": uuid.to_string()
                 }
             });
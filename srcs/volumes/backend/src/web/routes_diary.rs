@@ -0,0 +1,112 @@
+use axum::{
+    extract::{Path, Query, State},
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use std::num::NonZeroU32;
+
+use serde::Deserialize;
+use time::Date;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        alcohol::{AlcoholBmc, AlcoholSummary},
+        meal::{DailySummary, Meal, MealBmc, MealCopy},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+#[derive(Debug, Deserialize)]
+struct DiaryRangeParams {
+    from: Date,
+    to: Date,
+}
+
+#[derive(Debug, Deserialize)]
+struct SummaryParams {
+    kcal_target: Option<NonZeroU32>,
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/diary/", get(list_diary_range_handler))
+        .route("/diary/:date", get(list_diary_day_handler))
+        .route("/diary/:date/summary", get(diary_summary_handler))
+        .route("/diary/:date/alcohol", get(alcohol_summary_handler))
+        .route("/diary/:date/copy", post(copy_diary_day_handler))
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn list_diary_day_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(date): Path<Date>,
+) -> Result<Json<Vec<Meal>>> {
+    debug!("{:<12} - Diary of {date}", "HANDLER");
+
+    let meals = MealBmc::list_by_date(&ctx, &mm, date).await?;
+
+    Ok(Json(meals))
+}
+
+async fn list_diary_range_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Query(params): Query<DiaryRangeParams>,
+) -> Result<Json<Vec<Meal>>> {
+    debug!("{:<12} - Diary from {} to {}", "HANDLER", params.from, params.to);
+
+    let meals = MealBmc::list_by_range(&ctx, &mm, params.from, params.to).await?;
+
+    Ok(Json(meals))
+}
+
+async fn diary_summary_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(date): Path<Date>,
+    Query(params): Query<SummaryParams>,
+) -> Result<Json<DailySummary>> {
+    debug!("{:<12} - Diary summary of {date}", "HANDLER");
+
+    let summary = MealBmc::daily_summary(&ctx, &mm, date, params.kcal_target).await?;
+
+    Ok(Json(summary))
+}
+
+async fn alcohol_summary_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(date): Path<Date>,
+) -> Result<Json<AlcoholSummary>> {
+    debug!("{:<12} - Alcohol of {date}", "HANDLER");
+
+    let summary = AlcoholBmc::summary(&ctx, &mm, date).await?;
+
+    Ok(Json(summary))
+}
+
+async fn copy_diary_day_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(date): Path<Date>,
+    Json(payload): Json<MealCopy>,
+) -> Result<Json<Vec<Meal>>> {
+    debug!("{:<12} - Copy diary {date} to {}", "HANDLER", payload.to);
+
+    let ids = MealBmc::copy_day(&ctx, &mm, date, payload).await?;
+
+    let mut meals = Vec::with_capacity(ids.len());
+    for id in ids {
+        meals.push(MealBmc::get(&ctx, &mm, id).await?);
+    }
+
+    Ok(Json(meals))
+}
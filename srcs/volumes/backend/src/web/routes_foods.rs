@@ -0,0 +1,194 @@
+use axum::{
+    extract::{Path, Query, State},
+    middleware,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        food::{Food, FoodBmc, FoodForCreate, FoodForUpdate},
+        food_portion::{FoodPortion, FoodPortionBmc, FoodPortionForCreate},
+        nutrient::{NutrientInfo, NUTRIENTS},
+        product::{ProductBmc, ProductLookup},
+        unit::{standard_units, Conversion, Quantity, Unit, UnitBmc, UnitInfo},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+#[derive(Debug, Deserialize)]
+struct ConvertParams {
+    amount: Option<f32>,
+    unit: Unit,
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/foods/", post(create_food_handler).get(list_foods_handler))
+        .route(
+            "/foods/:id",
+            get(get_food_handler)
+                .patch(update_food_handler)
+                .delete(delete_food_handler),
+        )
+        .route(
+            "/foods/:id/portions/",
+            post(create_portion_handler).get(list_portions_handler),
+        )
+        .route(
+            "/foods/:id/portions/:portion_id",
+            delete(delete_portion_handler),
+        )
+        .route("/foods/:id/convert", get(convert_handler))
+        .route("/foods/barcode/:code", get(barcode_lookup_handler))
+        .route("/nutrients/", get(list_nutrients_handler))
+        .route("/units/", get(list_units_handler))
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn create_food_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Json(payload): Json<FoodForCreate>,
+) -> Result<Json<Food>> {
+    debug!("{:<12} - Create food", "HANDLER");
+
+    let id = FoodBmc::create(&ctx, &mm, payload).await?;
+    let food = FoodBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(food))
+}
+
+async fn list_foods_handler(State(mm): State<ModelManager>, ctx: Ctx) -> Result<Json<Vec<Food>>> {
+    debug!("{:<12} - List foods", "HANDLER");
+
+    let foods = FoodBmc::list(&ctx, &mm).await?;
+
+    Ok(Json(foods))
+}
+
+async fn get_food_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Food>> {
+    debug!("{:<12} - Get food {id}", "HANDLER");
+
+    let food = FoodBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(food))
+}
+
+async fn update_food_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Json(payload): Json<FoodForUpdate>,
+) -> Result<Json<Food>> {
+    debug!("{:<12} - Update food {id}", "HANDLER");
+
+    FoodBmc::update(&ctx, &mm, id, payload).await?;
+    let food = FoodBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(food))
+}
+
+async fn delete_food_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Food>> {
+    debug!("{:<12} - Delete food {id}", "HANDLER");
+
+    let food = FoodBmc::get(&ctx, &mm, id).await?;
+    FoodBmc::delete(&ctx, &mm, id).await?;
+
+    Ok(Json(food))
+}
+
+async fn create_portion_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Json(payload): Json<FoodPortionForCreate>,
+) -> Result<Json<FoodPortion>> {
+    debug!("{:<12} - Create food {id} portion", "HANDLER");
+
+    let portion_id = FoodPortionBmc::create(&ctx, &mm, id, payload).await?;
+    let portion = FoodPortionBmc::get(&ctx, &mm, id, portion_id).await?;
+
+    Ok(Json(portion))
+}
+
+async fn list_portions_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<FoodPortion>>> {
+    debug!("{:<12} - List food {id} portions", "HANDLER");
+
+    let portions = FoodPortionBmc::list(&ctx, &mm, id).await?;
+
+    Ok(Json(portions))
+}
+
+async fn delete_portion_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path((id, portion_id)): Path<(i64, i64)>,
+) -> Result<Json<FoodPortion>> {
+    debug!("{:<12} - Delete food {id} portion {portion_id}", "HANDLER");
+
+    let portion = FoodPortionBmc::get(&ctx, &mm, id, portion_id).await?;
+    FoodPortionBmc::delete(&ctx, &mm, id, portion_id).await?;
+
+    Ok(Json(portion))
+}
+
+async fn convert_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Query(params): Query<ConvertParams>,
+) -> Result<Json<Conversion>> {
+    debug!("{:<12} - Convert food {id} quantity", "HANDLER");
+
+    let conversion =
+        UnitBmc::convert(&ctx, &mm, id, params.amount.unwrap_or(1.), params.unit).await?;
+
+    Ok(Json(conversion))
+}
+
+async fn barcode_lookup_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(code): Path<String>,
+    Query(quantity): Query<Quantity>,
+) -> Result<Json<ProductLookup>> {
+    debug!("{:<12} - Lookup barcode {code}", "HANDLER");
+
+    let lookup = ProductBmc::lookup(&ctx, &mm, &code, &quantity).await?;
+
+    Ok(Json(lookup))
+}
+
+/// Extended nutrients meals can declare, with their units.
+async fn list_nutrients_handler() -> Json<&'static [NutrientInfo]> {
+    debug!("{:<12} - List nutrients", "HANDLER");
+
+    Json(NUTRIENTS)
+}
+
+/// Units convertible for every food, portions depend on the food.
+async fn list_units_handler() -> Json<Vec<UnitInfo>> {
+    debug!("{:<12} - List units", "HANDLER");
+
+    Json(standard_units())
+}
@@ -0,0 +1,80 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::get,
+    Json, Router,
+};
+use time::Date;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        goal::{GoalBmc, GoalDayProgress, GoalForSet, GoalWithTargets},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route(
+            "/goal/",
+            get(get_goal_handler)
+                .put(set_goal_handler)
+                .delete(delete_goal_handler),
+        )
+        .route("/diary/:date/goal", get(goal_day_progress_handler))
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn set_goal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Json(payload): Json<GoalForSet>,
+) -> Result<Json<GoalWithTargets>> {
+    debug!("{:<12} - Set goal", "HANDLER");
+
+    GoalBmc::set(&ctx, &mm, payload).await?;
+    let goal = GoalBmc::get_with_targets(&ctx, &mm).await?;
+
+    Ok(Json(goal))
+}
+
+async fn get_goal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+) -> Result<Json<GoalWithTargets>> {
+    debug!("{:<12} - Get goal", "HANDLER");
+
+    let goal = GoalBmc::get_with_targets(&ctx, &mm).await?;
+
+    Ok(Json(goal))
+}
+
+async fn delete_goal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+) -> Result<Json<GoalWithTargets>> {
+    debug!("{:<12} - Delete goal", "HANDLER");
+
+    let goal = GoalBmc::get_with_targets(&ctx, &mm).await?;
+    GoalBmc::delete(&ctx, &mm).await?;
+
+    Ok(Json(goal))
+}
+
+async fn goal_day_progress_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(date): Path<Date>,
+) -> Result<Json<GoalDayProgress>> {
+    debug!("{:<12} - Goal progress of {date}", "HANDLER");
+
+    let progress = GoalBmc::day_progress(&ctx, &mm, date).await?;
+
+    Ok(Json(progress))
+}
@@ -0,0 +1,156 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{delete, get},
+    Json, Router,
+};
+use serde::Serialize;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        household::{
+            Household, HouseholdBmc, HouseholdForCreate, HouseholdMember, HouseholdMemberForAdd,
+        },
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+#[derive(Serialize)]
+struct HouseholdWithMembers {
+    #[serde(flatten)]
+    household: Household,
+    members: Vec<HouseholdMember>,
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route(
+            "/households/",
+            get(list_households_handler).post(create_household_handler),
+        )
+        .route(
+            "/households/:id",
+            get(get_household_handler).delete(delete_household_handler),
+        )
+        .route(
+            "/households/:id/members/",
+            get(list_members_handler).post(add_member_handler),
+        )
+        .route(
+            "/households/:id/members/:user_id",
+            delete(remove_member_handler),
+        )
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn household_with_members(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    id: i64,
+) -> Result<HouseholdWithMembers> {
+    let household = HouseholdBmc::get(ctx, mm, id).await?;
+    let members = HouseholdBmc::list_members(ctx, mm, id).await?;
+
+    Ok(HouseholdWithMembers { household, members })
+}
+
+async fn create_household_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Json(payload): Json<HouseholdForCreate>,
+) -> Result<Json<HouseholdWithMembers>> {
+    debug!("{:<12} - Create household", "HANDLER");
+
+    let id = HouseholdBmc::create(&ctx, &mm, payload).await?;
+
+    Ok(Json(household_with_members(&ctx, &mm, id).await?))
+}
+
+async fn list_households_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+) -> Result<Json<Vec<Household>>> {
+    debug!("{:<12} - List households", "HANDLER");
+
+    let households = HouseholdBmc::list(&ctx, &mm).await?;
+
+    Ok(Json(households))
+}
+
+async fn get_household_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<HouseholdWithMembers>> {
+    debug!("{:<12} - Get household {id}", "HANDLER");
+
+    Ok(Json(household_with_members(&ctx, &mm, id).await?))
+}
+
+async fn delete_household_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Household>> {
+    debug!("{:<12} - Delete household {id}", "HANDLER");
+
+    let household = HouseholdBmc::get(&ctx, &mm, id).await?;
+    HouseholdBmc::delete(&ctx, &mm, id).await?;
+
+    Ok(Json(household))
+}
+
+async fn list_members_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<HouseholdMember>>> {
+    debug!("{:<12} - List household {id} members", "HANDLER");
+
+    let members = HouseholdBmc::list_members(&ctx, &mm, id).await?;
+
+    Ok(Json(members))
+}
+
+async fn add_member_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Json(payload): Json<HouseholdMemberForAdd>,
+) -> Result<Json<Vec<HouseholdMember>>> {
+    debug!("{:<12} - Add member to household {id}", "HANDLER");
+
+    HouseholdBmc::add_member(&ctx, &mm, id, payload).await?;
+    let members = HouseholdBmc::list_members(&ctx, &mm, id).await?;
+
+    Ok(Json(members))
+}
+
+/// Also used by a member to leave the household.
+async fn remove_member_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path((id, user_id)): Path<(i64, i64)>,
+) -> Result<Json<Vec<HouseholdMember>>> {
+    debug!(
+        "{:<12} - Remove member {user_id} of household {id}",
+        "HANDLER"
+    );
+
+    HouseholdBmc::remove_member(&ctx, &mm, id, user_id).await?;
+
+    // A member who left can't see the household anymore.
+    let members = if user_id == ctx.user_id() {
+        Vec::new()
+    } else {
+        HouseholdBmc::list_members(&ctx, &mm, id).await?
+    };
+
+    Ok(Json(members))
+}
@@ -0,0 +1,94 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use time::Date;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        hydration::{HydrationBmc, HydrationSummary, WaterLog, WaterLogForCreate},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/water/", post(create_water_log_handler))
+        .route(
+            "/water/:id",
+            get(get_water_log_handler).delete(delete_water_log_handler),
+        )
+        .route("/diary/:date/water", get(list_water_day_handler))
+        .route("/diary/:date/hydration", get(hydration_summary_handler))
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn create_water_log_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Json(payload): Json<WaterLogForCreate>,
+) -> Result<Json<WaterLog>> {
+    debug!("{:<12} - Log water", "HANDLER");
+
+    let id = HydrationBmc::create(&ctx, &mm, payload).await?;
+    let log = HydrationBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(log))
+}
+
+async fn get_water_log_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<WaterLog>> {
+    debug!("{:<12} - Get water log {id}", "HANDLER");
+
+    let log = HydrationBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(log))
+}
+
+async fn delete_water_log_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<WaterLog>> {
+    debug!("{:<12} - Delete water log {id}", "HANDLER");
+
+    let log = HydrationBmc::get(&ctx, &mm, id).await?;
+    HydrationBmc::delete(&ctx, &mm, id).await?;
+
+    Ok(Json(log))
+}
+
+async fn list_water_day_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(date): Path<Date>,
+) -> Result<Json<Vec<WaterLog>>> {
+    debug!("{:<12} - Water of {date}", "HANDLER");
+
+    let logs = HydrationBmc::list_by_date(&ctx, &mm, date).await?;
+
+    Ok(Json(logs))
+}
+
+async fn hydration_summary_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(date): Path<Date>,
+) -> Result<Json<HydrationSummary>> {
+    debug!("{:<12} - Hydration summary of {date}", "HANDLER");
+
+    let summary = HydrationBmc::daily_summary(&ctx, &mm, date).await?;
+
+    Ok(Json(summary))
+}
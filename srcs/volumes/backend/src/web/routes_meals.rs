@@ -0,0 +1,267 @@
+use axum::{
+    extract::{Path, Query, State},
+    middleware,
+    routing::{delete, get, patch, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use time::Date;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        list_options::{Page, SortOrder},
+        meal::{
+            Meal, MealBmc, MealFilter, MealForCreate, MealForUpdate, MealListOptions, MealRelog,
+            MealSort, TrashedMeal,
+        },
+        meal_entry::{MealEntry, MealEntryBmc, MealEntryForCreate, MealEntryForUpdate},
+        share::SharePermission,
+        tag::{TagBmc, TagUsage},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+#[derive(Debug, Deserialize)]
+struct MealListParams {
+    limit: Option<i64>,
+    cursor: Option<String>,
+    #[serde(default)]
+    sort: MealSort,
+    #[serde(default)]
+    order: SortOrder,
+    name: Option<String>,
+    kcal_min: Option<i32>,
+    kcal_max: Option<i32>,
+    from: Option<Date>,
+    to: Option<Date>,
+    tag: Option<String>,
+}
+
+impl From<MealListParams> for MealListOptions {
+    fn from(params: MealListParams) -> Self {
+        MealListOptions {
+            limit: params.limit,
+            cursor: params.cursor,
+            sort: params.sort,
+            order: params.order,
+            filter: MealFilter {
+                name_contains: params.name,
+                kcal_min: params.kcal_min,
+                kcal_max: params.kcal_max,
+                from: params.from,
+                to: params.to,
+                tag: params.tag,
+            },
+        }
+    }
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/meals/", post(create_meal_handler).get(list_meals_handler))
+        .route("/meals/tags", get(list_tags_handler))
+        .route("/meals/trash/", get(list_trash_handler))
+        .route("/meals/trash/:id", delete(purge_meal_handler))
+        .route(
+            "/meals/:id",
+            get(get_meal_handler)
+                .patch(update_meal_handler)
+                .delete(delete_meal_handler),
+        )
+        .route("/meals/:id/relog", post(relog_meal_handler))
+        .route("/meals/:id/restore", post(restore_meal_handler))
+        .route(
+            "/meals/:id/entries/",
+            post(create_meal_entry_handler).get(list_meal_entries_handler),
+        )
+        .route(
+            "/meals/:id/entries/:entry_id",
+            patch(update_meal_entry_handler).delete(delete_meal_entry_handler),
+        )
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn create_meal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Json(payload): Json<MealForCreate>,
+) -> Result<Json<Meal>> {
+    debug!("{:<12} - Create meal", "HANDLER");
+
+    let id = MealBmc::create(&ctx, &mm, payload).await?;
+    let meal = MealBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(meal))
+}
+
+async fn list_meals_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Query(params): Query<MealListParams>,
+) -> Result<Json<Page<Meal>>> {
+    debug!("{:<12} - List meals", "HANDLER");
+
+    let meals = MealBmc::list(&ctx, &mm, params.into()).await?;
+
+    Ok(Json(meals))
+}
+
+async fn list_tags_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+) -> Result<Json<Vec<TagUsage>>> {
+    debug!("{:<12} - List meal tags", "HANDLER");
+
+    let tags = TagBmc::list(&ctx, &mm).await?;
+
+    Ok(Json(tags))
+}
+
+async fn get_meal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Meal>> {
+    debug!("{:<12} - Get meal {id}", "HANDLER");
+
+    let meal = MealBmc::get_shared(&ctx, &mm, id, SharePermission::Read).await?;
+
+    Ok(Json(meal))
+}
+
+async fn update_meal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Json(payload): Json<MealForUpdate>,
+) -> Result<Json<Meal>> {
+    debug!("{:<12} - Update meal {id}", "HANDLER");
+
+    MealBmc::update(&ctx, &mm, id, payload).await?;
+    let meal = MealBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(meal))
+}
+
+async fn delete_meal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Meal>> {
+    debug!("{:<12} - Delete meal {id}", "HANDLER");
+
+    let meal = MealBmc::get(&ctx, &mm, id).await?;
+    MealBmc::delete(&ctx, &mm, id).await?;
+
+    Ok(Json(meal))
+}
+
+async fn list_trash_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+) -> Result<Json<Vec<TrashedMeal>>> {
+    debug!("{:<12} - List trashed meals", "HANDLER");
+
+    let meals = MealBmc::list_trash(&ctx, &mm).await?;
+
+    Ok(Json(meals))
+}
+
+async fn restore_meal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Meal>> {
+    debug!("{:<12} - Restore meal {id}", "HANDLER");
+
+    MealBmc::restore(&ctx, &mm, id).await?;
+    let meal = MealBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(meal))
+}
+
+async fn purge_meal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<TrashedMeal>> {
+    debug!("{:<12} - Purge meal {id}", "HANDLER");
+
+    let meal = MealBmc::get_trashed(&ctx, &mm, id).await?;
+    MealBmc::purge(&ctx, &mm, id).await?;
+
+    Ok(Json(meal))
+}
+
+async fn relog_meal_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Json(payload): Json<MealRelog>,
+) -> Result<Json<Meal>> {
+    debug!("{:<12} - Relog meal {id}", "HANDLER");
+
+    let new_id = MealBmc::relog(&ctx, &mm, id, payload).await?;
+    let meal = MealBmc::get(&ctx, &mm, new_id).await?;
+
+    Ok(Json(meal))
+}
+
+async fn create_meal_entry_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(meal_id): Path<i64>,
+    Json(payload): Json<MealEntryForCreate>,
+) -> Result<Json<MealEntry>> {
+    debug!("{:<12} - Create entry for meal {meal_id}", "HANDLER");
+
+    let id = MealEntryBmc::create(&ctx, &mm, meal_id, payload).await?;
+    let entry = MealEntryBmc::get(&ctx, &mm, meal_id, id).await?;
+
+    Ok(Json(entry))
+}
+
+async fn list_meal_entries_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(meal_id): Path<i64>,
+) -> Result<Json<Vec<MealEntry>>> {
+    debug!("{:<12} - List entries of meal {meal_id}", "HANDLER");
+
+    let entries = MealEntryBmc::list(&ctx, &mm, meal_id).await?;
+
+    Ok(Json(entries))
+}
+
+async fn update_meal_entry_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path((meal_id, id)): Path<(i64, i64)>,
+    Json(payload): Json<MealEntryForUpdate>,
+) -> Result<Json<MealEntry>> {
+    debug!("{:<12} - Update entry {id} of meal {meal_id}", "HANDLER");
+
+    MealEntryBmc::update(&ctx, &mm, meal_id, id, payload).await?;
+    let entry = MealEntryBmc::get(&ctx, &mm, meal_id, id).await?;
+
+    Ok(Json(entry))
+}
+
+async fn delete_meal_entry_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path((meal_id, id)): Path<(i64, i64)>,
+) -> Result<Json<MealEntry>> {
+    debug!("{:<12} - Delete entry {id} of meal {meal_id}", "HANDLER");
+
+    let entry = MealEntryBmc::get(&ctx, &mm, meal_id, id).await?;
+    MealEntryBmc::delete(&ctx, &mm, meal_id, id).await?;
+
+    Ok(Json(entry))
+}
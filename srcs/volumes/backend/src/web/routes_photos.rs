@@ -0,0 +1,154 @@
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, Path, State},
+    http::header,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        photo::{MealPhoto, MealPhotoBmc, PhotoVariant, PHOTO_MAX_BYTES},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::{Error, Result};
+
+/// Photos sent in a single upload.
+const PHOTO_UPLOAD_MAX_FILES: usize = 4;
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route(
+            "/meals/:id/photos/",
+            get(list_photos_handler).post(upload_photos_handler),
+        )
+        .route(
+            "/meals/:id/photos/:photo_id",
+            get(get_photo_handler).delete(delete_photo_handler),
+        )
+        .route(
+            "/meals/:id/photos/:photo_id/thumbnail",
+            get(get_thumbnail_handler),
+        )
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .layer(DefaultBodyLimit::max(
+            PHOTO_MAX_BYTES * PHOTO_UPLOAD_MAX_FILES,
+        ))
+        .with_state(mm)
+}
+
+/// Every file field of the form is attached, whatever its name.
+async fn upload_photos_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(meal_id): Path<i64>,
+    mut multipart: Multipart,
+) -> Result<Json<Vec<MealPhoto>>> {
+    debug!("{:<12} - Upload photos of meal {meal_id}", "HANDLER");
+
+    let mut uploads = Vec::new();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| Error::InvalidUpload(err.body_text()))?
+    {
+        if field.file_name().is_none() {
+            continue;
+        }
+        if uploads.len() == PHOTO_UPLOAD_MAX_FILES {
+            return Err(Error::InvalidUpload(format!(
+                "at most {PHOTO_UPLOAD_MAX_FILES} photos per upload"
+            )));
+        }
+
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|err| Error::InvalidUpload(err.body_text()))?;
+        uploads.push(bytes.to_vec());
+    }
+
+    if uploads.is_empty() {
+        return Err(Error::InvalidUpload("no photo in the upload".to_string()));
+    }
+
+    let mut photos = Vec::new();
+    for id in MealPhotoBmc::create(&ctx, &mm, meal_id, uploads).await? {
+        photos.push(MealPhotoBmc::get(&ctx, &mm, meal_id, id).await?);
+    }
+
+    Ok(Json(photos))
+}
+
+async fn list_photos_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(meal_id): Path<i64>,
+) -> Result<Json<Vec<MealPhoto>>> {
+    debug!("{:<12} - List photos of meal {meal_id}", "HANDLER");
+
+    let photos = MealPhotoBmc::list(&ctx, &mm, meal_id).await?;
+
+    Ok(Json(photos))
+}
+
+async fn photo_response(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    meal_id: i64,
+    id: i64,
+    variant: PhotoVariant,
+) -> Result<Response> {
+    let (photo, bytes) = MealPhotoBmc::read(ctx, mm, meal_id, id, variant).await?;
+
+    let headers = [
+        (
+            header::CONTENT_TYPE,
+            photo.content_type(variant).to_string(),
+        ),
+        // Only ever served to the owner, shared caches must not keep it.
+        (header::CACHE_CONTROL, "private, max-age=86400".to_string()),
+    ];
+
+    Ok((headers, bytes).into_response())
+}
+
+async fn get_photo_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path((meal_id, id)): Path<(i64, i64)>,
+) -> Result<Response> {
+    debug!("{:<12} - Get photo {id} of meal {meal_id}", "HANDLER");
+
+    photo_response(&ctx, &mm, meal_id, id, PhotoVariant::Original).await
+}
+
+async fn get_thumbnail_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path((meal_id, id)): Path<(i64, i64)>,
+) -> Result<Response> {
+    debug!("{:<12} - Get thumbnail {id} of meal {meal_id}", "HANDLER");
+
+    photo_response(&ctx, &mm, meal_id, id, PhotoVariant::Thumbnail).await
+}
+
+async fn delete_photo_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path((meal_id, id)): Path<(i64, i64)>,
+) -> Result<Json<MealPhoto>> {
+    debug!("{:<12} - Delete photo {id} of meal {meal_id}", "HANDLER");
+
+    let photo = MealPhotoBmc::get(&ctx, &mm, meal_id, id).await?;
+    MealPhotoBmc::delete(&ctx, &mm, meal_id, id).await?;
+
+    Ok(Json(photo))
+}
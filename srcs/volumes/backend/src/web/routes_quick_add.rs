@@ -0,0 +1,110 @@
+use axum::{
+    extract::{Path, Query, State},
+    middleware,
+    routing::{get, put},
+    Json, Router,
+};
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        favorite::FavoriteBmc,
+        meal::{Meal, MealBmc},
+        quick_add::{FrequentFood, FrequentMeal, QuickAddBmc},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+struct QuickAddParams {
+    limit: Option<u32>,
+}
+
+impl QuickAddParams {
+    fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT).into()
+    }
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/quick-add/meals", get(frequent_meals_handler))
+        .route("/quick-add/foods", get(frequent_foods_handler))
+        .route("/favorites/", get(list_favorites_handler))
+        .route(
+            "/favorites/:meal_id",
+            put(add_favorite_handler).delete(remove_favorite_handler),
+        )
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn frequent_meals_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Query(params): Query<QuickAddParams>,
+) -> Result<Json<Vec<FrequentMeal>>> {
+    debug!("{:<12} - List frequent meals", "HANDLER");
+
+    let meals = QuickAddBmc::meals(&ctx, &mm, params.limit()).await?;
+
+    Ok(Json(meals))
+}
+
+async fn frequent_foods_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Query(params): Query<QuickAddParams>,
+) -> Result<Json<Vec<FrequentFood>>> {
+    debug!("{:<12} - List frequent foods", "HANDLER");
+
+    let foods = QuickAddBmc::foods(&ctx, &mm, params.limit()).await?;
+
+    Ok(Json(foods))
+}
+
+async fn list_favorites_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+) -> Result<Json<Vec<Meal>>> {
+    debug!("{:<12} - List favorites", "HANDLER");
+
+    let meals = FavoriteBmc::list(&ctx, &mm).await?;
+
+    Ok(Json(meals))
+}
+
+async fn add_favorite_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(meal_id): Path<i64>,
+) -> Result<Json<Meal>> {
+    debug!("{:<12} - Add favorite {meal_id}", "HANDLER");
+
+    FavoriteBmc::add(&ctx, &mm, meal_id).await?;
+    let meal = MealBmc::get(&ctx, &mm, meal_id).await?;
+
+    Ok(Json(meal))
+}
+
+async fn remove_favorite_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(meal_id): Path<i64>,
+) -> Result<Json<Meal>> {
+    debug!("{:<12} - Remove favorite {meal_id}", "HANDLER");
+
+    let meal = MealBmc::get(&ctx, &mm, meal_id).await?;
+    FavoriteBmc::remove(&ctx, &mm, meal_id).await?;
+
+    Ok(Json(meal))
+}
@@ -0,0 +1,208 @@
+use axum::{
+    extract::{Path, Query, State},
+    middleware,
+    routing::{get, patch, post},
+    Json, Router,
+};
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        meal::{Meal, MealBmc},
+        recipe::{
+            Recipe, RecipeBmc, RecipeForCreate, RecipeForUpdate, RecipeIngredient,
+            RecipeIngredientForCreate, RecipeIngredientForUpdate, RecipeNutrition, RecipePortion,
+        },
+        share::SharePermission,
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+#[derive(Debug, Deserialize)]
+struct IngredientsParams {
+    servings: Option<f32>,
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route(
+            "/recipes/",
+            post(create_recipe_handler).get(list_recipes_handler),
+        )
+        .route(
+            "/recipes/:id",
+            get(get_recipe_handler)
+                .patch(update_recipe_handler)
+                .delete(delete_recipe_handler),
+        )
+        .route("/recipes/:id/nutrition", get(recipe_nutrition_handler))
+        .route("/recipes/:id/log", post(log_recipe_handler))
+        .route(
+            "/recipes/:id/ingredients/",
+            post(add_ingredient_handler).get(list_ingredients_handler),
+        )
+        .route(
+            "/recipes/:id/ingredients/:ingredient_id",
+            patch(update_ingredient_handler).delete(remove_ingredient_handler),
+        )
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn create_recipe_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Json(payload): Json<RecipeForCreate>,
+) -> Result<Json<Recipe>> {
+    debug!("{:<12} - Create recipe", "HANDLER");
+
+    let id = RecipeBmc::create(&ctx, &mm, payload).await?;
+    let recipe = RecipeBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(recipe))
+}
+
+async fn list_recipes_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+) -> Result<Json<Vec<Recipe>>> {
+    debug!("{:<12} - List recipes", "HANDLER");
+
+    let recipes = RecipeBmc::list(&ctx, &mm).await?;
+
+    Ok(Json(recipes))
+}
+
+async fn get_recipe_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Recipe>> {
+    debug!("{:<12} - Get recipe {id}", "HANDLER");
+
+    let recipe = RecipeBmc::get_shared(&ctx, &mm, id, SharePermission::Read).await?;
+
+    Ok(Json(recipe))
+}
+
+async fn update_recipe_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Json(payload): Json<RecipeForUpdate>,
+) -> Result<Json<Recipe>> {
+    debug!("{:<12} - Update recipe {id}", "HANDLER");
+
+    RecipeBmc::update(&ctx, &mm, id, payload).await?;
+    let recipe = RecipeBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(recipe))
+}
+
+async fn delete_recipe_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<Recipe>> {
+    debug!("{:<12} - Delete recipe {id}", "HANDLER");
+
+    let recipe = RecipeBmc::get(&ctx, &mm, id).await?;
+    RecipeBmc::delete(&ctx, &mm, id).await?;
+
+    Ok(Json(recipe))
+}
+
+async fn recipe_nutrition_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<RecipeNutrition>> {
+    debug!("{:<12} - Get recipe {id} nutrition", "HANDLER");
+
+    let nutrition = RecipeBmc::nutrition(&ctx, &mm, id).await?;
+
+    Ok(Json(nutrition))
+}
+
+async fn log_recipe_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Json(payload): Json<RecipePortion>,
+) -> Result<Json<Meal>> {
+    debug!("{:<12} - Log recipe {id}", "HANDLER");
+
+    let meal_id = RecipeBmc::log_portion(&ctx, &mm, id, payload).await?;
+    let meal = MealBmc::get(&ctx, &mm, meal_id).await?;
+
+    Ok(Json(meal))
+}
+
+async fn add_ingredient_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Json(payload): Json<RecipeIngredientForCreate>,
+) -> Result<Json<RecipeIngredient>> {
+    debug!("{:<12} - Add ingredient to recipe {id}", "HANDLER");
+
+    let ingredient_id = RecipeBmc::add_ingredient(&ctx, &mm, id, payload).await?;
+    let ingredient = RecipeBmc::get_ingredient(&ctx, &mm, id, ingredient_id).await?;
+
+    Ok(Json(ingredient))
+}
+
+async fn list_ingredients_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Query(params): Query<IngredientsParams>,
+) -> Result<Json<Vec<RecipeIngredient>>> {
+    debug!("{:<12} - List recipe {id} ingredients", "HANDLER");
+
+    let ingredients = match params.servings {
+        Some(servings) => RecipeBmc::scaled_ingredients(&ctx, &mm, id, servings).await?,
+        None => RecipeBmc::list_ingredients(&ctx, &mm, id).await?,
+    };
+
+    Ok(Json(ingredients))
+}
+
+async fn update_ingredient_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path((id, ingredient_id)): Path<(i64, i64)>,
+    Json(payload): Json<RecipeIngredientForUpdate>,
+) -> Result<Json<RecipeIngredient>> {
+    debug!(
+        "{:<12} - Update recipe {id} ingredient {ingredient_id}",
+        "HANDLER"
+    );
+
+    RecipeBmc::update_ingredient(&ctx, &mm, id, ingredient_id, payload).await?;
+    let ingredient = RecipeBmc::get_ingredient(&ctx, &mm, id, ingredient_id).await?;
+
+    Ok(Json(ingredient))
+}
+
+async fn remove_ingredient_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path((id, ingredient_id)): Path<(i64, i64)>,
+) -> Result<Json<RecipeIngredient>> {
+    debug!(
+        "{:<12} - Remove recipe {id} ingredient {ingredient_id}",
+        "HANDLER"
+    );
+
+    let ingredient = RecipeBmc::get_ingredient(&ctx, &mm, id, ingredient_id).await?;
+    RecipeBmc::remove_ingredient(&ctx, &mm, id, ingredient_id).await?;
+
+    Ok(Json(ingredient))
+}
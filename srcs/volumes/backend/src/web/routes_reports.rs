@@ -0,0 +1,80 @@
+use std::num::NonZeroU32;
+
+use axum::{
+    extract::{Query, State},
+    middleware,
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+use time::Date;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        report::{AdherenceTarget, NutritionReport, ReportBmc, ReportRange},
+        ModelManager,
+    },
+    utils::time_utils::now_utc,
+    web::mw_auth::mw_require_auth,
+};
+
+use super::{Error, Result};
+
+const DEFAULT_TOLERANCE_PCT: u32 = 10;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReportPeriod {
+    Week,
+    Month,
+    Custom,
+}
+
+/// `date` picks the week or month, defaults to today.
+/// `from` and `to` are required for custom periods.
+#[derive(Debug, Deserialize)]
+struct ReportParams {
+    period: ReportPeriod,
+    date: Option<Date>,
+    from: Option<Date>,
+    to: Option<Date>,
+    kcal_target: Option<NonZeroU32>,
+    tolerance_pct: Option<u32>,
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/reports/nutrition", get(nutrition_report_handler))
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn nutrition_report_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Query(params): Query<ReportParams>,
+) -> Result<Json<NutritionReport>> {
+    debug!("{:<12} - Nutrition report {params:?}", "HANDLER");
+
+    let date = params.date.unwrap_or_else(|| now_utc().date());
+
+    let range = match params.period {
+        ReportPeriod::Week => ReportRange::Week(date),
+        ReportPeriod::Month => ReportRange::Month(date),
+        ReportPeriod::Custom => ReportRange::Custom {
+            from: params.from.ok_or(Error::MissingQueryParam { param: "from" })?,
+            to: params.to.ok_or(Error::MissingQueryParam { param: "to" })?,
+        },
+    };
+
+    let target = params.kcal_target.map(|kcal| AdherenceTarget {
+        kcal,
+        tolerance_pct: params.tolerance_pct.unwrap_or(DEFAULT_TOLERANCE_PCT),
+    });
+
+    let report = ReportBmc::nutrition(&ctx, &mm, range, target).await?;
+
+    Ok(Json(report))
+}
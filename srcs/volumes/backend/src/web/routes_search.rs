@@ -0,0 +1,67 @@
+use axum::{
+    extract::{Query, State},
+    middleware,
+    routing::get,
+    Json, Router,
+};
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        search::{SearchBmc, SearchKind, SearchQuery, SearchResults},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    q: String,
+    kind: Option<SearchKind>,
+    tag: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+impl From<SearchParams> for SearchQuery {
+    fn from(params: SearchParams) -> Self {
+        SearchQuery {
+            text: params.q,
+            kind: params.kind,
+            tag: params.tag,
+            limit: params
+                .limit
+                .unwrap_or(DEFAULT_LIMIT)
+                .clamp(1, MAX_LIMIT)
+                .into(),
+            offset: params.offset.unwrap_or_default().into(),
+        }
+    }
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/search/", get(search_handler))
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn search_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<SearchResults>> {
+    debug!("{:<12} - Search {:?}", "HANDLER", params.q);
+
+    let results = SearchBmc::search(&ctx, &mm, params.into()).await?;
+
+    Ok(Json(results))
+}
@@ -0,0 +1,95 @@
+use axum::{
+    extract::{Path, Query, State},
+    middleware,
+    routing::{delete, get},
+    Json, Router,
+};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        share::{ShareBmc, ShareGrant, ShareGrantForCreate, ShareResource, SharedItem},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::{Error, Result};
+
+/// Grants on a single resource when given.
+#[derive(Debug, Deserialize)]
+struct ShareListParams {
+    resource: Option<ShareResource>,
+    resource_id: Option<i64>,
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route(
+            "/shares/",
+            get(list_shares_handler).post(create_share_handler),
+        )
+        .route("/shares/:id", delete(delete_share_handler))
+        .route("/shared/", get(shared_with_me_handler))
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn create_share_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Json(payload): Json<ShareGrantForCreate>,
+) -> Result<Json<ShareGrant>> {
+    debug!("{:<12} - Create share", "HANDLER");
+
+    let id = ShareBmc::create(&ctx, &mm, payload).await?;
+    let grant = ShareBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(grant))
+}
+
+async fn list_shares_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Query(params): Query<ShareListParams>,
+) -> Result<Json<Vec<ShareGrant>>> {
+    debug!("{:<12} - List shares", "HANDLER");
+
+    let grants = match params.resource {
+        Some(resource) => {
+            let resource_id = params.resource_id.ok_or(Error::MissingQueryParam {
+                param: "resource_id",
+            })?;
+            ShareBmc::list_for(&ctx, &mm, resource, resource_id).await?
+        }
+        None => ShareBmc::list(&ctx, &mm).await?,
+    };
+
+    Ok(Json(grants))
+}
+
+async fn delete_share_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<ShareGrant>> {
+    debug!("{:<12} - Delete share {id}", "HANDLER");
+
+    let grant = ShareBmc::get(&ctx, &mm, id).await?;
+    ShareBmc::delete(&ctx, &mm, id).await?;
+
+    Ok(Json(grant))
+}
+
+async fn shared_with_me_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+) -> Result<Json<Vec<SharedItem>>> {
+    debug!("{:<12} - List shared with me", "HANDLER");
+
+    let items = ShareBmc::shared_with_me(&ctx, &mm).await?;
+
+    Ok(Json(items))
+}
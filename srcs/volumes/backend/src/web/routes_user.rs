@@ -13,7 +13,7 @@ use crate::{
     crypt,
     ctx::Ctx,
     model::{
-        user::{public_user::{PublicUser, PublicUserBmc, PublicUserForUpdate}, user::{User, UserBmc, UserForLogin, UserForNewPwd}, FullUser, FullUserBmc, FullUserForCreate},
+        user::{public_user::{PublicUser, PublicUserBmc, PublicUserForUpdate}, user::{User, UserBmc, UserForLogin, UserForNewPwd}, FullUser, FullUserBmc, FullUserForCreate, FullUserWithEnergy},
         ModelManager,
     },
     utils::password::check_password_safety,
@@ -68,9 +68,9 @@ async fn create_user_handler(
 
 async fn get_user_handler(
     State(mm): State<ModelManager>,
-    ctx: Ctx) -> Result<Json<FullUser>> {
+    ctx: Ctx) -> Result<Json<FullUserWithEnergy>> {
     let full_user = FullUserBmc::get(&ctx, &mm, ctx.user_id()).await?;
-    Ok(Json(full_user))
+    Ok(Json(full_user.into()))
 }
 
 async fn update_user_password_handler(
@@ -118,7 +118,7 @@ async fn update_public_user_handler(
     Json(payload): Json<PublicUserForUpdate>
     ) -> Result<Json<PublicUser>> {
     PublicUserBmc::update(&ctx, &mm, payload).await?;
-    let updated_user = PublicUserBmc::get(&ctx, &mm, ctx.user_id())
+    let updated_user = PublicUserBmc::first_by_owner(&ctx, &mm)
         .await?;
 
     Ok(Json(updated_user))
@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Path, Query, State},
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use time::Date;
+use tracing::debug;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        weight::{WeightBmc, WeightLog, WeightLogForCreate, WeightLogForUpdate},
+        ModelManager,
+    },
+    web::mw_auth::mw_require_auth,
+};
+
+use super::Result;
+
+#[derive(Debug, Deserialize)]
+struct WeightRangeParams {
+    from: Option<Date>,
+    to: Option<Date>,
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route(
+            "/weights/",
+            post(create_weight_handler).get(list_weights_handler),
+        )
+        .route(
+            "/weights/:id",
+            get(get_weight_handler)
+                .patch(update_weight_handler)
+                .delete(delete_weight_handler),
+        )
+        .route_layer(middleware::from_fn(mw_require_auth))
+        .with_state(mm)
+}
+
+async fn create_weight_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Json(payload): Json<WeightLogForCreate>,
+) -> Result<Json<WeightLog>> {
+    debug!("{:<12} - Log weight", "HANDLER");
+
+    let id = WeightBmc::create(&ctx, &mm, payload).await?;
+    let log = WeightBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(log))
+}
+
+async fn list_weights_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Query(params): Query<WeightRangeParams>,
+) -> Result<Json<Vec<WeightLog>>> {
+    debug!("{:<12} - List weights", "HANDLER");
+
+    let logs = WeightBmc::list(&ctx, &mm, params.from, params.to).await?;
+
+    Ok(Json(logs))
+}
+
+async fn get_weight_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<WeightLog>> {
+    debug!("{:<12} - Get weight {id}", "HANDLER");
+
+    let log = WeightBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(log))
+}
+
+async fn update_weight_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+    Json(payload): Json<WeightLogForUpdate>,
+) -> Result<Json<WeightLog>> {
+    debug!("{:<12} - Update weight {id}", "HANDLER");
+
+    WeightBmc::update(&ctx, &mm, id, payload).await?;
+    let log = WeightBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Json(log))
+}
+
+async fn delete_weight_handler(
+    State(mm): State<ModelManager>,
+    ctx: Ctx,
+    Path(id): Path<i64>,
+) -> Result<Json<WeightLog>> {
+    debug!("{:<12} - Delete weight {id}", "HANDLER");
+
+    let log = WeightBmc::get(&ctx, &mm, id).await?;
+    WeightBmc::delete(&ctx, &mm, id).await?;
+
+    Ok(Json(log))
+}